@@ -0,0 +1,18 @@
+use std::process::Command;
+
+// The commit kmgrid is built from, for `kmgrid --version`. Builds from a source tarball have none.
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_owned())
+        .filter(|hash| !hash.is_empty());
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=KMGRID_GIT_HASH={hash}");
+    }
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
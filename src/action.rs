@@ -0,0 +1,744 @@
+use std::str::FromStr;
+
+use egui::{vec2, Key, Modifiers, Vec2};
+
+use crate::{KeyBindings, Mode};
+
+/// Everything a key can do in the overlay. Input handling only maps keys to actions,
+/// `MyApp::perform` is the single place where actions take effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Exit,
+    ShowScreen,
+    ShowNarrow,
+    ShowCell,
+    ShowHints,
+
+    SelectRegion(usize),
+    ClickRegion(usize),
+    SkipToCell,
+    NarrowAtPointer,
+    PrevScreen,
+    NextScreen,
+
+    SelectCell(usize),
+    MicroPoint(usize),
+    NudgeUp,
+    NudgeDown,
+    NudgeLeft,
+    NudgeRight,
+
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    WarpUp,
+    WarpDown,
+    WarpLeft,
+    WarpRight,
+
+    LeftClick,
+    LeftClickAndExit,
+    MiddleClick,
+    RightClick,
+    DoubleClick,
+    TripleClick,
+    BackClick,
+    ForwardClick,
+
+    LeftClickDown,
+    LeftClickUp,
+    DragAndExit,
+    DragStart,
+    DragDrop,
+
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    ScrollPageUp,
+    ScrollPageDown,
+
+    SpeedQuarter,
+    SpeedHalf,
+    SpeedTwice,
+    SpeedQuadruple,
+
+    ToggleTouch,
+    ToggleHeatmap,
+    ToggleCursor,
+    TogglePause,
+    MoveWindow,
+    ResizeWindow,
+    ActivateWindow,
+    NextProfile,
+    MirrorLayout,
+    HistoryBack,
+    HistoryForward,
+    IdentifyDisplays,
+    SearchTargets,
+    ScanSelect,
+    GazeSelect,
+
+    RunMacro(usize),
+}
+
+/// How a bound key fires its action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Once, on the frame the key goes down
+    Press,
+    /// Every frame while the key is down, but only after it has been released once in the
+    /// current mode, so the key that entered the mode doesn't fire right away
+    Hold,
+    /// Every frame while the key is down, adjusting the other actions of that frame
+    Modifier,
+}
+
+// Config names of the actions that don't carry an index
+const NAMES: [(&str, Action); 59] = [
+    ("exit", Action::Exit),
+    ("show_screen", Action::ShowScreen),
+    ("show_narrow", Action::ShowNarrow),
+    ("show_cell", Action::ShowCell),
+    ("show_hints", Action::ShowHints),
+    ("skip_to_cell", Action::SkipToCell),
+    ("narrow_at_pointer", Action::NarrowAtPointer),
+    ("prev_screen", Action::PrevScreen),
+    ("next_screen", Action::NextScreen),
+    ("nudge_up", Action::NudgeUp),
+    ("nudge_down", Action::NudgeDown),
+    ("nudge_left", Action::NudgeLeft),
+    ("nudge_right", Action::NudgeRight),
+    ("move_up", Action::MoveUp),
+    ("move_down", Action::MoveDown),
+    ("move_left", Action::MoveLeft),
+    ("move_right", Action::MoveRight),
+    ("warp_up", Action::WarpUp),
+    ("warp_down", Action::WarpDown),
+    ("warp_left", Action::WarpLeft),
+    ("warp_right", Action::WarpRight),
+    ("left_click", Action::LeftClick),
+    ("left_click_and_exit", Action::LeftClickAndExit),
+    ("middle_click", Action::MiddleClick),
+    ("right_click", Action::RightClick),
+    ("double_click", Action::DoubleClick),
+    ("triple_click", Action::TripleClick),
+    ("back_click", Action::BackClick),
+    ("forward_click", Action::ForwardClick),
+    ("left_click_down", Action::LeftClickDown),
+    ("left_click_up", Action::LeftClickUp),
+    ("drag_and_exit", Action::DragAndExit),
+    ("drag_start", Action::DragStart),
+    ("drag_drop", Action::DragDrop),
+    ("scroll_up", Action::ScrollUp),
+    ("scroll_down", Action::ScrollDown),
+    ("scroll_left", Action::ScrollLeft),
+    ("scroll_right", Action::ScrollRight),
+    ("scroll_page_up", Action::ScrollPageUp),
+    ("scroll_page_down", Action::ScrollPageDown),
+    ("speed_quarter", Action::SpeedQuarter),
+    ("speed_half", Action::SpeedHalf),
+    ("speed_twice", Action::SpeedTwice),
+    ("speed_quadruple", Action::SpeedQuadruple),
+    ("toggle_touch", Action::ToggleTouch),
+    ("toggle_heatmap", Action::ToggleHeatmap),
+    ("toggle_cursor", Action::ToggleCursor),
+    ("toggle_pause", Action::TogglePause),
+    ("move_window", Action::MoveWindow),
+    ("resize_window", Action::ResizeWindow),
+    ("activate_window", Action::ActivateWindow),
+    ("next_profile", Action::NextProfile),
+    ("mirror_layout", Action::MirrorLayout),
+    ("history_back", Action::HistoryBack),
+    ("history_forward", Action::HistoryForward),
+    ("identify_displays", Action::IdentifyDisplays),
+    ("search_targets", Action::SearchTargets),
+    ("scan_select", Action::ScanSelect),
+    ("gaze_select", Action::GazeSelect),
+];
+
+impl FromStr for Action {
+    type Err = String;
+
+    /// Parses config names like `left_click`, `region_3`, `cell_14`, `point_4` or `macro_0`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, action)) = NAMES.iter().find(|(name, _)| *name == s) {
+            return Ok(*action);
+        }
+
+        let indexed = |prefix: &str| s.strip_prefix(prefix).and_then(|i| i.parse().ok());
+        if let Some(i) = indexed("region_") {
+            Ok(Action::SelectRegion(i))
+        } else if let Some(i) = indexed("region_click_") {
+            Ok(Action::ClickRegion(i))
+        } else if let Some(i) = indexed("cell_") {
+            Ok(Action::SelectCell(i))
+        } else if let Some(i) = indexed("point_") {
+            Ok(Action::MicroPoint(i))
+        } else if let Some(i) = indexed("macro_") {
+            Ok(Action::RunMacro(i))
+        } else {
+            Err(format!("Unknown action {s}"))
+        }
+    }
+}
+
+impl Action {
+    pub fn name(self) -> String {
+        match self {
+            Action::SelectRegion(i) => format!("region_{i}"),
+            Action::ClickRegion(i) => format!("region_click_{i}"),
+            Action::SelectCell(i) => format!("cell_{i}"),
+            Action::MicroPoint(i) => format!("point_{i}"),
+            Action::RunMacro(i) => format!("macro_{i}"),
+            _ => NAMES
+                .iter()
+                .find(|(_, action)| *action == self)
+                .map(|(name, _)| name.to_string())
+                .unwrap(),
+        }
+    }
+
+    pub fn trigger(self) -> Trigger {
+        match self {
+            Action::MoveUp
+            | Action::MoveDown
+            | Action::MoveLeft
+            | Action::MoveRight
+            | Action::ScrollUp
+            | Action::ScrollDown
+            | Action::ScrollLeft
+            | Action::ScrollRight => Trigger::Hold,
+
+            Action::SpeedQuarter
+            | Action::SpeedHalf
+            | Action::SpeedTwice
+            | Action::SpeedQuadruple => Trigger::Modifier,
+
+            _ => Trigger::Press,
+        }
+    }
+
+    /// Unit step of the movement actions, zero for everything else
+    pub fn direction(self) -> Vec2 {
+        match self {
+            Action::MoveUp => vec2(0.0, -1.0),
+            Action::MoveDown => vec2(0.0, 1.0),
+            Action::MoveLeft => vec2(-1.0, 0.0),
+            Action::MoveRight => vec2(1.0, 0.0),
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// Whether the action jumps the pointer halfway toward an edge, see `MyApp::warp`
+    pub fn warps(self) -> bool {
+        matches!(
+            self,
+            Action::WarpUp | Action::WarpDown | Action::WarpLeft | Action::WarpRight
+        )
+    }
+
+    /// Whether the action moves the pointer, scrolls, or changes how fast those go
+    pub fn moves_or_scrolls(self) -> bool {
+        self.direction() != Vec2::ZERO
+            || self.trigger() == Trigger::Modifier
+            || matches!(
+                self,
+                Action::ScrollUp
+                    | Action::ScrollDown
+                    | Action::ScrollLeft
+                    | Action::ScrollRight
+                    | Action::ScrollPageUp
+                    | Action::ScrollPageDown
+            )
+    }
+
+    /// Whether the action navigates the grid or puts the pointer at an absolute position
+    pub fn uses_grid(self) -> bool {
+        matches!(
+            self,
+            Action::ShowScreen
+                | Action::ShowNarrow
+                | Action::ShowCell
+                | Action::ShowHints
+                | Action::SelectRegion(_)
+                | Action::ClickRegion(_)
+                | Action::SkipToCell
+                | Action::NarrowAtPointer
+                | Action::PrevScreen
+                | Action::NextScreen
+                | Action::SelectCell(_)
+                | Action::MicroPoint(_)
+                | Action::NudgeUp
+                | Action::NudgeDown
+                | Action::NudgeLeft
+                | Action::NudgeRight
+                | Action::WarpUp
+                | Action::WarpDown
+                | Action::WarpLeft
+                | Action::WarpRight
+                | Action::HistoryBack
+                | Action::HistoryForward
+                | Action::ScanSelect
+                | Action::GazeSelect
+        )
+    }
+
+    /// Whether the action picks a spot on the grid or clicks, as opposed to moving, scrolling or
+    /// changing how the overlay looks
+    pub fn selects(self) -> bool {
+        self.clicks()
+            || matches!(
+                self,
+                Action::SelectRegion(_)
+                    | Action::ClickRegion(_)
+                    | Action::SkipToCell
+                    | Action::NarrowAtPointer
+                    | Action::SelectCell(_)
+                    | Action::MicroPoint(_)
+                    | Action::GazeSelect
+                    | Action::LeftClickUp
+            )
+    }
+
+    /// Whether the action clicks or presses a button where the pointer is
+    pub fn clicks(self) -> bool {
+        matches!(
+            self,
+            Action::LeftClick
+                | Action::LeftClickAndExit
+                | Action::MiddleClick
+                | Action::RightClick
+                | Action::DoubleClick
+                | Action::TripleClick
+                | Action::BackClick
+                | Action::ForwardClick
+                | Action::LeftClickDown
+                | Action::DragAndExit
+                | Action::DragStart
+                | Action::DragDrop
+        )
+    }
+
+    /// Whether the config's `after` can say what the overlay does once the action clicked
+    pub fn takes_after(self) -> bool {
+        matches!(
+            self,
+            Action::LeftClick
+                | Action::LeftClickAndExit
+                | Action::MiddleClick
+                | Action::RightClick
+                | Action::DoubleClick
+                | Action::TripleClick
+                | Action::BackClick
+                | Action::ForwardClick
+        )
+    }
+
+    /// Applies a speed modifier to the movement distance, other actions leave it untouched
+    pub fn apply_speed(self, dist: f32) -> f32 {
+        match self {
+            Action::SpeedQuarter => dist / 4.0,
+            Action::SpeedHalf => dist / 2.0,
+            Action::SpeedTwice => dist * 2.0,
+            Action::SpeedQuadruple => dist * 4.0,
+            _ => dist,
+        }
+    }
+}
+
+/// A sequence of actions run by a single key, in every mode
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub key: Chord,
+    pub steps: Vec<Action>,
+    /// Preview the clicks and wait for Enter before running
+    pub confirm: bool,
+}
+
+/// A key and the modifiers held with it, like `ctrl+J`. Without modifiers, the key works whatever
+/// is held, unless a chord of it with the held modifiers is bound too.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chord {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl Chord {
+    pub fn plain(key: Key) -> Self {
+        Chord {
+            key,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub fn has_modifiers(self) -> bool {
+        self.modifiers != Modifiers::NONE
+    }
+
+    /// Whether `held` allows the chord: exactly its Ctrl, Shift and Alt, or anything for a plain key
+    pub fn modifiers_match(self, held: Modifiers) -> bool {
+        !self.has_modifiers()
+            || (held.ctrl == self.modifiers.ctrl
+                && held.shift == self.modifiers.shift
+                && held.alt == self.modifiers.alt)
+    }
+
+    /// `Ctrl+`, `Shift+` and `Alt+` for the modifiers, to go before the key's name or label
+    pub fn modifier_prefix(self) -> String {
+        [
+            (self.modifiers.ctrl, "Ctrl+"),
+            (self.modifiers.shift, "Shift+"),
+            (self.modifiers.alt, "Alt+"),
+        ]
+        .into_iter()
+        .filter(|(held, _)| *held)
+        .map(|(_, name)| name)
+        .collect()
+    }
+
+    pub fn name(self) -> String {
+        format!("{}{}", self.modifier_prefix(), self.key.name())
+    }
+}
+
+/// Splits `ctrl+`, `shift+` and `alt+` (any case, any order) off a binding like `ctrl+shift+J`,
+/// leaving the key name. `ctrl++` is Ctrl with the `+` key.
+pub fn split_modifiers(mut name: &str) -> (Modifiers, &str) {
+    let mut modifiers = Modifiers::NONE;
+    while let Some((prefix, rest)) = name.split_once('+') {
+        let held = match prefix.to_lowercase().as_str() {
+            "ctrl" | "control" => &mut modifiers.ctrl,
+            "shift" => &mut modifiers.shift,
+            "alt" => &mut modifiers.alt,
+            _ => break,
+        };
+        if rest.is_empty() {
+            break;
+        }
+        *held = true;
+        name = rest;
+    }
+    (modifiers, name)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Binding {
+    pub chord: Chord,
+    pub action: Action,
+}
+
+/// Key to action lookup for each mode. Bindings are kept in the order they are checked.
+#[derive(Debug, Clone, Default)]
+pub struct DispatchTable {
+    screen: Vec<Binding>,
+    narrow: Vec<Binding>,
+    cell: Vec<Binding>,
+    recurse: Vec<Binding>,
+    hints: Vec<Binding>,
+}
+
+impl DispatchTable {
+    /// Builds the lookup from the configured bindings. When a key is bound more than once in a
+    /// mode, the more specific binding wins, in this order:
+    /// 1. the mode's section in `modes`
+    /// 2. the flat bindings (`region`, `grid`, `mouse`, ...)
+    /// 3. in Narrow mode, the movement, scroll and speed keys of Cell mode
+    /// 4. the built-in Escape / Backspace / Enter / arrow keys
+    ///
+    /// Two bindings from the same level sharing a key are rejected.
+    pub fn new(bindings: &KeyBindings) -> Result<Self, String> {
+        let mut table = DispatchTable::default();
+        let mut conflicts = Vec::new();
+
+        let modes = [
+            Mode::Screen,
+            Mode::Narrow,
+            Mode::Cell,
+            Mode::Recurse(1),
+            Mode::Hints,
+        ];
+        for mode in modes {
+            let overrides = bindings.modes.for_mode(mode);
+            let flat: Vec<_> = flat_bindings(bindings, mode)
+                .into_iter()
+                .filter(|b| {
+                    !overrides
+                        .iter()
+                        .any(|o| o.chord == b.chord || o.action == b.action)
+                })
+                .collect();
+
+            for level in [overrides, &flat] {
+                for (i, b) in level.iter().enumerate() {
+                    if let Some(other) = level[..i].iter().find(|o| o.chord == b.chord) {
+                        conflicts.push(format!(
+                            "{} is bound to both {} and {} in {:?} mode",
+                            b.chord.name(),
+                            other.action.name(),
+                            b.action.name(),
+                            mode
+                        ));
+                    }
+                }
+            }
+
+            let mut configured: Vec<_> = overrides.iter().chain(&flat).copied().collect();
+            for b in borrowed_bindings(bindings, mode) {
+                if !configured
+                    .iter()
+                    .any(|c| c.chord == b.chord || c.action == b.action)
+                {
+                    configured.push(b);
+                }
+            }
+            let builtin = builtin_bindings(mode)
+                .into_iter()
+                .filter(|b| !configured.iter().any(|c| c.chord == b.chord));
+            *table.for_mode_mut(mode) = configured.iter().copied().chain(builtin).collect();
+        }
+
+        if conflicts.is_empty() {
+            Ok(table)
+        } else {
+            Err(conflicts.join("\n"))
+        }
+    }
+
+    /// The Cell mode bindings that move or scroll, plus the keys that exit or pause, for
+    /// `--scroll-only`
+    pub fn scroll_only(&self) -> Self {
+        let cell = self
+            .cell
+            .iter()
+            .filter(|b| {
+                b.action.moves_or_scrolls()
+                    || matches!(b.action, Action::Exit | Action::TogglePause)
+            })
+            .copied()
+            .collect();
+        DispatchTable {
+            cell,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, mode: Mode, binding: Binding) {
+        self.for_mode_mut(mode).push(binding);
+    }
+
+    fn for_mode_mut(&mut self, mode: Mode) -> &mut Vec<Binding> {
+        match mode {
+            Mode::Screen => &mut self.screen,
+            Mode::Narrow => &mut self.narrow,
+            Mode::Cell => &mut self.cell,
+            Mode::Recurse(_) => &mut self.recurse,
+            Mode::Hints => &mut self.hints,
+        }
+    }
+
+    pub fn for_mode(&self, mode: Mode) -> &[Binding] {
+        match mode {
+            Mode::Screen => &self.screen,
+            Mode::Narrow => &self.narrow,
+            Mode::Cell => &self.cell,
+            Mode::Recurse(_) => &self.recurse,
+            Mode::Hints => &self.hints,
+        }
+    }
+}
+
+// Cell mode keys that also work in Narrow mode, on wherever the pointer is, so scrolling doesn't
+// take picking a cell first. Cell keys sharing a key take precedence.
+fn borrowed_bindings(bindings: &KeyBindings, mode: Mode) -> Vec<Binding> {
+    let mouse = &bindings.mouse;
+    match mode {
+        Mode::Narrow => [
+            (&mouse.move_up, Action::MoveUp),
+            (&mouse.move_down, Action::MoveDown),
+            (&mouse.move_left, Action::MoveLeft),
+            (&mouse.move_right, Action::MoveRight),
+            (&mouse.scroll_up, Action::ScrollUp),
+            (&mouse.scroll_down, Action::ScrollDown),
+            (&mouse.scroll_left, Action::ScrollLeft),
+            (&mouse.scroll_right, Action::ScrollRight),
+            (&mouse.scroll_page_up, Action::ScrollPageUp),
+            (&mouse.scroll_page_down, Action::ScrollPageDown),
+            (&mouse.speed_quarter, Action::SpeedQuarter),
+            (&mouse.speed_half, Action::SpeedHalf),
+            (&mouse.speed_twice, Action::SpeedTwice),
+            (&mouse.speed_quadruple, Action::SpeedQuadruple),
+        ]
+        .into_iter()
+        .flat_map(|(keys, action)| bind_all(keys, action))
+        .collect(),
+        Mode::Screen | Mode::Cell | Mode::Recurse(_) | Mode::Hints => Vec::new(),
+    }
+}
+
+// A binding for each of the keys of an action
+fn bind_all(chords: &[Chord], action: Action) -> impl Iterator<Item = Binding> + '_ {
+    chords.iter().map(move |&chord| Binding { chord, action })
+}
+
+fn builtin_bindings(mode: Mode) -> Vec<Binding> {
+    let bind = |key, action| Binding {
+        chord: Chord::plain(key),
+        action,
+    };
+    match mode {
+        Mode::Screen => vec![
+            bind(Key::Escape, Action::Exit),
+            bind(Key::Backspace, Action::Exit),
+        ],
+        Mode::Narrow => vec![
+            bind(Key::Escape, Action::Exit),
+            bind(Key::Backspace, Action::ShowScreen),
+            bind(Key::Enter, Action::ShowCell),
+            bind(Key::ArrowUp, Action::NudgeUp),
+            bind(Key::ArrowDown, Action::NudgeDown),
+            bind(Key::ArrowLeft, Action::NudgeLeft),
+            bind(Key::ArrowRight, Action::NudgeRight),
+        ],
+        Mode::Cell => vec![
+            bind(Key::Escape, Action::Exit),
+            bind(Key::Backspace, Action::ShowNarrow),
+        ],
+        Mode::Recurse(_) => vec![
+            bind(Key::Escape, Action::Exit),
+            bind(Key::Backspace, Action::ShowNarrow),
+            bind(Key::Enter, Action::ShowCell),
+        ],
+        Mode::Hints => vec![
+            bind(Key::Escape, Action::Exit),
+            bind(Key::Backspace, Action::ShowScreen),
+        ],
+    }
+}
+
+fn flat_bindings(bindings: &KeyBindings, mode: Mode) -> Vec<Binding> {
+    let mouse = &bindings.mouse;
+    let bind = |key, action| Binding {
+        chord: Chord::plain(key),
+        action,
+    };
+
+    let scan = bind_all(&bindings.scan_select, Action::ScanSelect);
+    let pause = bind_all(&bindings.pause, Action::TogglePause);
+    let move_window = bind_all(&bindings.move_window, Action::MoveWindow);
+    let resize_window = bind_all(&bindings.resize_window, Action::ResizeWindow);
+    let activate_window = bind_all(&bindings.activate_window, Action::ActivateWindow);
+    let next_profile = bind_all(&bindings.next_profile, Action::NextProfile);
+    let mirror_layout = bind_all(&bindings.mirror_layout, Action::MirrorLayout);
+    let history_back = bind_all(&bindings.history_back, Action::HistoryBack);
+    let history_forward = bind_all(&bindings.history_forward, Action::HistoryForward);
+    let narrow_at_pointer = bind_all(&bindings.narrow_at_pointer, Action::NarrowAtPointer);
+    let show_hints = match mode {
+        Mode::Hints => &[],
+        Mode::Screen | Mode::Narrow | Mode::Cell | Mode::Recurse(_) => &bindings.show_hints[..],
+    };
+    let show_hints = bind_all(show_hints, Action::ShowHints);
+    // Shortcuts back, in the modes they lead back from
+    let back_to_narrow = match mode {
+        Mode::Cell | Mode::Recurse(_) => &bindings.back_to_narrow[..],
+        Mode::Screen | Mode::Narrow | Mode::Hints => &[],
+    };
+    let back_to_narrow = bind_all(back_to_narrow, Action::ShowNarrow);
+    let back_to_screen = match mode {
+        Mode::Narrow | Mode::Cell | Mode::Recurse(_) | Mode::Hints => &bindings.back_to_screen[..],
+        Mode::Screen => &[],
+    };
+    let back_to_screen = bind_all(back_to_screen, Action::ShowScreen);
+    let macros = bindings.macros.iter().enumerate().map(|(i, m)| Binding {
+        chord: m.key,
+        action: Action::RunMacro(i),
+    });
+    let gaze = match mode {
+        Mode::Screen | Mode::Narrow => &bindings.gaze_select[..],
+        Mode::Cell | Mode::Recurse(_) | Mode::Hints => &[],
+    };
+    let gaze = bind_all(gaze, Action::GazeSelect);
+    let mode_bindings = match mode {
+        Mode::Screen => {
+            let mut screen = Vec::new();
+            for (i, key) in bindings.region.iter().enumerate() {
+                screen.push(bind(*key, Action::SelectRegion(i)));
+            }
+            screen.extend(bind_all(&bindings.skip_to_cell, Action::SkipToCell));
+            screen.extend(bind_all(&bindings.prev_screen, Action::PrevScreen));
+            screen.extend(bind_all(&bindings.next_screen, Action::NextScreen));
+            screen
+        }
+        Mode::Narrow | Mode::Recurse(_) => {
+            let mut narrow = Vec::new();
+            for (i, key) in bindings.grid.iter().enumerate() {
+                narrow.push(bind(*key, Action::SelectCell(i)));
+            }
+            narrow
+        }
+        // Hint keys are typed in `MyApp::handle_input`, before any binding
+        Mode::Hints => Vec::new(),
+        Mode::Cell => {
+            let points = bindings
+                .micro_grid
+                .iter()
+                .enumerate()
+                .map(|(i, key)| bind(*key, Action::MicroPoint(i)));
+            [
+                (&mouse.left_click_and_exit, Action::LeftClickAndExit),
+                (&mouse.left_click, Action::LeftClick),
+                (&mouse.right_click, Action::RightClick),
+                (&mouse.double_click, Action::DoubleClick),
+                (&mouse.drag_start, Action::DragStart),
+                (&mouse.drag_drop, Action::DragDrop),
+                (&mouse.triple_click, Action::TripleClick),
+                (&mouse.back_click, Action::BackClick),
+                (&mouse.forward_click, Action::ForwardClick),
+                (&mouse.middle_click, Action::MiddleClick),
+                (&mouse.scroll_up, Action::ScrollUp),
+                (&mouse.scroll_down, Action::ScrollDown),
+                (&mouse.scroll_left, Action::ScrollLeft),
+                (&mouse.scroll_right, Action::ScrollRight),
+                (&mouse.scroll_page_up, Action::ScrollPageUp),
+                (&mouse.scroll_page_down, Action::ScrollPageDown),
+                (&mouse.left_click_down, Action::LeftClickDown),
+                (&mouse.left_click_up, Action::LeftClickUp),
+                (&mouse.speed_quarter, Action::SpeedQuarter),
+                (&mouse.speed_half, Action::SpeedHalf),
+                (&mouse.speed_twice, Action::SpeedTwice),
+                (&mouse.speed_quadruple, Action::SpeedQuadruple),
+                (&mouse.move_down, Action::MoveDown),
+                (&mouse.move_up, Action::MoveUp),
+                (&mouse.move_left, Action::MoveLeft),
+                (&mouse.move_right, Action::MoveRight),
+                (&mouse.warp_up, Action::WarpUp),
+                (&mouse.warp_down, Action::WarpDown),
+                (&mouse.warp_left, Action::WarpLeft),
+                (&mouse.warp_right, Action::WarpRight),
+            ]
+            .into_iter()
+            .flat_map(|(keys, action)| bind_all(keys, action))
+            .chain(points)
+            .collect()
+        }
+    };
+    mode_bindings
+        .into_iter()
+        .chain(scan)
+        .chain(pause)
+        .chain(move_window)
+        .chain(resize_window)
+        .chain(activate_window)
+        .chain(next_profile)
+        .chain(mirror_layout)
+        .chain(history_back)
+        .chain(history_forward)
+        .chain(narrow_at_pointer)
+        .chain(show_hints)
+        .chain(back_to_narrow)
+        .chain(back_to_screen)
+        .chain(gaze)
+        .chain(macros)
+        .collect()
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::ptr;
+
+use egui::{pos2, Color32, ColorImage, Painter, Rect, Rounding, TextureHandle, TextureOptions};
+use x11::xlib;
+
+/// What to draw behind the grid when the X server has no compositor. Without one the transparent
+/// window comes out black and hides the screen the user is trying to click on.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransparencyFallback {
+    /// A screenshot of the display taken right before the overlay opens, dimmed if that fails
+    #[default]
+    Screenshot,
+    /// A dark solid background, so the overlay at least doesn't look like a crash
+    Dim,
+    /// Nothing, for setups where the check is wrong
+    None,
+}
+
+const DIM_COLOR: Color32 = Color32::from_rgb(40, 40, 40);
+
+/// Whether a compositing manager holds the `_NET_WM_CM_S<screen>` selection, meaning transparent
+/// windows really are transparent
+pub fn is_composited() -> bool {
+    // SAFETY: the display is only used within this block and closed before leaving it
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            // Not on X11, e.g. Wayland, where compositing is a given
+            return true;
+        }
+
+        let name = CString::new(format!("_NET_WM_CM_S{}", xlib::XDefaultScreen(display))).unwrap();
+        let atom = xlib::XInternAtom(display, name.as_ptr(), xlib::False);
+        let owner = xlib::XGetSelectionOwner(display, atom);
+
+        xlib::XCloseDisplay(display);
+        owner != 0
+    }
+}
+
+/// Grabs `rect` of the root window. Only 24 and 32 bit true color visuals are supported.
+pub fn capture(rect: Rect) -> Option<ColorImage> {
+    // SAFETY: the display and image are only used within this block and freed before leaving it
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return None;
+        }
+
+        let root = xlib::XDefaultRootWindow(display);
+        let image = xlib::XGetImage(
+            display,
+            root,
+            rect.min.x as i32,
+            rect.min.y as i32,
+            rect.width() as u32,
+            rect.height() as u32,
+            xlib::XAllPlanes(),
+            xlib::ZPixmap,
+        );
+        let result = if image.is_null() {
+            None
+        } else {
+            let result = to_color_image(&*image);
+            xlib::XDestroyImage(image);
+            result
+        };
+
+        xlib::XCloseDisplay(display);
+        result
+    }
+}
+
+unsafe fn to_color_image(image: &xlib::XImage) -> Option<ColorImage> {
+    if image.bits_per_pixel != 32 {
+        return None;
+    }
+    let channel = |mask: u64| {
+        let shift = mask.trailing_zeros();
+        (mask >> shift == 0xff).then_some(shift)
+    };
+    let r = channel(image.red_mask)?;
+    let g = channel(image.green_mask)?;
+    let b = channel(image.blue_mask)?;
+
+    let (width, height) = (image.width as usize, image.height as usize);
+    let stride = image.bytes_per_line as usize;
+    let data = std::slice::from_raw_parts(image.data as *const u8, stride * height);
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in data.chunks_exact(stride) {
+        for px in row[..width * 4].chunks_exact(4) {
+            let px = [px[0], px[1], px[2], px[3]];
+            let px = if image.byte_order == xlib::LSBFirst {
+                u32::from_le_bytes(px)
+            } else {
+                u32::from_be_bytes(px)
+            };
+            pixels.push(Color32::from_rgb(
+                (px >> r) as u8,
+                (px >> g) as u8,
+                (px >> b) as u8,
+            ));
+        }
+    }
+    Some(ColorImage {
+        size: [width, height],
+        pixels,
+    })
+}
+
+/// The background of each display for overlays without a compositor
+pub struct Backdrop {
+    screenshots: Vec<Option<ColorImage>>,
+    textures: HashMap<usize, TextureHandle>,
+}
+
+impl Backdrop {
+    /// Checks for a compositor and takes the screenshots if there is none. Has to run before the
+    /// overlay window opens, or it would be in the screenshots.
+    pub fn new(fallback: TransparencyFallback, areas: &[Rect]) -> Option<Self> {
+        if fallback == TransparencyFallback::None || is_composited() {
+            return None;
+        }
+        let screenshots = match fallback {
+            TransparencyFallback::Screenshot => areas.iter().map(|area| capture(*area)).collect(),
+            _ => Vec::new(),
+        };
+        Some(Backdrop {
+            screenshots,
+            textures: HashMap::new(),
+        })
+    }
+
+    /// The screenshots no longer match the displays, dim everything from now on
+    pub fn invalidate(&mut self) {
+        self.screenshots.clear();
+        self.textures.clear();
+    }
+
+    pub fn draw(&mut self, ctx: &egui::Context, painter: &Painter, display_idx: usize, rect: Rect) {
+        let screenshot = self.screenshots.get_mut(display_idx).and_then(Option::take);
+        if let Some(image) = screenshot {
+            let texture = ctx.load_texture("backdrop", image, TextureOptions::LINEAR);
+            self.textures.insert(display_idx, texture);
+        }
+
+        match self.textures.get(&display_idx) {
+            Some(texture) => {
+                let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+                painter.image(texture.id(), rect, uv, Color32::WHITE);
+            }
+            None => {
+                painter.rect_filled(rect, Rounding::ZERO, DIM_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::migrate;
+use crate::mouse::PointerBackend;
+
+// What this build can do, for wrapper scripts to check before relying on it. Only ever grows
+// entries, so checking for one keeps working.
+const FEATURES: &[&str] = &["x11", "evdev", "control-socket", "toml-config", "daemon"];
+
+/// `kmgrid --version`, with `json` as an object for bug reports and scripts
+pub fn print(json: bool) {
+    let version = env!("CARGO_PKG_VERSION");
+    let git_hash = option_env!("KMGRID_GIT_HASH");
+    if !json {
+        match git_hash {
+            Some(hash) => println!("kmgrid {version} ({hash})"),
+            None => println!("kmgrid {version}"),
+        }
+        return;
+    }
+
+    let info = serde_json::json!({
+        "version": version,
+        "git_hash": git_hash,
+        "target": format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+        "config_version": migrate::VERSION,
+        "pointer_backends": [PointerBackend::Xtest, PointerBackend::Uinput],
+        "features": FEATURES,
+    });
+    println!("{}", serde_json::to_string_pretty(&info).unwrap());
+}
@@ -0,0 +1,414 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::migrate;
+
+const FILE_NAMES: [&str; 2] = ["config.json", "config.toml"];
+
+// Variables that pick the config file and profile rather than set something in the config
+const ENV_CONFIG: &str = "KMGRID_CONFIG";
+const ENV_PROFILE: &str = "KMGRID_PROFILE";
+
+/// The config used when there's no config file, and underneath the one there is: QWERTY home row
+/// bindings and the stock colors and speeds
+pub fn defaults() -> Value {
+    serde_json::from_str(include_str!("../resources/default_config.json"))
+        .expect("The built-in config is valid JSON")
+}
+
+/// Finds the config file: the one given on the command line if any, then `$KMGRID_CONFIG`,
+/// otherwise the first `config.json` or `config.toml` in `$XDG_CONFIG_HOME/kmgrid`,
+/// `~/.config/kmgrid` and the working directory
+pub fn locate(cli: Option<&Path>) -> Option<PathBuf> {
+    let env = std::env::var_os(ENV_CONFIG)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    let cli = cli.or(env.as_deref());
+    let xdg = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from);
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    locate_in(cli, xdg, home, PathBuf::from("."))
+}
+
+/// Where a config written by kmgrid goes: `config.json` in `$XDG_CONFIG_HOME/kmgrid`, or else in
+/// `~/.config/kmgrid`
+pub fn new_path() -> Option<PathBuf> {
+    let xdg = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from);
+    let dir = xdg.map(|dir| dir.join("kmgrid")).or_else(|| {
+        std::env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".config").join("kmgrid"))
+    })?;
+    Some(dir.join(FILE_NAMES[0]))
+}
+
+fn locate_in(
+    cli: Option<&Path>,
+    xdg: Option<PathBuf>,
+    home: Option<PathBuf>,
+    cwd: PathBuf,
+) -> Option<PathBuf> {
+    if let Some(path) = cli {
+        return Some(path.to_owned());
+    }
+    let dirs = [
+        xdg.map(|dir| dir.join("kmgrid")),
+        home.map(|dir| dir.join(".config").join("kmgrid")),
+        Some(cwd),
+    ];
+    dirs.into_iter()
+        .flatten()
+        .flat_map(|dir| FILE_NAMES.map(|name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Reads the config file at `path`, with its `include`s merged in.
+///
+/// The merged config is brought up to the current config version of `path`, printing what that
+/// changed. Included files are fragments of it, any `version` they have is ignored.
+///
+/// Files ending in `.toml` are TOML and files ending in `.json` JSON, anything else is tried as
+/// JSON first and TOML second. Both come out as the same JSON value, and a file of either format
+/// can include files of the other.
+///
+/// `include` is a path or a list of paths, relative to the file naming them. Included files are
+/// merged in order, then the including file on top, so later files win. Objects are merged key by
+/// key at every level, anything else (numbers, strings, lists like `region`) is replaced as a
+/// whole. Included files can include others, but not the file that included them.
+pub fn load(path: &Path) -> Result<Value, String> {
+    let mut value = load_with_includes(path, &mut Vec::new(), &mut Vec::new())?;
+    let (version, changes) = migrate::migrate(&mut value)
+        .map_err(|err| format!("Unable to read config file {}: {err}", path.display()))?;
+    if !changes.is_empty() {
+        eprintln!(
+            "Upgrading {} from config version {version} to {}",
+            path.display(),
+            migrate::VERSION
+        );
+        for change in changes {
+            eprintln!("  {change}");
+        }
+    }
+    Ok(value)
+}
+
+/// The files [`load`] reads for the config at `path`: the file itself and everything it includes.
+/// Files past one that can't be read are left out.
+pub fn files(path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let _ = load_with_includes(path, &mut Vec::new(), &mut files);
+    files
+}
+
+fn load_with_includes(
+    path: &Path,
+    including: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Value, String> {
+    files.push(path.to_owned());
+    let canonical = path
+        .canonicalize()
+        .map_err(|err| format!("Unable to find config file {}: {err}", path.display()))?;
+    if including.contains(&canonical) {
+        return Err(format!("{} includes itself", path.display()));
+    }
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read config file {}: {err}", path.display()))?;
+    let mut value = parse(path, &text)
+        .map_err(|err| format!("Unable to parse config file {}: {err}", path.display()))?;
+    if !including.is_empty() {
+        if let Some(fragment) = value.as_object_mut() {
+            fragment.remove("version");
+        }
+    }
+    let includes = match value.as_object_mut().and_then(|o| o.remove("include")) {
+        None => Vec::new(),
+        Some(Value::String(include)) => vec![include],
+        Some(Value::Array(includes)) => includes
+            .into_iter()
+            .map(|include| match include {
+                Value::String(include) => Ok(include),
+                other => Err(format!("Invalid include {other} in {}", path.display())),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(other) => return Err(format!("Invalid include {other} in {}", path.display())),
+    };
+
+    including.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut merged = Value::Object(Map::new());
+    for include in includes {
+        merge(
+            &mut merged,
+            load_with_includes(&dir.join(include), including, files)?,
+        );
+    }
+    including.pop();
+
+    merge(&mut merged, value);
+    Ok(merged)
+}
+
+/// Parses a config file, JSON or TOML by its extension. JSON may have `//` comments on lines of
+/// their own, like `kmgrid dump-default-config` writes.
+pub fn parse(path: &Path, text: &str) -> Result<Value, String> {
+    let json = |text: &str| {
+        // Blanked rather than removed, so error positions still match the file
+        let text: String = text
+            .lines()
+            .map(|line| {
+                if line.trim_start().starts_with("//") {
+                    ""
+                } else {
+                    line
+                }
+            })
+            .flat_map(|line| [line, "\n"])
+            .collect();
+        serde_json::from_str(&text).map_err(|err: serde_json::Error| err.to_string())
+    };
+    let toml = |text| toml::from_str(text).map_err(|err: toml::de::Error| err.to_string());
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => json(text),
+        Some("toml") => toml(text),
+        // The JSON error is the likelier one to help
+        _ => json(text).or_else(|err| toml(text).map_err(|_| err)),
+    }
+}
+
+/// The profile named by `$KMGRID_PROFILE`, for when the command line doesn't pick one
+pub fn env_profile() -> Option<String> {
+    std::env::var(ENV_PROFILE)
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// Settings from `KMGRID_*` environment variables, the top layer over the config file and every
+/// profile, so scripted launches can tweak a setting or two: `KMGRID_MOVEMENT_SPEED=40mm/s` sets
+/// `movement_speed`, and `__` goes a level down as in `KMGRID_STYLE__LABEL_MIN_SIZE=16`. Names are
+/// lowercased. Values are JSON where they parse as such (`12`, `true`, `["Q", "W"]`) and strings
+/// otherwise.
+pub fn env_overrides() -> Map<String, Value> {
+    overrides_from(std::env::vars())
+}
+
+fn overrides_from(vars: impl Iterator<Item = (String, String)>) -> Map<String, Value> {
+    let mut overrides = Value::Object(Map::new());
+    for (var, text) in vars {
+        let Some(name) = var.strip_prefix("KMGRID_") else {
+            continue;
+        };
+        if var == ENV_CONFIG || var == ENV_PROFILE || name.is_empty() {
+            continue;
+        }
+        let mut layer = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        for key in name.rsplit("__") {
+            layer = Value::Object(Map::from_iter([(key.to_lowercase(), layer)]));
+        }
+        merge(&mut overrides, layer);
+    }
+    match overrides {
+        Value::Object(overrides) => overrides,
+        _ => unreachable!("Only objects get merged in"),
+    }
+}
+
+/// Merges `top` into `base`, recursing into objects present in both
+pub fn merge(base: &mut Value, top: Value) {
+    match (base, top) {
+        (Value::Object(base), Value::Object(top)) => {
+            for (key, value) in top {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, top) => *base = top,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory with the given files in it, removed again on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let dir = std::env::temp_dir().join(format!("kmgrid-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            for file in files {
+                let path = dir.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, "{}").unwrap();
+            }
+            TempDir(dir)
+        }
+
+        fn path(&self, file: &str) -> PathBuf {
+            self.0.join(file)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn cli_path_wins() {
+        let dir = TempDir::new("cli", &["xdg/kmgrid/config.json", "cwd/config.json"]);
+        let cli = dir.path("elsewhere.json");
+        let found = locate_in(Some(&cli), Some(dir.path("xdg")), None, dir.path("cwd"));
+        assert_eq!(found, Some(cli));
+    }
+
+    #[test]
+    fn xdg_before_home_before_cwd() {
+        let dir = TempDir::new(
+            "order",
+            &[
+                "xdg/kmgrid/config.json",
+                "home/.config/kmgrid/config.json",
+                "cwd/config.json",
+            ],
+        );
+        let locate = |xdg: bool, home: bool| {
+            locate_in(
+                None,
+                xdg.then(|| dir.path("xdg")),
+                home.then(|| dir.path("home")),
+                dir.path("cwd"),
+            )
+        };
+        assert_eq!(locate(true, true), Some(dir.path("xdg/kmgrid/config.json")));
+        assert_eq!(
+            locate(false, true),
+            Some(dir.path("home/.config/kmgrid/config.json"))
+        );
+        assert_eq!(locate(false, false), Some(dir.path("cwd/config.json")));
+    }
+
+    #[test]
+    fn skips_directories_without_a_config() {
+        let dir = TempDir::new("skip", &["xdg/kmgrid/other.json", "cwd/config.toml"]);
+        let found = locate_in(
+            None,
+            Some(dir.path("xdg")),
+            Some(dir.path("home")),
+            dir.path("cwd"),
+        );
+        assert_eq!(found, Some(dir.path("cwd/config.toml")));
+    }
+
+    #[test]
+    fn json_before_toml() {
+        let dir = TempDir::new("format", &["cwd/config.json", "cwd/config.toml"]);
+        let found = locate_in(None, None, None, dir.path("cwd"));
+        assert_eq!(found, Some(dir.path("cwd/config.json")));
+    }
+
+    #[test]
+    fn merge_keeps_keys_the_top_leaves_out() {
+        let mut base = serde_json::json!({
+            "key_bindings": { "mouse": { "left_click": "D", "right_click": "S" }, "region": ["Q"] },
+            "movement_speed": 5,
+        });
+        let top = serde_json::json!({
+            "key_bindings": { "mouse": { "left_click": "X" } },
+        });
+        merge(&mut base, top);
+        assert_eq!(base["key_bindings"]["mouse"]["left_click"], "X");
+        assert_eq!(base["key_bindings"]["mouse"]["right_click"], "S");
+        assert_eq!(base["key_bindings"]["region"], serde_json::json!(["Q"]));
+        assert_eq!(base["movement_speed"], 5);
+    }
+
+    #[test]
+    fn merge_replaces_lists_and_values() {
+        let mut base = serde_json::json!({ "region": ["Q", "W"], "scroll_speed": 1 });
+        merge(
+            &mut base,
+            serde_json::json!({ "region": ["A"], "scroll_speed": 3 }),
+        );
+        assert_eq!(
+            base,
+            serde_json::json!({ "region": ["A"], "scroll_speed": 3 })
+        );
+    }
+
+    #[test]
+    fn partial_config_over_defaults() {
+        let mut config = defaults();
+        merge(&mut config, serde_json::json!({ "movement_speed": 9 }));
+        assert_eq!(config["movement_speed"], 9);
+        assert_eq!(config["key_bindings"], defaults()["key_bindings"]);
+        assert_eq!(config["style"], defaults()["style"]);
+    }
+
+    #[test]
+    fn env_overrides_nest_and_parse_json() {
+        let vars = [
+            ("KMGRID_MOVEMENT_SPEED", "40mm/s"),
+            ("KMGRID_SCROLL_SPEED", "3"),
+            ("KMGRID_STYLE__LABEL_MIN_SIZE", "16"),
+            ("KMGRID_STYLE__AUTO_LABEL_COLOR", "true"),
+            ("KMGRID_PROFILE", "fast"),
+            ("HOME", "/home/me"),
+        ];
+        let overrides = overrides_from(vars.map(|(k, v)| (k.to_owned(), v.to_owned())).into_iter());
+        assert_eq!(
+            Value::Object(overrides),
+            serde_json::json!({
+                "movement_speed": "40mm/s",
+                "scroll_speed": 3,
+                "style": { "label_min_size": 16, "auto_label_color": true },
+            })
+        );
+    }
+
+    #[test]
+    fn includes_take_the_version_of_the_including_file() {
+        let dir = TempDir::new("version", &[]);
+        std::fs::create_dir_all(&dir.0).unwrap();
+        std::fs::write(
+            dir.path("base.json"),
+            r#"{ "version": 99, "scroll_speed": 2 }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path("config.json"), r#"{ "include": "base.json" }"#).unwrap();
+        assert_eq!(
+            load(&dir.path("config.json")),
+            Ok(serde_json::json!({ "scroll_speed": 2, "version": migrate::VERSION }))
+        );
+    }
+
+    #[test]
+    fn files_follow_the_includes() {
+        let dir = TempDir::new("files", &["a.json", "b.json"]);
+        std::fs::write(
+            dir.path("config.json"),
+            r#"{ "include": ["a.json", "c.json"] }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path("a.json"), r#"{ "include": "b.json" }"#).unwrap();
+        assert_eq!(
+            files(&dir.path("config.json")),
+            ["config.json", "a.json", "b.json", "c.json"].map(|file| dir.path(file))
+        );
+    }
+
+    #[test]
+    fn nothing_found() {
+        let dir = TempDir::new("none", &[]);
+        assert_eq!(locate_in(None, None, None, dir.path("cwd")), None);
+    }
+}
@@ -0,0 +1,81 @@
+use egui::{Color32, ColorImage, Rect};
+
+use crate::backdrop;
+
+// Samples along each side of a label's area, plenty for its average brightness
+const SAMPLES: usize = 24;
+
+/// Screenshots of the displays from right before the overlay opened, to give every label the
+/// text color that stands out most from what's underneath it
+pub struct LabelShades {
+    screenshots: Vec<Option<ColorImage>>,
+}
+
+impl LabelShades {
+    /// Has to run before the overlay window opens, or it would be in the screenshots
+    pub fn capture(areas: &[Rect]) -> Self {
+        LabelShades {
+            screenshots: areas.iter().map(|area| backdrop::capture(*area)).collect(),
+        }
+    }
+
+    /// Text and outline colors for a label covering `rect` of display `display_idx`, in the
+    /// coordinates of its overlay window: black on white over light content, white on black
+    /// otherwise
+    pub fn colors(&self, display_idx: usize, rect: Rect) -> (Color32, Color32) {
+        let light = self
+            .screenshots
+            .get(display_idx)
+            .and_then(Option::as_ref)
+            .and_then(|image| average_luminance(image, rect))
+            .is_some_and(prefers_black_text);
+        if light {
+            (Color32::BLACK, Color32::WHITE)
+        } else {
+            (Color32::WHITE, Color32::BLACK)
+        }
+    }
+}
+
+fn average_luminance(image: &ColorImage, rect: Rect) -> Option<f32> {
+    let [width, height] = image.size;
+    let x0 = (rect.min.x.max(0.0) as usize).min(width);
+    let y0 = (rect.min.y.max(0.0) as usize).min(height);
+    let x1 = (rect.max.x.max(0.0) as usize).min(width);
+    let y1 = (rect.max.y.max(0.0) as usize).min(height);
+    if x0 >= x1 || y0 >= y1 {
+        return None;
+    }
+
+    let step_x = ((x1 - x0) / SAMPLES).max(1);
+    let step_y = ((y1 - y0) / SAMPLES).max(1);
+    let mut sum = 0.0;
+    let mut count = 0;
+    for y in (y0..y1).step_by(step_y) {
+        for x in (x0..x1).step_by(step_x) {
+            sum += luminance(image.pixels[y * width + x]);
+            count += 1;
+        }
+    }
+    Some(sum / count as f32)
+}
+
+// Relative luminance as WCAG defines it, on linear light rather than the gamma encoded values
+fn luminance(color: Color32) -> f32 {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r()) + 0.7152 * linear(color.g()) + 0.0722 * linear(color.b())
+}
+
+// Whether black text has the higher contrast ratio over a background of `luminance`
+fn prefers_black_text(luminance: f32) -> bool {
+    let with_black = (luminance + 0.05) / 0.05;
+    let with_white = 1.05 / (luminance + 0.05);
+    with_black > with_white
+}
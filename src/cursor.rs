@@ -0,0 +1,34 @@
+use std::ptr;
+
+use x11::{xfixes, xlib};
+
+/// Keeps the system cursor hidden on every display until dropped. The X server shows it again
+/// by itself if kmgrid dies, since hiding only lasts as long as the connection that asked for it.
+pub struct HiddenCursor {
+    display: *mut xlib::Display,
+}
+
+impl HiddenCursor {
+    pub fn new() -> Option<Self> {
+        // SAFETY: the display stays open for as long as the cursor is hidden, `drop` closes it
+        unsafe {
+            let display = xlib::XOpenDisplay(ptr::null());
+            if display.is_null() {
+                return None;
+            }
+            xfixes::XFixesHideCursor(display, xlib::XDefaultRootWindow(display));
+            xlib::XFlush(display);
+            Some(HiddenCursor { display })
+        }
+    }
+}
+
+impl Drop for HiddenCursor {
+    fn drop(&mut self) {
+        // SAFETY: the display was opened in `new` and isn't used anywhere else
+        unsafe {
+            xfixes::XFixesShowCursor(self.display, xlib::XDefaultRootWindow(self.display));
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
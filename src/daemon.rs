@@ -0,0 +1,38 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use device_query::{DeviceQuery, DeviceState, Keycode};
+
+/// Watches the keyboard for the `reopen` key while `kmgrid daemon` keeps the overlay hidden.
+/// The window stays alive in between, so showing it again skips starting up a new one.
+pub struct Activation {
+    requested: Arc<AtomicBool>,
+}
+
+impl Activation {
+    /// Wakes `ctx` up whenever one of `keys` goes down
+    pub fn spawn(keys: Vec<Keycode>, ctx: egui::Context) -> Self {
+        let requested = Arc::new(AtomicBool::new(false));
+        let flag = requested.clone();
+        std::thread::spawn(move || {
+            let device_state = DeviceState::new();
+            let mut was_down = false;
+            loop {
+                let down = device_state.get_keys().iter().any(|k| keys.contains(k));
+                if down && !was_down {
+                    flag.store(true, Ordering::Relaxed);
+                    ctx.request_repaint();
+                }
+                was_down = down;
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+        Activation { requested }
+    }
+
+    /// Whether the key went down since the last call
+    pub fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,587 @@
+use std::fmt::Write;
+
+use serde_json::{Map, Value};
+
+// What every setting does, in the order the TOML lists them. Tables get theirs above the header.
+const COMMENTS: &[(&str, &str)] = &[
+    (
+        "version",
+        "Version of the config format, older configs are upgraded when loaded",
+    ),
+    (
+        "primary_offset_x",
+        "Shifts the grid on the primary display right, e.g. past a side panel",
+    ),
+    (
+        "primary_offset_y",
+        "Shifts the grid on the primary display down, e.g. below a top bar",
+    ),
+    (
+        "scroll_speed",
+        "Lines per scroll step, a whole number above 0",
+    ),
+    (
+        "scroll_acceleration",
+        "{\"max\", \"ramp_ms\"}: held scroll keys grow from scroll_speed to max steps",
+    ),
+    (
+        "page_scroll_amount",
+        "Scroll steps of scroll_page_up and scroll_page_down",
+    ),
+    (
+        "movement_speed",
+        "Pixels per frame, or a string like \"40mm/s\" or \"25%/s\" of the display's width",
+    ),
+    (
+        "acceleration",
+        "Replaces movement_speed: {\"initial\", \"max\", \"ramp_ms\"} to speed up while held",
+    ),
+    (
+        "cell_speedup",
+        "{\"ramp_ms\", \"max_factor\"}: moving and scrolling speed up with the time in Cell mode",
+    ),
+    (
+        "sticky_speed_keys",
+        "Speed keys latch when tapped: once for the next move, twice for every move until a third",
+    ),
+    ("region_cols", "Regions across each display"),
+    ("region_rows", "Regions down each display"),
+    ("cell_cols", "Cells across each region"),
+    ("cell_rows", "Cells down each region"),
+    (
+        "reopen_window_ms",
+        "How long after closing the reopen key brings the overlay back where it was, 0 for never",
+    ),
+    (
+        "respect_fullscreen",
+        "Don't reopen over a fullscreen window like a presentation, except with reopen_anyway",
+    ),
+    (
+        "locale",
+        "Language of messages: \"en\", \"de\" or \"ru\", from the environment when unset",
+    ),
+    (
+        "layout",
+        "Default bindings: \"qwerty\", \"colemak\", \"dvorak\", \"azerty\", \"left_hand\" or \"right_hand\"",
+    ),
+    (
+        "layout_labels",
+        "Label keys with what the active keyboard layout types",
+    ),
+    (
+        "physical_keys",
+        "Match every key on its position, whatever the layout types with it",
+    ),
+    (
+        "skip_to_cell_display",
+        "Display skip_to_cell uses: \"follow_pointer\" or \"stay\" on the overlay's",
+    ),
+    (
+        "after_left_click",
+        "After left_click: \"refocus\" the overlay, \"yield\" focus or \"close\"",
+    ),
+    (
+        "after",
+        "Click action names to \"exit\", \"stay\", \"screen\" or \"narrow\" after clicking",
+    ),
+    (
+        "activate_before_click",
+        "Focus and raise the window under the pointer before every click",
+    ),
+    (
+        "multi_click_delay_ms",
+        "Time between the clicks of double_click and triple_click",
+    ),
+    (
+        "click_hold_ms",
+        "How long click keys need to be held before they click, 0 clicks right away",
+    ),
+    (
+        "held_button_timeout_ms",
+        "A left button kmgrid left down this long is released when it next starts",
+    ),
+    (
+        "region_click_modifier",
+        "Held with a region key, clicks its center: \"shift\", \"ctrl\", \"alt\" or \"none\"",
+    ),
+    (
+        "residual_context",
+        "Grid kept around the picked region: \"active\", \"bare\", \"grid\" or \"labels\"",
+    ),
+    (
+        "narrow_labels",
+        "Cells labeled in Narrow mode: \"middle_row\", \"all\" or \"corners\"",
+    ),
+    (
+        "touch_input",
+        "Tapping a region or cell picks it like its key",
+    ),
+    (
+        "hide_cursor",
+        "Hide the system cursor while the overlay is open",
+    ),
+    (
+        "sticky",
+        "Keep the overlay on screen when switching workspaces",
+    ),
+    (
+        "exclude_from_capture",
+        "Keep the overlay out of screenshots and screen sharing, where the system allows it",
+    ),
+    (
+        "recursive_narrowing",
+        "Keep splitting the picked cell into another grid until Enter",
+    ),
+    (
+        "count_prefixes",
+        "Digits typed before a movement key in Cell mode repeat the move that many times",
+    ),
+    (
+        "start_in_hints",
+        "Open in hint mode rather than Screen mode, see key_bindings.show_hints",
+    ),
+    (
+        "sequence_labels",
+        "Label every cell of Screen mode with its region and cell key, to pick both in one go",
+    ),
+    (
+        "scan_interval_ms",
+        "How long switch scanning highlights each item",
+    ),
+    (
+        "gaze_input",
+        "Eye or head tracker points from \"stdin\" or a UDP address",
+    ),
+    (
+        "hooks",
+        "Shell commands run on overlay events, with KMGRID_* variables describing them",
+    ),
+    ("hooks.on_open", "Run when the overlay opens"),
+    ("hooks.on_close", "Run when the overlay closes or hides"),
+    (
+        "hooks.on_click",
+        "Run on every click, with the action in KMGRID_ACTION",
+    ),
+    (
+        "command_socket",
+        "Unix socket path for kmgrid status and commands",
+    ),
+    (
+        "transparency_fallback",
+        "Without a compositor draw a \"screenshot\", \"dim\" the screen or \"none\"",
+    ),
+    (
+        "pointer_poll_ms",
+        "How often the pointer position is read, for skip_to_cell",
+    ),
+    (
+        "relative_only_windows",
+        "Window classes that only get the Cell mode keys, like games",
+    ),
+    (
+        "pointer_backend",
+        "How the pointer gets moved: \"xtest\" or \"uinput\"",
+    ),
+    (
+        "event_log",
+        "File to append every selection and click to, for suggest-layout",
+    ),
+    (
+        "targets",
+        "Named spots for search_targets: { name, x, y } in screen pixels",
+    ),
+    (
+        "input_devices",
+        "Pedals and such read through evdev: { path, bindings: { \"BTN_0\": action }, grab }",
+    ),
+    (
+        "key_bindings",
+        "Key names as egui has them, e.g. \"A\", \"Enter\", \";\" or \"AD01\"",
+    ),
+    ("key_bindings.region", "One key per region, row by row"),
+    (
+        "key_bindings.skip_to_cell",
+        "Narrows to the cell under the pointer",
+    ),
+    (
+        "key_bindings.prev_screen",
+        "Moves the grid to the previous display",
+    ),
+    (
+        "key_bindings.next_screen",
+        "Moves the grid to the next display",
+    ),
+    ("key_bindings.grid", "One key per cell, row by row"),
+    (
+        "key_bindings.micro_grid",
+        "Nine keys for the points of a 3x3 grid over the cell in Cell mode",
+    ),
+    (
+        "key_bindings.reopen",
+        "Brings the overlay back, see reopen_window_ms and kmgrid daemon",
+    ),
+    (
+        "key_bindings.reopen_anyway",
+        "Brings the overlay back even over a fullscreen window, see respect_fullscreen",
+    ),
+    (
+        "key_bindings.scan_select",
+        "Turns on switch scanning and picks the highlighted item",
+    ),
+    (
+        "key_bindings.gaze_select",
+        "Picks the region under the latest gaze_input point",
+    ),
+    (
+        "key_bindings.pause",
+        "Hides the overlay for a capture until pressed again",
+    ),
+    (
+        "key_bindings.move_window",
+        "Moves the focused window with the movement keys",
+    ),
+    (
+        "key_bindings.resize_window",
+        "Resizes the focused window from its nearest edge",
+    ),
+    (
+        "key_bindings.activate_window",
+        "Focuses and raises the window under the pointer",
+    ),
+    (
+        "key_bindings.next_profile",
+        "Switches to the next of the profiles",
+    ),
+    (
+        "key_bindings.back_to_narrow",
+        "Back to Narrow mode of the same region from Cell mode",
+    ),
+    (
+        "key_bindings.back_to_screen",
+        "Back to Screen mode from any other mode",
+    ),
+    (
+        "key_bindings.narrow_at_pointer",
+        "Narrow mode of the region under the pointer",
+    ),
+    (
+        "key_bindings.show_hints",
+        "Hint mode, every cell labeled at once, from any other mode",
+    ),
+    (
+        "key_bindings.mirror_layout",
+        "Flips every binding to its mirror image across the keyboard and back",
+    ),
+    (
+        "key_bindings.history_back",
+        "Jumps back to the position warped or clicked at before, in every mode",
+    ),
+    (
+        "key_bindings.history_forward",
+        "Jumps forward again after history_back, in every mode",
+    ),
+    (
+        "key_bindings.hint_keys",
+        "Keys making up the labels of hint mode, as few as tell the cells apart",
+    ),
+    (
+        "key_bindings.macros",
+        "Keys that run several actions: { key, steps, confirm }",
+    ),
+    (
+        "key_bindings.easy_keys",
+        "Easiest keys to reach first, for kmgrid suggest-layout",
+    ),
+    (
+        "key_bindings.modes",
+        "Per-mode overrides, action name to key",
+    ),
+    ("key_bindings.modes.screen", "While picking a region"),
+    ("key_bindings.modes.narrow", "While picking a cell"),
+    (
+        "key_bindings.modes.recurse",
+        "While picking within a cell, see recursive_narrowing",
+    ),
+    (
+        "key_bindings.modes.cell",
+        "While moving, clicking and scrolling",
+    ),
+    ("key_bindings.mouse", "Cell mode keys"),
+    (
+        "key_bindings.mouse.move_up",
+        "Moves the pointer up while held",
+    ),
+    (
+        "key_bindings.mouse.move_down",
+        "Moves the pointer down while held",
+    ),
+    (
+        "key_bindings.mouse.move_left",
+        "Moves the pointer left while held",
+    ),
+    (
+        "key_bindings.mouse.move_right",
+        "Moves the pointer right while held",
+    ),
+    (
+        "key_bindings.mouse.warp_up",
+        "Jumps the pointer halfway to the top of what's left of the display",
+    ),
+    (
+        "key_bindings.mouse.warp_down",
+        "Jumps the pointer halfway to the bottom of what's left of the display",
+    ),
+    (
+        "key_bindings.mouse.warp_left",
+        "Jumps the pointer halfway to the left of what's left of the display",
+    ),
+    (
+        "key_bindings.mouse.warp_right",
+        "Jumps the pointer halfway to the right of what's left of the display",
+    ),
+    (
+        "key_bindings.mouse.left_click",
+        "Left click, keeping the overlay open",
+    ),
+    (
+        "key_bindings.mouse.left_click_and_exit",
+        "Left click and close",
+    ),
+    ("key_bindings.mouse.middle_click", "Middle click and close"),
+    ("key_bindings.mouse.right_click", "Right click and close"),
+    (
+        "key_bindings.mouse.double_click",
+        "Double click and close, e.g. to select a word",
+    ),
+    (
+        "key_bindings.mouse.triple_click",
+        "Triple click and close, e.g. to select a line",
+    ),
+    (
+        "key_bindings.mouse.back_click",
+        "Clicks the back side button and closes, browsers go back a page",
+    ),
+    (
+        "key_bindings.mouse.forward_click",
+        "Clicks the forward side button and closes, browsers go forward a page",
+    ),
+    (
+        "key_bindings.mouse.drag_start",
+        "Presses the left button and goes back to Screen mode to pick where to drop",
+    ),
+    (
+        "key_bindings.mouse.drag_drop",
+        "Lets go of the button drag_start pressed and closes",
+    ),
+    (
+        "key_bindings.mouse.left_click_down",
+        "Presses the left button, to start a drag",
+    ),
+    (
+        "key_bindings.mouse.left_click_up",
+        "Releases the left button",
+    ),
+    ("key_bindings.mouse.scroll_up", "Scrolls up while held"),
+    ("key_bindings.mouse.scroll_down", "Scrolls down while held"),
+    ("key_bindings.mouse.scroll_left", "Scrolls left while held"),
+    (
+        "key_bindings.mouse.scroll_right",
+        "Scrolls right while held",
+    ),
+    (
+        "key_bindings.mouse.scroll_page_up",
+        "Scrolls up page_scroll_amount steps at once",
+    ),
+    (
+        "key_bindings.mouse.scroll_page_down",
+        "Scrolls down page_scroll_amount steps at once",
+    ),
+    (
+        "key_bindings.mouse.speed_quarter",
+        "Held with the movement keys, moves at a quarter",
+    ),
+    (
+        "key_bindings.mouse.speed_half",
+        "Held with the movement keys, moves at half speed",
+    ),
+    (
+        "key_bindings.mouse.speed_twice",
+        "Held with the movement keys, moves twice as fast",
+    ),
+    (
+        "key_bindings.mouse.speed_quadruple",
+        "Held with the movement keys, moves four times as fast",
+    ),
+    (
+        "style",
+        "Colors are [red, green, blue, alpha] from 0 to 255",
+    ),
+    ("style.region_line1", "Outer line around regions and cells"),
+    ("style.region_line2", "Inner line around regions and cells"),
+    (
+        "style.region_grid_line1",
+        "Lines between cells in Screen mode",
+    ),
+    ("style.region_grid_line2", "Second color of those lines"),
+    ("style.left_grid", "Even rows of regions"),
+    (
+        "style.right_grid",
+        "Odd rows of regions, and the picked region",
+    ),
+    ("style.scan_highlight", "Switch scanning highlight"),
+    ("style.gaze_highlight", "Region under the gaze_input point"),
+    (
+        "style.heatmap",
+        "Most picked regions and cells, fading with fewer picks",
+    ),
+    (
+        "style.window_border",
+        "Border along the overlay window's edges, none when unset",
+    ),
+    ("style.window_border_width", "Width of that border"),
+    ("style.label_min_size", "Smallest label font size"),
+    ("style.label_max_size", "Largest label font size"),
+    (
+        "style.auto_label_color",
+        "Black or white labels from the brightness under each one, instead of always white",
+    ),
+    (
+        "profiles",
+        "Named overrides of everything above, see --profile and next_profile",
+    ),
+];
+
+const HEADER: &str =
+    "# The built-in kmgrid config. Keep only what you want to change, the rest falls back to\n\
+                      # these defaults.\n";
+
+/// The config as JSON, with what every setting does above it in `//` comments, which kmgrid reads
+/// past
+pub fn json(config: &Value) -> String {
+    let mut out = HEADER.replace('#', "//");
+    if let Value::Object(table) = config {
+        write_object(&mut out, "", table, 0);
+    }
+    out.push('\n');
+    out
+}
+
+fn write_object(out: &mut String, path: &str, object: &Map<String, Value>, depth: usize) {
+    let indent = "    ".repeat(depth + 1);
+    out.push_str("{\n");
+    let keys = ordered_keys(path, object);
+    for (i, key) in keys.iter().enumerate() {
+        let full = join(path, key);
+        if let Some(comment) = comment(&full) {
+            writeln!(out, "{indent}// {comment}").unwrap();
+        }
+        write!(out, "{indent}{}: ", Value::from(key.as_str())).unwrap();
+        match &object[key.as_str()] {
+            Value::Object(inner) if !inner.is_empty() => write_object(out, &full, inner, depth + 1),
+            value => out.push_str(&value.to_string()),
+        }
+        out.push_str(if i + 1 < keys.len() { ",\n" } else { "\n" });
+    }
+    write!(out, "{}}}", "    ".repeat(depth)).unwrap();
+}
+
+/// The config as TOML, with what every setting does above it
+pub fn toml(config: &Value) -> String {
+    let mut out = HEADER.to_owned() + "\n";
+    if let Value::Object(table) = config {
+        write_table(&mut out, "", table);
+    }
+    out
+}
+
+fn write_table(out: &mut String, path: &str, table: &Map<String, Value>) {
+    let keys = ordered_keys(path, table);
+
+    // TOML needs the plain values of a table before its subtables
+    let is_table = |value: &Value| value.as_object().is_some_and(|o| !o.is_empty());
+    for key in keys.iter().filter(|key| !is_table(&table[key.as_str()])) {
+        if let Some(comment) = comment(&join(path, key)) {
+            writeln!(out, "# {comment}").unwrap();
+        }
+        match ::toml::Value::try_from(&table[key.as_str()]) {
+            Ok(value) => writeln!(out, "{key} = {value}").unwrap(),
+            // Unset, TOML has no null
+            Err(_) => writeln!(out, "# {key} is unset").unwrap(),
+        }
+    }
+    for key in keys.iter().filter(|key| is_table(&table[key.as_str()])) {
+        let full = join(path, key);
+        out.push('\n');
+        if let Some(comment) = comment(&full) {
+            writeln!(out, "# {comment}").unwrap();
+        }
+        writeln!(out, "[{full}]").unwrap();
+        write_table(out, &full, table[key.as_str()].as_object().unwrap());
+    }
+}
+
+// Keys in the order of `COMMENTS`, anything it doesn't list last
+fn ordered_keys<'a>(path: &str, object: &'a Map<String, Value>) -> Vec<&'a String> {
+    let mut keys: Vec<_> = object.keys().collect();
+    keys.sort_by_key(|key| {
+        let full = join(path, key);
+        COMMENTS
+            .iter()
+            .position(|(name, _)| *name == full)
+            .unwrap_or(COMMENTS.len())
+    });
+    keys
+}
+
+fn comment(path: &str) -> Option<&'static str> {
+    COMMENTS
+        .iter()
+        .find(|(name, _)| *name == path)
+        .map(|(_, comment)| *comment)
+}
+
+fn join(path: &str, key: &str) -> String {
+    match path {
+        "" => key.to_owned(),
+        path => format!("{path}.{key}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_default_setting_has_a_comment() {
+        let config =
+            serde_json::to_value(crate::parse_config(None, None, &Default::default()).unwrap())
+                .unwrap();
+        let mut missing = Vec::new();
+        let mut paths = vec![(String::new(), &config)];
+        while let Some((path, value)) = paths.pop() {
+            for (key, value) in value.as_object().into_iter().flatten() {
+                let path = join(&path, key);
+                if comment(&path).is_none() {
+                    missing.push(path.clone());
+                }
+                paths.push((path, value));
+            }
+        }
+        assert!(missing.is_empty(), "No comment for {missing:?}");
+    }
+
+    #[test]
+    fn reads_back_as_the_defaults() {
+        let config =
+            serde_json::to_value(crate::parse_config(None, None, &Default::default()).unwrap())
+                .unwrap();
+        for (name, text) in [
+            ("config.json", json(&config)),
+            ("config.toml", toml(&config)),
+        ] {
+            let read = crate::config::parse(std::path::Path::new(name), &text);
+            let read: crate::JsonConfig = serde_json::from_value(read.unwrap()).unwrap();
+            assert_eq!(serde_json::to_value(read).unwrap(), config, "{name}");
+        }
+    }
+}
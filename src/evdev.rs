@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+
+use crate::action::{Action, Trigger};
+
+const EV_KEY: u16 = 0x01;
+
+// From linux/input.h, `_IOW('E', 0x90, int)`
+const EVIOCGRAB: libc::c_ulong = 0x4004_4590;
+
+// Names of the codes pedals, macro pads and extra mouse buttons tend to send, the rest go by number
+const CODE_NAMES: &[(&str, u16)] = &[
+    ("BTN_0", 0x100),
+    ("BTN_1", 0x101),
+    ("BTN_2", 0x102),
+    ("BTN_3", 0x103),
+    ("BTN_4", 0x104),
+    ("BTN_5", 0x105),
+    ("BTN_6", 0x106),
+    ("BTN_7", 0x107),
+    ("BTN_8", 0x108),
+    ("BTN_9", 0x109),
+    ("BTN_LEFT", 0x110),
+    ("BTN_RIGHT", 0x111),
+    ("BTN_MIDDLE", 0x112),
+    ("BTN_SIDE", 0x113),
+    ("BTN_EXTRA", 0x114),
+    ("BTN_FORWARD", 0x115),
+    ("BTN_BACK", 0x116),
+    ("BTN_TASK", 0x117),
+    ("KEY_PROG1", 148),
+    ("KEY_PROG2", 149),
+    ("KEY_PROG3", 202),
+    ("KEY_PROG4", 203),
+    ("KEY_F13", 183),
+    ("KEY_F14", 184),
+    ("KEY_F15", 185),
+    ("KEY_F16", 186),
+    ("KEY_F17", 187),
+    ("KEY_F18", 188),
+    ("KEY_F19", 189),
+    ("KEY_F20", 190),
+    ("KEY_F21", 191),
+    ("KEY_F22", 192),
+    ("KEY_F23", 193),
+    ("KEY_F24", 194),
+];
+
+/// An extra input device read directly, like a foot pedal, with what its buttons do
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct JsonInputDevice {
+    /// Event device, best by its stable name under `/dev/input/by-id`
+    pub path: String,
+    /// Button or key, by its evdev name like `BTN_0` or `KEY_F13` or by number, to an action
+    /// name or `reopen`
+    pub bindings: BTreeMap<String, String>,
+    /// Keep the device's input from reaching anything else, e.g. a pedal that types F13
+    #[serde(default)]
+    pub grab: bool,
+}
+
+/// What a device button does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeviceAction {
+    /// Shows the hidden overlay of `kmgrid daemon`
+    Reopen,
+    Action(Action),
+}
+
+impl DeviceAction {
+    /// Fires every frame the button is down rather than once per press, like moving
+    pub fn while_held(self) -> bool {
+        matches!(self, DeviceAction::Action(action) if action.trigger() != Trigger::Press)
+    }
+}
+
+impl std::str::FromStr for DeviceAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reopen" => Ok(DeviceAction::Reopen),
+            action => action.parse().map(DeviceAction::Action),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputDevice {
+    pub path: String,
+    pub grab: bool,
+    pub bindings: Vec<(u16, DeviceAction)>,
+}
+
+impl JsonInputDevice {
+    pub fn transform(&self) -> InputDevice {
+        let bindings = self
+            .bindings
+            .iter()
+            .map(|(code, action)| {
+                let code = parse_code(code)
+                    .unwrap_or_else(|| panic!("Unable to parse input device code {code}"));
+                let action = action
+                    .parse()
+                    .unwrap_or_else(|err| panic!("Unable to parse action {action}: {err}"));
+                (code, action)
+            })
+            .collect();
+        InputDevice {
+            path: self.path.clone(),
+            grab: self.grab,
+            bindings,
+        }
+    }
+}
+
+/// Reads a code by its name from `CODE_NAMES` or as a number
+pub fn parse_code(name: &str) -> Option<u16> {
+    CODE_NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, code)| *code)
+        .or_else(|| name.parse().ok())
+}
+
+#[derive(Default)]
+struct Shared {
+    // Presses since the overlay last looked, by path
+    presses: Vec<(String, u16)>,
+    held: HashSet<(String, u16)>,
+    // Woken on every press, so a hidden daemon overlay notices `reopen`
+    waker: Option<egui::Context>,
+}
+
+/// Button presses from every configured input device, each read on a thread of its own. The
+/// devices are opened once, at start.
+#[derive(Clone, Default)]
+pub struct DeviceInput {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl DeviceInput {
+    pub fn spawn(devices: &[InputDevice]) -> Result<Self, String> {
+        let input = DeviceInput::default();
+        for device in devices {
+            let mut file = File::open(&device.path)
+                .map_err(|err| format!("Unable to open {}: {err}", device.path))?;
+            // SAFETY: EVIOCGRAB takes a plain int argument
+            if device.grab && unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGRAB, 1) } == -1 {
+                let err = io::Error::last_os_error();
+                return Err(format!("Unable to grab {}: {err}", device.path));
+            }
+
+            let path = device.path.clone();
+            let shared = input.shared.clone();
+            std::thread::spawn(move || {
+                let mut buf = [0; std::mem::size_of::<libc::input_event>()];
+                while file.read_exact(&mut buf).is_ok() {
+                    // SAFETY: the kernel writes whole `input_event`s, plain old data
+                    let event: libc::input_event =
+                        unsafe { std::ptr::read_unaligned(buf.as_ptr().cast()) };
+                    if event.type_ != EV_KEY {
+                        continue;
+                    }
+                    let mut shared = shared.lock().unwrap();
+                    let key = (path.clone(), event.code);
+                    match event.value {
+                        1 => {
+                            shared.presses.push(key.clone());
+                            shared.held.insert(key);
+                            if let Some(ctx) = &shared.waker {
+                                ctx.request_repaint();
+                            }
+                        }
+                        0 => {
+                            shared.held.remove(&key);
+                        }
+                        // Autorepeat
+                        _ => {}
+                    }
+                }
+                eprintln!("Stopped reading {path}");
+            });
+        }
+        Ok(input)
+    }
+
+    /// Repaints `ctx` on every press from now on
+    pub fn wake(&self, ctx: egui::Context) {
+        self.shared.lock().unwrap().waker = Some(ctx);
+    }
+
+    /// Presses since the last call, as the device's path and the code
+    pub fn take_presses(&self) -> Vec<(String, u16)> {
+        std::mem::take(&mut self.shared.lock().unwrap().presses)
+    }
+
+    pub fn is_held(&self, path: &str, code: u16) -> bool {
+        self.shared
+            .lock()
+            .unwrap()
+            .held
+            .contains(&(path.to_owned(), code))
+    }
+}
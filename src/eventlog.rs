@@ -0,0 +1,64 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One selection or click, a line in the event log
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Event {
+    /// Seconds since the Unix epoch
+    pub time: f64,
+    pub action: String,
+    pub display: usize,
+    pub region: i32,
+    pub cell: i32,
+    /// Where the pointer ended up, in screen coordinates
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Event {
+    pub fn now(action: String, display: usize, region: i32, cell: i32, x: f32, y: f32) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+        Event {
+            time,
+            action,
+            display,
+            region,
+            cell,
+            x,
+            y,
+        }
+    }
+}
+
+/// Opt-in local usage log, appended to as JSON lines so users can study how they use the grid.
+/// Nothing ever leaves the file.
+pub struct EventLog {
+    file: File,
+}
+
+impl EventLog {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(EventLog { file })
+    }
+
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        let line = serde_json::to_string(event)?;
+        writeln!(self.file, "{line}")
+    }
+}
+
+/// Every event logged so far, skipping lines that don't parse
+pub fn read(path: &str) -> io::Result<Vec<Event>> {
+    let file = File::open(path)?;
+    let mut events = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(event) = serde_json::from_str(&line?) {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
@@ -0,0 +1,58 @@
+use std::io::BufRead;
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+
+use egui::{pos2, Pos2};
+
+/// Latest point reported by an external eye / head tracker, in screen pixels. Trackers send one
+/// point per line (or UDP datagram) as `X Y` or `X,Y`.
+#[derive(Clone, Default)]
+pub struct GazeInput {
+    latest: Arc<Mutex<Option<Pos2>>>,
+}
+
+impl GazeInput {
+    /// Starts listening on `source`, either `stdin` or a UDP address like `127.0.0.1:4243`
+    pub fn spawn(source: &str) -> std::io::Result<Self> {
+        let gaze = GazeInput::default();
+        let latest = gaze.latest.clone();
+
+        if source == "stdin" {
+            std::thread::spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    let Ok(line) = line else { break };
+                    if let Some(point) = parse_point(&line) {
+                        *latest.lock().unwrap() = Some(point);
+                    }
+                }
+            });
+        } else {
+            let socket = UdpSocket::bind(source)?;
+            std::thread::spawn(move || {
+                let mut buf = [0; 256];
+                while let Ok(len) = socket.recv(&mut buf) {
+                    let text = String::from_utf8_lossy(&buf[..len]);
+                    if let Some(point) = text.lines().rev().find_map(parse_point) {
+                        *latest.lock().unwrap() = Some(point);
+                    }
+                }
+            });
+        }
+        Ok(gaze)
+    }
+
+    pub fn latest(&self) -> Option<Pos2> {
+        *self.latest.lock().unwrap()
+    }
+}
+
+fn parse_point(line: &str) -> Option<Pos2> {
+    let mut coords = line
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f32>().ok());
+    match (coords.next(), coords.next(), coords.next()) {
+        (Some(Some(x)), Some(Some(y)), None) => Some(pos2(x, y)),
+        _ => None,
+    }
+}
@@ -0,0 +1,244 @@
+use egui::{vec2, Pos2, Rect, Vec2};
+
+/// Columns and rows of regions on a display, and of cells in every region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridSize {
+    pub regions: [usize; 2],
+    pub cells: [usize; 2],
+}
+
+impl Default for GridSize {
+    /// 4x4 regions of 5x3 cells
+    fn default() -> Self {
+        GridSize {
+            regions: [4, 4],
+            cells: [5, 3],
+        }
+    }
+}
+
+impl GridSize {
+    pub fn region_count(&self) -> usize {
+        self.regions[0] * self.regions[1]
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells[0] * self.cells[1]
+    }
+}
+
+/// How a display is carved up: it's split into a grid of regions, and every region into a grid
+/// of cells. Regions and cells are numbered row by row from the top left, like the key bindings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridGeometry {
+    rect: Rect,
+    regions: [usize; 2],
+    cells: [usize; 2],
+}
+
+impl GridGeometry {
+    /// The default 4x4 regions of 5x3 cells grid, over a display whose top left corner is at
+    /// `origin` in the caller's coordinates (screen coordinates, or window coordinates shifted by
+    /// the display offset).
+    pub fn new(origin: Pos2, size: Vec2) -> Self {
+        GridGeometry::with_size(origin, size, GridSize::default())
+    }
+
+    /// Like `new`, with `grid` regions and cells
+    pub fn with_size(origin: Pos2, size: Vec2, grid: GridSize) -> Self {
+        GridGeometry {
+            rect: Rect::from_min_size(origin, size),
+            regions: grid.regions,
+            cells: grid.cells,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Columns and rows of regions
+    pub fn regions(&self) -> [usize; 2] {
+        self.regions
+    }
+
+    /// Columns and rows of cells within a region
+    pub fn cells(&self) -> [usize; 2] {
+        self.cells
+    }
+
+    pub fn region_count(&self) -> usize {
+        self.regions[0] * self.regions[1]
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells[0] * self.cells[1]
+    }
+
+    pub fn region_size(&self) -> Vec2 {
+        vec2(
+            self.rect.width() / self.regions[0] as f32,
+            self.rect.height() / self.regions[1] as f32,
+        )
+    }
+
+    pub fn cell_size(&self) -> Vec2 {
+        let region_size = self.region_size();
+        vec2(
+            region_size.x / self.cells[0] as f32,
+            region_size.y / self.cells[1] as f32,
+        )
+    }
+
+    pub fn region_rect(&self, region: usize) -> Rect {
+        let size = self.region_size();
+        let min = self.rect.min
+            + vec2(
+                size.x * (region % self.regions[0]) as f32,
+                size.y * (region / self.regions[0]) as f32,
+            );
+        Rect::from_min_size(min, size)
+    }
+
+    pub fn cell_rect(&self, region: usize, cell: usize) -> Rect {
+        let size = self.cell_size();
+        let min = self.region_rect(region).min
+            + vec2(
+                size.x * (cell % self.cells[0]) as f32,
+                size.y * (cell / self.cells[0]) as f32,
+            );
+        Rect::from_min_size(min, size)
+    }
+
+    pub fn cell_center(&self, region: usize, cell: usize) -> Pos2 {
+        self.cell_rect(region, cell).center()
+    }
+
+    /// The region and cell containing `point`, if it's on this display
+    pub fn locate(&self, point: Pos2) -> Option<(usize, usize)> {
+        if !self.rect.contains(point) {
+            return None;
+        }
+
+        // Points on the right or bottom edge still belong to the last column or row
+        let grid_pos = |pos: f32, size: f32, count: usize| ((pos / size) as usize).min(count - 1);
+
+        let rel = point - self.rect.min;
+        let region_size = self.region_size();
+        let region_x = grid_pos(rel.x, region_size.x, self.regions[0]);
+        let region_y = grid_pos(rel.y, region_size.y, self.regions[1]);
+
+        let rel = rel
+            - vec2(
+                region_x as f32 * region_size.x,
+                region_y as f32 * region_size.y,
+            );
+        let cell_size = self.cell_size();
+        let cell_x = grid_pos(rel.x, cell_size.x, self.cells[0]);
+        let cell_y = grid_pos(rel.y, cell_size.y, self.cells[1]);
+
+        Some((
+            region_x + region_y * self.regions[0],
+            cell_x + cell_y * self.cells[0],
+        ))
+    }
+
+    /// Every cell on the display as `(region, cell, rect)`, region by region
+    pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize, Rect)> + '_ {
+        (0..self.region_count()).flat_map(move |region| {
+            (0..self.cell_count()).map(move |cell| (region, cell, self.cell_rect(region, cell)))
+        })
+    }
+}
+
+/// The virtual desktop all displays are part of, in the coordinates the displays report. X always
+/// starts it at 0,0, but Windows keeps the primary display there and puts the others around it,
+/// at negative coordinates when they are left of or above it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Desktop {
+    rect: Rect,
+}
+
+impl Desktop {
+    /// The smallest desktop holding every display in `bounds`
+    pub fn spanning(bounds: impl IntoIterator<Item = Rect>) -> Self {
+        let rect = bounds
+            .into_iter()
+            .fold(Rect::NOTHING, |rect, b| rect.union(b));
+        Desktop {
+            rect: if rect.is_positive() { rect } else { Rect::ZERO },
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// The nearest pixel of the desktop to `point`
+    pub fn clamp(&self, point: Pos2) -> Pos2 {
+        point.clamp(
+            self.rect.min,
+            (self.rect.max - vec2(1.0, 1.0)).max(self.rect.min),
+        )
+    }
+
+    /// `point` counted from the top left corner of the desktop, the way absolute pointer devices
+    /// count, which can't go negative
+    pub fn to_device(&self, point: Pos2) -> Pos2 {
+        (self.clamp(point) - self.rect.min).to_pos2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    // A 1920x1080 primary display with a 1280x1024 one to its left, like Windows lays them out
+    fn left_of_primary() -> [Rect; 2] {
+        [
+            Rect::from_min_size(pos2(0.0, 0.0), vec2(1920.0, 1080.0)),
+            Rect::from_min_size(pos2(-1280.0, -200.0), vec2(1280.0, 1024.0)),
+        ]
+    }
+
+    #[test]
+    fn desktop_spans_negative_origins() {
+        let desktop = Desktop::spanning(left_of_primary());
+        assert_eq!(
+            desktop.rect(),
+            Rect::from_min_max(pos2(-1280.0, -200.0), pos2(1920.0, 1080.0))
+        );
+        assert_eq!(desktop.to_device(pos2(-1280.0, -200.0)), pos2(0.0, 0.0));
+        assert_eq!(desktop.to_device(pos2(0.0, 0.0)), pos2(1280.0, 200.0));
+        assert_eq!(desktop.to_device(pos2(5000.0, -900.0)), pos2(3199.0, 0.0));
+        assert_eq!(Desktop::spanning([]).rect(), Rect::ZERO);
+    }
+
+    #[test]
+    fn grid_works_on_displays_at_negative_origins() {
+        let [_, left] = left_of_primary();
+        let geometry = GridGeometry::new(left.min, left.size());
+        for (region, cell, rect) in geometry.iter_cells() {
+            assert!(left.contains_rect(rect));
+            assert_eq!(geometry.locate(rect.center()), Some((region, cell)));
+        }
+        assert_eq!(geometry.locate(left.min), Some((0, 0)));
+        assert_eq!(geometry.locate(pos2(100.0, 0.0)), None);
+    }
+
+    #[test]
+    fn desktop_spans_displays_above_the_primary() {
+        // A 1440x900 primary display, a 1920x1080 one above it and a 1280x800 one to the right
+        // with their bottom edges lined up
+        let primary = Rect::from_min_size(pos2(0.0, 0.0), vec2(1440.0, 900.0));
+        let above = Rect::from_min_size(pos2(-240.0, -1080.0), vec2(1920.0, 1080.0));
+        let right = Rect::from_min_size(pos2(1440.0, 100.0), vec2(1280.0, 800.0));
+        let desktop = Desktop::spanning([primary, above, right]);
+        assert_eq!(
+            desktop.rect(),
+            Rect::from_min_max(pos2(-240.0, -1080.0), pos2(2720.0, 900.0))
+        );
+        assert_eq!(desktop.to_device(pos2(0.0, 0.0)), pos2(240.0, 1080.0));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::action::Action;
+use crate::eventlog::Event;
+
+/// How often each region and cell got picked according to the event log
+#[derive(Default)]
+pub struct Heatmap {
+    // By display and region
+    regions: HashMap<(usize, usize), u32>,
+    // By display, region and cell
+    cells: HashMap<(usize, usize, usize), u32>,
+}
+
+impl Heatmap {
+    pub fn new(events: &[Event]) -> Self {
+        let mut heatmap = Heatmap::default();
+        for event in events {
+            let Ok(action) = Action::from_str(&event.action) else {
+                continue;
+            };
+            let region = event.region as usize;
+            match action {
+                Action::SelectRegion(_) | Action::ClickRegion(_) | Action::GazeSelect => {
+                    *heatmap.regions.entry((event.display, region)).or_default() += 1;
+                }
+                Action::SelectCell(_) | Action::SkipToCell if event.cell >= 0 => {
+                    let key = (event.display, region, event.cell as usize);
+                    *heatmap.cells.entry(key).or_default() += 1;
+                }
+                _ => {}
+            }
+        }
+        heatmap
+    }
+
+    /// Share of the busiest region on the display, from 0 to 1
+    pub fn region_heat(&self, display: usize, region: usize) -> f32 {
+        let max = self
+            .regions
+            .iter()
+            .filter(|((d, _), _)| *d == display)
+            .map(|(_, n)| *n)
+            .max();
+        let count = self.regions.get(&(display, region));
+        heat(count, max)
+    }
+
+    /// Share of the busiest cell in the region, from 0 to 1
+    pub fn cell_heat(&self, display: usize, region: usize, cell: usize) -> f32 {
+        let max = self
+            .cells
+            .iter()
+            .filter(|((d, r, _), _)| *d == display && *r == region)
+            .map(|(_, n)| *n)
+            .max();
+        let count = self.cells.get(&(display, region, cell));
+        heat(count, max)
+    }
+
+    /// Hits of each of the `count` regions, over all displays
+    pub fn region_totals(&self, count: usize) -> Vec<u32> {
+        let mut totals = vec![0; count];
+        for ((_, region), n) in &self.regions {
+            if let Some(total) = totals.get_mut(*region) {
+                *total += n;
+            }
+        }
+        totals
+    }
+
+    /// Hits of each of the `count` cells, over all displays and regions
+    pub fn cell_totals(&self, count: usize) -> Vec<u32> {
+        let mut totals = vec![0; count];
+        for ((_, _, cell), n) in &self.cells {
+            if let Some(total) = totals.get_mut(*cell) {
+                *total += n;
+            }
+        }
+        totals
+    }
+}
+
+fn heat(count: Option<&u32>, max: Option<u32>) -> f32 {
+    match (count, max) {
+        (Some(count), Some(max)) if max > 0 => *count as f32 / max as f32,
+        _ => 0.0,
+    }
+}
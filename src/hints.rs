@@ -0,0 +1,66 @@
+/// Labels for `count` hints out of `keys` hint keys, each a list of key indices. All labels are
+/// as long as it takes to tell `count` of them apart, so none is the start of another and every
+/// one is complete as soon as that many keys are typed. In order, so hints next to each other
+/// share their first keys.
+pub fn labels(count: usize, keys: usize) -> Vec<Vec<usize>> {
+    if keys < 2 {
+        return (0..count.min(keys)).map(|i| vec![i]).collect();
+    }
+    let mut len = 1;
+    while keys.pow(len) < count {
+        len += 1;
+    }
+    (0..count)
+        .map(|i| {
+            (0..len)
+                .rev()
+                .map(|digit| i / keys.pow(digit) % keys)
+                .collect()
+        })
+        .collect()
+}
+
+/// Indices of the labels that start with what has been typed so far
+pub fn matching<'a>(
+    labels: &'a [Vec<usize>],
+    typed: &'a [usize],
+) -> impl Iterator<Item = usize> + 'a {
+    (0..labels.len()).filter(|&i| labels[i].starts_with(typed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_are_unique_and_none_starts_another() {
+        let labels = labels(240, 16);
+        assert_eq!(labels.len(), 240);
+        assert!(labels.iter().all(|label| label.len() == 2));
+        for (i, a) in labels.iter().enumerate() {
+            for b in &labels[i + 1..] {
+                assert!(!b.starts_with(a) && !a.starts_with(b));
+            }
+        }
+    }
+
+    #[test]
+    fn labels_grow_with_the_hint_count() {
+        assert_eq!(labels(3, 4), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(labels(5, 4)[4], vec![1, 0]);
+        assert_eq!(labels(17, 4)[16], vec![1, 0, 0]);
+        assert_eq!(labels(3, 1), vec![vec![0]]);
+    }
+
+    #[test]
+    fn typing_narrows_down_the_hints() {
+        let labels = labels(12, 4);
+        assert_eq!(matching(&labels, &[]).count(), 12);
+        assert_eq!(
+            matching(&labels, &[1]).collect::<Vec<_>>(),
+            vec![4, 5, 6, 7]
+        );
+        assert_eq!(matching(&labels, &[1, 2]).collect::<Vec<_>>(), vec![6]);
+        assert_eq!(matching(&labels, &[3]).count(), 0);
+    }
+}
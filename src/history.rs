@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use egui::Pos2;
+
+// Positions kept, the oldest go first
+const LEN: usize = 50;
+
+/// Positions the pointer got warped or clicked at, oldest first, to go back and forward through
+/// like the pages of a browser
+#[derive(Debug, Default)]
+pub struct PositionHistory {
+    positions: VecDeque<Pos2>,
+    // The position gone back to, none while not going through the history
+    index: Option<usize>,
+}
+
+impl PositionHistory {
+    pub fn new(positions: impl IntoIterator<Item = Pos2>) -> Self {
+        let mut history = PositionHistory::default();
+        for pos in positions {
+            history.push(pos);
+        }
+        history
+    }
+
+    /// Adds a position after the current one, dropping any gone back past like a browser does
+    pub fn push(&mut self, pos: Pos2) {
+        if let Some(i) = self.index.take() {
+            self.positions.truncate(i + 1);
+        }
+        if self.positions.back() == Some(&pos) {
+            return;
+        }
+        self.positions.push_back(pos);
+        if self.positions.len() > LEN {
+            self.positions.pop_front();
+        }
+    }
+
+    /// The position before the current one, or the latest when not going through the history
+    pub fn back(&mut self) -> Option<Pos2> {
+        let i = match self.index {
+            None => self.positions.len().checked_sub(1)?,
+            Some(i) => i.checked_sub(1)?,
+        };
+        self.index = Some(i);
+        Some(self.positions[i])
+    }
+
+    pub fn forward(&mut self) -> Option<Pos2> {
+        let i = self.index? + 1;
+        let pos = *self.positions.get(i)?;
+        self.index = Some(i);
+        Some(pos)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Pos2> + '_ {
+        self.positions.iter().copied()
+    }
+
+    /// The position gone back to, counting from 1, and how many there are
+    pub fn place(&self) -> Option<(usize, usize)> {
+        self.index.map(|i| (i + 1, self.positions.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    #[test]
+    fn goes_back_and_forward() {
+        let mut history = PositionHistory::new([pos2(1.0, 1.0), pos2(2.0, 2.0), pos2(3.0, 3.0)]);
+        assert_eq!(history.forward(), None);
+        assert_eq!(history.back(), Some(pos2(3.0, 3.0)));
+        assert_eq!(history.back(), Some(pos2(2.0, 2.0)));
+        assert_eq!(history.place(), Some((2, 3)));
+        assert_eq!(history.back(), Some(pos2(1.0, 1.0)));
+        assert_eq!(history.back(), None);
+        assert_eq!(history.forward(), Some(pos2(2.0, 2.0)));
+        assert_eq!(history.forward(), Some(pos2(3.0, 3.0)));
+        assert_eq!(history.forward(), None);
+    }
+
+    #[test]
+    fn pushing_after_going_back_drops_what_came_after() {
+        let mut history = PositionHistory::new([pos2(1.0, 1.0), pos2(2.0, 2.0), pos2(3.0, 3.0)]);
+        history.back();
+        history.back();
+        history.push(pos2(4.0, 4.0));
+        assert_eq!(history.place(), None);
+        assert_eq!(
+            history.positions().collect::<Vec<_>>(),
+            vec![pos2(1.0, 1.0), pos2(2.0, 2.0), pos2(4.0, 4.0)]
+        );
+    }
+
+    #[test]
+    fn keeps_the_latest_positions_without_repeats() {
+        let mut history = PositionHistory::new((0..LEN + 10).map(|i| pos2(i as f32, 0.0)));
+        history.push(pos2((LEN + 9) as f32, 0.0));
+        assert_eq!(history.positions().count(), LEN);
+        assert_eq!(history.positions().next(), Some(pos2(10.0, 0.0)));
+    }
+}
@@ -0,0 +1,59 @@
+use std::process::{Command, Stdio};
+
+/// Shell commands run when the overlay opens, closes or clicks, e.g. to pause a screen dimmer
+/// while the grid is up
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Hooks {
+    #[serde(default)]
+    pub on_open: Option<String>,
+    #[serde(default)]
+    pub on_close: Option<String>,
+    #[serde(default)]
+    pub on_click: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    Open,
+    Close,
+    Click,
+}
+
+impl HookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::Open => "open",
+            HookEvent::Close => "close",
+            HookEvent::Click => "click",
+        }
+    }
+}
+
+impl Hooks {
+    /// Starts the command for `event` with `sh -c`, if there is one, without waiting for it.
+    /// `vars` and `KMGRID_EVENT` go into its environment.
+    pub fn run(&self, event: HookEvent, vars: &[(&str, String)]) {
+        let command = match event {
+            HookEvent::Open => &self.on_open,
+            HookEvent::Close => &self.on_close,
+            HookEvent::Click => &self.on_click,
+        };
+        let Some(command) = command else {
+            return;
+        };
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("KMGRID_EVENT", event.name())
+            .envs(vars.iter().map(|(name, value)| (name, value)))
+            .stdin(Stdio::null())
+            .spawn();
+        match child {
+            // Reaped on a thread of its own, so slow hooks hold nothing up
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(err) => eprintln!("Unable to run the on_{} hook: {err}", event.name()),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt::Display;
+
+/// Languages the on-screen text is available in. Key labels are not translated, they always
+/// show the bound key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    De,
+    Ru,
+}
+
+/// Every piece of text kmgrid draws on the overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    MouseError,
+    DisplayInfoError,
+    DisplayChanged,
+    ScanLeftClick,
+    ScanRightClick,
+    ScanMiddleClick,
+    ScanBack,
+    UnboundKey,
+    OffsetClamped,
+    ConfirmMacro,
+    SearchPrompt,
+    EventLogError,
+    HeatmapError,
+    HeatmapNoLog,
+    CaptureNotExcluded,
+    ConfigReloaded,
+    ConfigNotReloaded,
+    MovingWindow,
+    ResizingWindow,
+    NoFocusedWindow,
+    NoProfiles,
+    ProfileSwitched,
+    ProfileCleared,
+    PointerStalledXtest,
+    PointerStalledUinput,
+    TutorialRegion,
+    TutorialCell,
+    TutorialClick,
+    TutorialHints,
+    Dragging,
+    NoPointerInput,
+}
+
+impl Locale {
+    /// Picks the configured locale, falling back to the environment (`LC_ALL`, `LC_MESSAGES`,
+    /// `LANG`) and then to English
+    pub fn detect(configured: Option<&str>) -> Locale {
+        let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|val| !val.is_empty());
+
+        configured
+            .map(str::to_owned)
+            .or(from_env)
+            .and_then(|tag| Locale::from_tag(&tag))
+            .unwrap_or(Locale::En)
+    }
+
+    /// Parses tags like `de`, `de_DE` or `ru_RU.UTF-8`
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let lang = tag.split(['_', '-', '.']).next()?.to_lowercase();
+        match lang.as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "ru" => Some(Locale::Ru),
+            _ => None,
+        }
+    }
+
+    /// The translated text, with each `{}` replaced by the next argument
+    pub fn tr(self, msg: Msg, args: &[&dyn Display]) -> String {
+        let mut text = self.text(msg).to_owned();
+        for arg in args {
+            text = text.replacen("{}", &arg.to_string(), 1);
+        }
+        text
+    }
+
+    fn text(self, msg: Msg) -> &'static str {
+        match (self, msg) {
+            (Locale::En, Msg::MouseError) => "Failed to manipulate mouse: {}",
+            (Locale::En, Msg::DisplayInfoError) => "Unable to get display info: {}",
+            (Locale::En, Msg::DisplayChanged) => "Display layout changed",
+            (Locale::En, Msg::ScanLeftClick) => "Left click",
+            (Locale::En, Msg::ScanRightClick) => "Right click",
+            (Locale::En, Msg::ScanMiddleClick) => "Middle click",
+            (Locale::En, Msg::ScanBack) => "Back",
+            (Locale::En, Msg::UnboundKey) => "{} is not bound",
+            (Locale::En, Msg::OffsetClamped) => "Offset doesn't fit display {}, using {}",
+            (Locale::En, Msg::ConfirmMacro) => "Enter: run the marked clicks, Escape: cancel",
+            (Locale::En, Msg::SearchPrompt) => "Go to: {}",
+            (Locale::En, Msg::EventLogError) => "Unable to write the event log: {}",
+            (Locale::En, Msg::HeatmapError) => "Unable to read the event log: {}",
+            (Locale::En, Msg::HeatmapNoLog) => "The heatmap needs event_log in the config",
+            (Locale::En, Msg::CaptureNotExcluded) => {
+                "The overlay can't be kept out of captures here, pause it instead"
+            }
+            (Locale::En, Msg::ConfigReloaded) => "Config reloaded",
+            (Locale::En, Msg::MovingWindow) => {
+                "Moving the window: movement keys, Enter to place it, Escape to put it back"
+            }
+            (Locale::En, Msg::ResizingWindow) => {
+                "Resizing the window: movement keys, Enter to keep the size, Escape to undo"
+            }
+            (Locale::En, Msg::NoFocusedWindow) => "No focused window",
+            (Locale::En, Msg::NoProfiles) => "The config has no profiles",
+            (Locale::En, Msg::ProfileSwitched) => "Profile {}",
+            (Locale::En, Msg::ProfileCleared) => "No profile",
+            (Locale::En, Msg::PointerStalledXtest) => {
+                "The pointer ignores kmgrid. On Wayland, try \"pointer_backend\": \"uinput\""
+            }
+            (Locale::En, Msg::PointerStalledUinput) => {
+                "The pointer ignores the uinput device, check that the display server picks up new input devices"
+            }
+            (Locale::En, Msg::TutorialRegion) => "Welcome to kmgrid! Press the key shown in a region to zoom in on it",
+            (Locale::En, Msg::TutorialCell) => "Now press the key of a cell to put the pointer there",
+            (Locale::En, Msg::TutorialClick) => "Press {} to click there and close, or Escape to close without clicking",
+            (Locale::En, Msg::Dragging) => "Dragging: pick where to drop it, then press {}",
+            (Locale::En, Msg::NoPointerInput) => {
+                "kmgrid can't move the pointer (see the terminal), clicks print their position instead"
+            }
+            (Locale::En, Msg::TutorialHints) => "Type the letters of a label to put the pointer there",
+            (Locale::En, Msg::ConfigNotReloaded) => {
+                "Config not reloaded, it has {} problems (see the terminal)"
+            }
+
+            (Locale::De, Msg::MouseError) => "Maus konnte nicht gesteuert werden: {}",
+            (Locale::De, Msg::DisplayInfoError) => "Bildschirminformationen nicht verfügbar: {}",
+            (Locale::De, Msg::DisplayChanged) => "Bildschirmanordnung geändert",
+            (Locale::De, Msg::ScanLeftClick) => "Linksklick",
+            (Locale::De, Msg::ScanRightClick) => "Rechtsklick",
+            (Locale::De, Msg::ScanMiddleClick) => "Mittelklick",
+            (Locale::De, Msg::ScanBack) => "Zurück",
+            (Locale::De, Msg::UnboundKey) => "{} ist nicht belegt",
+            (Locale::De, Msg::OffsetClamped) => {
+                "Versatz passt nicht auf Bildschirm {}, verwende {}"
+            }
+            (Locale::De, Msg::SearchPrompt) => "Gehe zu: {}",
+            (Locale::De, Msg::ConfirmMacro) => {
+                "Enter: markierte Klicks ausführen, Escape: abbrechen"
+            }
+            (Locale::De, Msg::EventLogError) => "Ereignisprotokoll nicht beschreibbar: {}",
+            (Locale::De, Msg::HeatmapError) => "Ereignisprotokoll nicht lesbar: {}",
+            (Locale::De, Msg::HeatmapNoLog) => "Die Heatmap braucht event_log in der Konfiguration",
+            (Locale::De, Msg::CaptureNotExcluded) => {
+                "Das Overlay lässt sich hier nicht von Aufnahmen ausnehmen, stattdessen pausieren"
+            }
+            (Locale::De, Msg::ConfigReloaded) => "Konfiguration neu geladen",
+            (Locale::De, Msg::MovingWindow) => {
+                "Fenster verschieben: Bewegungstasten, Enter zum Ablegen, Escape zum Zurücksetzen"
+            }
+            (Locale::De, Msg::ResizingWindow) => {
+                "Fenstergröße ändern: Bewegungstasten, Enter zum Übernehmen, Escape zum Zurücksetzen"
+            }
+            (Locale::De, Msg::NoFocusedWindow) => "Kein fokussiertes Fenster",
+            (Locale::De, Msg::NoProfiles) => "Die Konfiguration hat keine Profile",
+            (Locale::De, Msg::ProfileSwitched) => "Profil {}",
+            (Locale::De, Msg::ProfileCleared) => "Kein Profil",
+            (Locale::De, Msg::PointerStalledXtest) => {
+                "Der Zeiger ignoriert kmgrid. Unter Wayland \"pointer_backend\": \"uinput\" versuchen"
+            }
+            (Locale::De, Msg::PointerStalledUinput) => {
+                "Der Zeiger ignoriert das uinput-Gerät, nimmt der Display-Server neue Eingabegeräte an?"
+            }
+            (Locale::De, Msg::TutorialRegion) => "Willkommen bei kmgrid! Die Taste in einem Bereich drücken, um ihn heranzuholen",
+            (Locale::De, Msg::TutorialCell) => "Jetzt die Taste einer Zelle drücken, um den Zeiger dorthin zu setzen",
+            (Locale::De, Msg::TutorialClick) => "{} drücken, um dort zu klicken und zu schließen, oder Escape ohne Klick",
+            (Locale::De, Msg::Dragging) => "Ziehen: das Ziel wählen, dann {} drücken",
+            (Locale::De, Msg::NoPointerInput) => {
+                "kmgrid kann den Zeiger nicht bewegen (siehe Terminal), Klicks geben stattdessen ihre Position aus"
+            }
+            (Locale::De, Msg::TutorialHints) => "Die Buchstaben einer Markierung tippen, um den Zeiger dorthin zu setzen",
+            (Locale::De, Msg::ConfigNotReloaded) => {
+                "Konfiguration nicht neu geladen, sie hat {} Fehler (siehe Terminal)"
+            }
+
+            (Locale::Ru, Msg::MouseError) => "Не удалось управлять мышью: {}",
+            (Locale::Ru, Msg::DisplayInfoError) => "Не удалось получить данные о дисплеях: {}",
+            (Locale::Ru, Msg::DisplayChanged) => "Конфигурация дисплеев изменилась",
+            (Locale::Ru, Msg::ScanLeftClick) => "Левый клик",
+            (Locale::Ru, Msg::ScanRightClick) => "Правый клик",
+            (Locale::Ru, Msg::ScanMiddleClick) => "Средний клик",
+            (Locale::Ru, Msg::ScanBack) => "Назад",
+            (Locale::Ru, Msg::UnboundKey) => "Клавиша {} не назначена",
+            (Locale::Ru, Msg::OffsetClamped) => {
+                "Смещение не помещается на дисплей {}, используется {}"
+            }
+            (Locale::Ru, Msg::ConfirmMacro) => "Enter: выполнить отмеченные клики, Escape: отмена",
+            (Locale::Ru, Msg::SearchPrompt) => "Перейти к: {}",
+            (Locale::Ru, Msg::EventLogError) => "Не удалось записать журнал событий: {}",
+            (Locale::Ru, Msg::HeatmapError) => "Не удалось прочитать журнал событий: {}",
+            (Locale::Ru, Msg::HeatmapNoLog) => "Для тепловой карты нужен event_log в конфигурации",
+            (Locale::Ru, Msg::CaptureNotExcluded) => {
+                "Здесь оверлей нельзя скрыть от записи экрана, поставьте его на паузу"
+            }
+            (Locale::Ru, Msg::ConfigReloaded) => "Конфигурация перезагружена",
+            (Locale::Ru, Msg::MovingWindow) => {
+                "Перемещение окна: клавиши движения, Enter — оставить, Escape — вернуть"
+            }
+            (Locale::Ru, Msg::ResizingWindow) => {
+                "Изменение размера окна: клавиши движения, Enter — оставить, Escape — отменить"
+            }
+            (Locale::Ru, Msg::NoFocusedWindow) => "Нет активного окна",
+            (Locale::Ru, Msg::NoProfiles) => "В конфигурации нет профилей",
+            (Locale::Ru, Msg::ProfileSwitched) => "Профиль {}",
+            (Locale::Ru, Msg::ProfileCleared) => "Без профиля",
+            (Locale::Ru, Msg::PointerStalledXtest) => {
+                "Указатель не слушается kmgrid. В Wayland попробуйте \"pointer_backend\": \"uinput\""
+            }
+            (Locale::Ru, Msg::PointerStalledUinput) => {
+                "Указатель не слушается устройства uinput, проверьте, что дисплейный сервер подхватывает новые устройства ввода"
+            }
+            (Locale::Ru, Msg::TutorialRegion) => "Добро пожаловать в kmgrid! Нажмите клавишу в области, чтобы приблизить её",
+            (Locale::Ru, Msg::TutorialCell) => "Теперь нажмите клавишу ячейки, чтобы поставить туда указатель",
+            (Locale::Ru, Msg::TutorialClick) => "Нажмите {}, чтобы щёлкнуть и закрыть, или Escape, чтобы закрыть без щелчка",
+            (Locale::Ru, Msg::Dragging) => "Перетаскивание: выберите, куда отпустить, и нажмите {}",
+            (Locale::Ru, Msg::NoPointerInput) => {
+                "kmgrid не может двигать указатель (см. терминал), щелчки вместо этого выводят свою позицию"
+            }
+            (Locale::Ru, Msg::TutorialHints) => "Наберите буквы метки, чтобы поставить туда указатель",
+            (Locale::Ru, Msg::ConfigNotReloaded) => {
+                "Конфигурация не перезагружена, ошибок: {} (подробности в терминале)"
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Color32, FontId, Painter, Pos2, Rect, Rounding, ViewportBuilder, ViewportId};
+
+const IDENTIFY_DURATION: Duration = Duration::from_secs(3);
+
+/// Every display's index and output name in huge text on that display, to tell which is which
+/// when writing per-display config
+pub struct Identify {
+    // Bounds in screen coordinates, and the text to show there
+    labels: Vec<(Rect, String)>,
+    until: Instant,
+}
+
+impl Identify {
+    /// `displays` has the bounds and output name of each display, in kmgrid's display order
+    pub fn new(displays: impl IntoIterator<Item = (Rect, String)>) -> Self {
+        let labels = displays
+            .into_iter()
+            .enumerate()
+            .map(|(i, (rect, name))| (rect, format!("{i}\n{name}")))
+            .collect();
+        Identify {
+            labels,
+            until: Instant::now() + IDENTIFY_DURATION,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        Instant::now() >= self.until
+    }
+
+    /// Draws the label of display `display` into a window covering it, `window` in the window's
+    /// own coordinates
+    pub fn draw(&self, display: usize, painter: &Painter, window: Rect) {
+        if let Some((_, label)) = self.labels.get(display) {
+            draw_label(painter, window, label);
+        }
+    }
+
+    /// Draws the label of display `own` into the calling window, which covers `window` in its
+    /// own coordinates, and opens a window with the label on every other display. The other
+    /// windows close on the first frame this isn't called anymore.
+    pub fn show(&self, ctx: &egui::Context, own: usize, painter: &Painter, window: Rect) {
+        for (i, (rect, label)) in self.labels.iter().enumerate() {
+            if i == own {
+                draw_label(painter, window, label);
+                continue;
+            }
+
+            let builder = ViewportBuilder::default()
+                .with_title("kmgrid identify")
+                .with_decorations(false)
+                .with_window_type(egui::X11WindowType::Utility)
+                .with_mouse_passthrough(true)
+                .with_always_on_top()
+                .with_transparent(true)
+                .with_position(rect.min)
+                .with_inner_size(rect.size());
+            let id = ViewportId::from_hash_of(("identify", i));
+            ctx.show_viewport_immediate(id, builder, |ctx, _| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::none())
+                    .show(ctx, |ui| {
+                        let window = Rect::from_min_size(Pos2::ZERO, rect.size());
+                        draw_label(ui.painter(), window, label);
+                    });
+            });
+        }
+    }
+}
+
+fn draw_label(painter: &Painter, window: Rect, label: &str) {
+    let font = FontId::proportional(window.height() / 6.0);
+    let galley = painter.layout_no_wrap(label.to_owned(), font, Color32::WHITE);
+    let rect = Align2::CENTER_CENTER
+        .anchor_size(window.center(), galley.size())
+        .expand(window.height() / 30.0);
+    painter.rect_filled(rect, Rounding::same(12.0), Color32::from_black_alpha(200));
+    painter.galley(
+        Align2::CENTER_CENTER
+            .anchor_size(window.center(), galley.size())
+            .min,
+        galley,
+        Color32::WHITE,
+    );
+}
+
+/// `kmgrid identify`: shows the labels without the overlay, then exits
+pub fn run(displays: Vec<(Rect, String)>) -> eframe::Result {
+    let Some((first, _)) = displays.first().cloned() else {
+        return Ok(());
+    };
+    let options = eframe::NativeOptions {
+        viewport: ViewportBuilder::default()
+            .with_decorations(false)
+            .with_window_type(egui::X11WindowType::Utility)
+            .with_mouse_passthrough(true)
+            .with_always_on_top()
+            .with_transparent(true)
+            .with_position(first.min)
+            .with_inner_size(first.size()),
+        ..Default::default()
+    };
+    let app = IdentifyApp {
+        identify: Identify::new(displays),
+        size: first.size(),
+    };
+    eframe::run_native(
+        "kmgrid identify",
+        options,
+        Box::new(|_cc| Ok(Box::new(app))),
+    )
+}
+
+struct IdentifyApp {
+    identify: Identify,
+    // Of the first display, which the main window covers
+    size: egui::Vec2,
+}
+
+impl eframe::App for IdentifyApp {
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        egui::Rgba::TRANSPARENT.to_array()
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.identify.is_over() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
+        }
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                let window = Rect::from_min_size(Pos2::ZERO, self.size);
+                self.identify.show(ctx, 0, ui.painter(), window);
+            });
+        ctx.request_repaint();
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use device_query::{DeviceQuery, DeviceState, Keycode};
+use egui::{Event, Modifiers, ViewportBuilder};
+
+// Events kept on screen, the terminal has all of them
+const HISTORY: usize = 40;
+
+/// `kmgrid input-debug`: a plain window listing every key event egui gets, exactly as it gets
+/// it, and the keys the X server reports held. Everything is printed too, for bug reports about
+/// keys that do nothing.
+pub fn run() -> eframe::Result {
+    let options = eframe::NativeOptions {
+        viewport: ViewportBuilder::default()
+            .with_title("kmgrid input-debug")
+            .with_inner_size([900.0, 700.0]),
+        ..Default::default()
+    };
+    let app = InputDebug {
+        device_state: DeviceState::new(),
+        events: VecDeque::new(),
+        held: Vec::new(),
+    };
+    eframe::run_native(
+        "kmgrid input-debug",
+        options,
+        Box::new(|_cc| Ok(Box::new(app))),
+    )
+}
+
+struct InputDebug {
+    device_state: DeviceState,
+    events: VecDeque<String>,
+    // As of the last frame, from device_query
+    held: Vec<Keycode>,
+}
+
+impl InputDebug {
+    fn log(&mut self, line: String) {
+        println!("{line}");
+        self.events.push_back(line);
+        if self.events.len() > HISTORY {
+            self.events.pop_front();
+        }
+    }
+}
+
+impl eframe::App for InputDebug {
+    // Raw, before the overlay's fixups like the one for `+` would get to them
+    fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        for event in &raw_input.events {
+            let line = match event {
+                Event::Key {
+                    key,
+                    physical_key,
+                    pressed,
+                    repeat,
+                    modifiers,
+                } => format!(
+                    "egui key {} (physical {}) {}{}{}",
+                    key.name(),
+                    physical_key.map_or("none", |k| k.name()),
+                    if *pressed { "down" } else { "up" },
+                    if *repeat { ", repeat" } else { "" },
+                    modifier_names(*modifiers),
+                ),
+                Event::Text(text) => format!("egui text {text:?}"),
+                _ => continue,
+            };
+            self.log(line);
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let held = self.device_state.get_keys();
+        if held != self.held {
+            self.log(format!("X server holds {held:?}"));
+            self.held = held;
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Press the keys that misbehave, close the window when done");
+            ui.label("Every line is printed to the terminal as well, to paste into a bug report.");
+            ui.separator();
+            ui.label(format!("Held according to the X server: {:?}", self.held));
+            ui.separator();
+            for line in &self.events {
+                ui.monospace(line);
+            }
+        });
+        // The X server's view is polled, not pushed
+        ctx.request_repaint_after(Duration::from_millis(16));
+    }
+}
+
+fn modifier_names(modifiers: Modifiers) -> String {
+    [
+        (modifiers.shift, ", shift"),
+        (modifiers.ctrl, ", ctrl"),
+        (modifiers.alt, ", alt"),
+        (modifiers.mac_cmd, ", cmd"),
+    ]
+    .into_iter()
+    .filter(|(held, _)| *held)
+    .map(|(_, name)| name)
+    .collect()
+}
@@ -0,0 +1,224 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use egui::Key;
+
+// Far more than any page needs, an amount past this is more likely a typo
+const MAX_SCROLL_AMOUNT: u32 = 1000;
+
+/// A request from an external tool, e.g. a voice command grammar, one per line on the socket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// `select-region <key>`: the region bound to that key
+    SelectRegion(Key),
+    /// `select-cell <key>`: the cell bound to that key, in the selected region
+    SelectCell(Key),
+    /// `click <left|right|middle>`
+    Click(ClickButton),
+    /// `scroll <up|down|left|right> <amount>`: `amount` scroll steps of `scroll_speed`
+    Scroll(ScrollDirection, u32),
+    /// `release`: lets go of the left button
+    Release,
+    /// `pause`: hides the overlay, e.g. while taking a screenshot, until `resume`
+    Pause,
+    /// `resume`: shows the overlay again after `pause`
+    Resume,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClickButton {
+    Left,
+    Right,
+    Middle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let key = |name: &str| Key::from_name(name).ok_or_else(|| format!("Unknown key {name}"));
+
+        match words.as_slice() {
+            ["select-region", name] => Ok(Command::SelectRegion(key(name)?)),
+            ["select-cell", name] => Ok(Command::SelectCell(key(name)?)),
+            ["click", button] => {
+                let button = match *button {
+                    "left" => ClickButton::Left,
+                    "right" => ClickButton::Right,
+                    "middle" => ClickButton::Middle,
+                    _ => return Err(format!("Unknown button {button}")),
+                };
+                Ok(Command::Click(button))
+            }
+            ["scroll", dir, amount] => {
+                let dir = match *dir {
+                    "up" => ScrollDirection::Up,
+                    "down" => ScrollDirection::Down,
+                    "left" => ScrollDirection::Left,
+                    "right" => ScrollDirection::Right,
+                    _ => return Err(format!("Unknown scroll direction {dir}")),
+                };
+                let amount = amount
+                    .parse()
+                    .ok()
+                    .filter(|amount| *amount <= MAX_SCROLL_AMOUNT)
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid scroll amount {amount}, needs to be 0 to {MAX_SCROLL_AMOUNT}"
+                        )
+                    })?;
+                Ok(Command::Scroll(dir, amount))
+            }
+            ["release"] => Ok(Command::Release),
+            ["pause"] => Ok(Command::Pause),
+            ["resume"] => Ok(Command::Resume),
+            _ => Err(format!("Unknown command {line}")),
+        }
+    }
+}
+
+/// Status line sent for `status` requests when no overlay is open
+pub const CLOSED_STATUS: &str = r#"{"mode":"closed"}"#;
+
+// The latest status line and a counter bumped on every change, for `status --follow`
+struct StatusBoard {
+    line: Mutex<(u64, String)>,
+    changed: Condvar,
+}
+
+impl Default for StatusBoard {
+    fn default() -> Self {
+        StatusBoard {
+            line: Mutex::new((0, CLOSED_STATUS.to_owned())),
+            changed: Condvar::new(),
+        }
+    }
+}
+
+/// Commands received on the control socket that the overlay hasn't picked up yet
+#[derive(Clone, Default)]
+pub struct CommandQueue {
+    pending: Arc<Mutex<Vec<Command>>>,
+    status: Arc<StatusBoard>,
+}
+
+impl CommandQueue {
+    /// Listens on the Unix socket at `path`, replacing a stale socket file left behind by an
+    /// earlier run. A socket another kmgrid still listens on is left alone.
+    pub fn spawn(path: &str) -> std::io::Result<Self> {
+        if UnixStream::connect(path).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                "another kmgrid is listening on it",
+            ));
+        }
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path)?;
+
+        let queue = CommandQueue::default();
+        let served = queue.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let served = served.clone();
+                std::thread::spawn(move || served.serve(stream));
+            }
+        });
+        Ok(queue)
+    }
+
+    pub fn take(&self) -> Vec<Command> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+
+    /// Updates the line `status` requests get, waking up followers if it changed
+    pub fn publish_status(&self, line: String) {
+        let mut current = self.status.line.lock().unwrap();
+        if current.1 != line {
+            *current = (current.0 + 1, line);
+            self.status.changed.notify_all();
+        }
+    }
+
+    // Answers every line with `ok` or the parse error, so scripts can tell a typo from a no-op.
+    // `status` requests get the status line instead, `status --follow` every change of it.
+    fn serve(&self, stream: UnixStream) {
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            let reply = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => continue,
+                ["status"] => self.status.line.lock().unwrap().1.clone(),
+                ["status", "--follow"] => return self.follow_status(&mut writer),
+                _ => match Command::parse(&line) {
+                    Ok(command) => {
+                        self.pending.lock().unwrap().push(command);
+                        "ok".to_owned()
+                    }
+                    Err(err) => format!("error: {err}"),
+                },
+            };
+            if writeln!(writer, "{reply}").is_err() {
+                break;
+            }
+        }
+    }
+
+    fn follow_status(&self, writer: &mut UnixStream) {
+        let mut current = self.status.line.lock().unwrap();
+        loop {
+            let (version, line) = current.clone();
+            drop(current);
+            if writeln!(writer, "{line}").is_err() {
+                return;
+            }
+            current = self.status.line.lock().unwrap();
+            while current.0 == version {
+                current = self.status.changed.wait(current).unwrap();
+            }
+        }
+    }
+}
+
+/// `kmgrid status [--follow]`: prints the status of the kmgrid listening on `path` as JSON lines.
+/// Following keeps going across overlays closing and kmgrid restarting.
+pub fn print_status(path: &str, follow: bool) {
+    let mut last = None;
+    let mut print = |line: String| {
+        if last.as_ref() != Some(&line) {
+            println!("{line}");
+            last = Some(line);
+        }
+    };
+
+    loop {
+        if let Ok(mut stream) = UnixStream::connect(path) {
+            let request = if follow { "status --follow" } else { "status" };
+            if writeln!(stream, "{request}").is_ok() {
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else { break };
+                    print(line);
+                    if !follow {
+                        return;
+                    }
+                }
+            }
+        }
+
+        print(CLOSED_STATUS.to_owned());
+        if !follow {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::ptr;
+
+use egui::Key;
+use x11::xlib;
+use xkeysym::Keysym;
+
+use crate::window::XConnection;
+
+const XKB_USE_CORE_KBD: u32 = 0x0100;
+
+// X keycodes of the US QWERTY positions egui falls back to when a key doesn't produce ASCII
+const US_KEYCODES: [(Key, u8); 47] = [
+    (Key::Num1, 10),
+    (Key::Num2, 11),
+    (Key::Num3, 12),
+    (Key::Num4, 13),
+    (Key::Num5, 14),
+    (Key::Num6, 15),
+    (Key::Num7, 16),
+    (Key::Num8, 17),
+    (Key::Num9, 18),
+    (Key::Num0, 19),
+    (Key::Minus, 20),
+    (Key::Equals, 21),
+    (Key::Q, 24),
+    (Key::W, 25),
+    (Key::E, 26),
+    (Key::R, 27),
+    (Key::T, 28),
+    (Key::Y, 29),
+    (Key::U, 30),
+    (Key::I, 31),
+    (Key::O, 32),
+    (Key::P, 33),
+    (Key::OpenBracket, 34),
+    (Key::CloseBracket, 35),
+    (Key::A, 38),
+    (Key::S, 39),
+    (Key::D, 40),
+    (Key::F, 41),
+    (Key::G, 42),
+    (Key::H, 43),
+    (Key::J, 44),
+    (Key::K, 45),
+    (Key::L, 46),
+    (Key::Semicolon, 47),
+    (Key::Quote, 48),
+    (Key::Backtick, 49),
+    (Key::Backslash, 51),
+    (Key::Z, 52),
+    (Key::X, 53),
+    (Key::C, 54),
+    (Key::V, 55),
+    (Key::B, 56),
+    (Key::N, 57),
+    (Key::M, 58),
+    (Key::Comma, 59),
+    (Key::Period, 60),
+    (Key::Slash, 61),
+];
+
+/// The US key at an XKB key position like `AD01` (the top letter row's first key, Q on a US
+/// layout), for bindings that name positions rather than symbols
+pub fn key_at_position(name: &str) -> Option<Key> {
+    // Rows from the number row down, as (first keycode, keys in the row)
+    let (first, count) = match name.get(..2)? {
+        "AE" => (10, 12),
+        "AD" => (24, 12),
+        "AC" => (38, 11),
+        "AB" => (52, 10),
+        _ => {
+            let keycode = match name {
+                "TLDE" => 49,
+                "BKSL" => 51,
+                _ => return None,
+            };
+            return key_at_keycode(keycode);
+        }
+    };
+    let column: u8 = name.get(2..).filter(|c| c.len() == 2)?.parse().ok()?;
+    if !(1..=count).contains(&column) {
+        return None;
+    }
+    key_at_keycode(first + column - 1)
+}
+
+fn key_at_keycode(keycode: u8) -> Option<Key> {
+    US_KEYCODES
+        .iter()
+        .find(|(_, k)| *k == keycode)
+        .map(|(key, _)| *key)
+}
+
+/// Text drawn for bound keys. egui names keys after the US layout, so with `layout_labels`
+/// enabled, keys that type something else in the active XKB layout (e.g. `ж` on the `;` key of a
+/// Russian layout) are labeled with that instead.
+#[derive(Default)]
+pub struct KeyLabels {
+    labels: HashMap<Key, String>,
+}
+
+impl KeyLabels {
+    pub fn new(layout_labels: bool, group: u8) -> Self {
+        let mut labels = KeyLabels::default();
+        if layout_labels {
+            labels.refresh(group);
+        }
+        labels
+    }
+
+    pub fn label(&self, key: Key) -> &str {
+        self.labels.get(&key).map_or(key.name(), String::as_str)
+    }
+
+    /// Relabels the keys for layout group `group`
+    pub fn refresh(&mut self, group: u8) {
+        self.labels.clear();
+        self.relabel(group);
+    }
+
+    fn relabel(&mut self, group: u8) {
+        // SAFETY: the display is only used within this block and closed before leaving it
+        unsafe {
+            let display = xlib::XOpenDisplay(ptr::null());
+            if display.is_null() {
+                return;
+            }
+
+            for (key, keycode) in US_KEYCODES {
+                let keysym = xlib::XkbKeycodeToKeysym(display, keycode, group as i32, 0);
+                let label = Keysym::new(keysym as u32).key_char();
+
+                // Keys that still type ASCII are matched by egui on what they type, so
+                // their US name stays accurate
+                if let Some(c) = label.filter(|c| !c.is_ascii() && !c.is_control()) {
+                    self.labels.insert(key, c.to_uppercase().collect());
+                }
+            }
+
+            xlib::XCloseDisplay(display);
+        }
+    }
+}
+
+/// The active XKB layout group, 0 for the first configured layout. Asked on every key event, so
+/// over a connection that stays open.
+pub fn layout_group(connection: &XConnection) -> Option<u8> {
+    // SAFETY: the connection's display stays open while it's borrowed
+    unsafe {
+        let mut state: xlib::XkbStateRec = std::mem::zeroed();
+        (xlib::XkbGetState(connection.display(), XKB_USE_CORE_KBD, &mut state) == 0)
+            .then_some(state.group)
+    }
+}
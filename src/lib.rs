@@ -0,0 +1,3 @@
+//! Pieces of kmgrid that are useful without the overlay itself
+
+pub mod geometry;
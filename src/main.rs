@@ -2,153 +2,685 @@ use display_info::DisplayInfo;
 use egui::{Align2, Rect};
 use enigo::{Button, Enigo, Mouse, Settings};
 
-use eframe::{egui, Result};
+use eframe::egui;
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
 
-use eframe::egui::ViewportCommand;
-use egui::{pos2, vec2, Color32, Key, Pos2, Rounding, Stroke, Vec2};
-use std::{fs::File, io::Read};
+use eframe::egui::{ViewportCommand, ViewportId};
+use egui::{pos2, vec2, Color32, Key, Modifiers, Pos2, Rounding, Stroke, Vec2};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::f32::consts::{FRAC_PI_2, TAU};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use device_query::{DeviceQuery, DeviceState, Keycode};
+use kmgrid::geometry::{Desktop, GridGeometry, GridSize};
 
-#[derive(Clone, Copy)]
+mod action;
+mod backdrop;
+mod buildinfo;
+mod config;
+mod contrast;
+mod cursor;
+mod daemon;
+mod dump;
+mod evdev;
+mod eventlog;
+mod gaze;
+mod heatmap;
+mod hints;
+mod history;
+mod hooks;
+mod i18n;
+mod identify;
+mod inputdebug;
+mod ipc;
+mod labels;
+mod migrate;
+mod mouse;
+mod pointer;
+mod presets;
+mod reload;
+mod scan;
+mod search;
+mod speed;
+mod state;
+mod suggest;
+mod toast;
+mod uinput;
+mod validate;
+mod window;
+
+use action::{split_modifiers, Action, Binding, Chord, DispatchTable, Macro, Trigger};
+use backdrop::{Backdrop, TransparencyFallback};
+use contrast::LabelShades;
+use cursor::HiddenCursor;
+use daemon::Activation;
+use evdev::{DeviceAction, DeviceInput, InputDevice, JsonInputDevice};
+use eventlog::{Event, EventLog};
+use gaze::GazeInput;
+use heatmap::Heatmap;
+use history::PositionHistory;
+use hooks::{HookEvent, Hooks};
+use i18n::{Locale, Msg};
+use identify::Identify;
+use ipc::{ClickButton, Command, CommandQueue, ScrollDirection};
+use labels::KeyLabels;
+use mouse::{MouseWorker, PointerBackend};
+use pointer::PointerTracker;
+use presets::Layout;
+use reload::ConfigWatcher;
+use scan::Scanner;
+use search::Target;
+use speed::{Acceleration, CellSpeedup, ScrollAcceleration, Speed};
+use toast::Toasts;
+use validate::Problem;
+
+const DISPLAY_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+// Smallest width and height offsets may leave for the grid
+const MIN_DRAWABLE_SIZE: f32 = 100.0;
+// Label font size relative to the height of its region or cell, and the share of the width the
+// label may take at most
+const LABEL_HEIGHT_RATIO: f32 = 0.22;
+const LABEL_WIDTH_RATIO: f32 = 0.8;
+// Recursive narrowing stops subdividing once a pick is smaller than this in either direction
+const MIN_RECURSE_SIZE: f32 = 6.0;
+// Smallest size `resize_window` shrinks a window to
+const MIN_WINDOW_SIZE: f32 = 32.0;
+// Opacity left to the grid around the picked rectangle with `residual_context`
+const FAINT: f32 = 0.35;
+
+// Largest count `count_prefixes` adds up to, a few screens' worth of moves
+const MAX_COUNT: u32 = 999;
+
+// The grid `micro_grid` keys pick points of in Cell mode
+const MICRO_GRID: GridSize = GridSize {
+    regions: [1, 1],
+    cells: [3, 3],
+};
+
+#[derive(Clone, Copy, PartialEq)]
 struct Display {
     pos: Pos2,
     size: Vec2,
+    // Physical size, zero when the display doesn't report it
+    size_mm: Vec2,
+    // Space taken off the top left for panels, always leaving a drawable area
     offset: Vec2,
+    // The configured offset didn't fit and got clamped
+    offset_clamped: bool,
+    primary: bool,
+    grid: GridSize,
+    style: StyleConfig,
+    // Outline widths and offsets are tuned for 96 dpi and get multiplied by this
+    scale: f32,
+}
+
+impl Display {
+    fn new(
+        pos: Pos2,
+        size: Vec2,
+        size_mm: Vec2,
+        offset: Vec2,
+        primary: bool,
+        grid: GridSize,
+        style: StyleConfig,
+    ) -> Self {
+        let max_offset = (size - Vec2::splat(MIN_DRAWABLE_SIZE)).max(Vec2::ZERO);
+        let clamped = offset.clamp(Vec2::ZERO, max_offset);
+        Display {
+            pos,
+            size,
+            size_mm,
+            offset: clamped,
+            offset_clamped: clamped != offset,
+            primary,
+            grid,
+            style,
+            scale: 1.0,
+        }
+    }
+
+    // The whole display in screen coordinates
+    fn bounds(&self) -> Rect {
+        Rect::from_min_size(self.pos, self.size)
+    }
+
+    // The part of the display left after the offset, which the overlay window and the grid cover
+    fn area(&self) -> Rect {
+        Rect::from_min_max(self.pos + self.offset, self.bounds().max)
+    }
+
+    // Grid in screen coordinates
+    fn geometry(&self) -> GridGeometry {
+        GridGeometry::with_size(self.area().min, self.area().size(), self.grid)
+    }
+
+    // Grid in the coordinates of the overlay window
+    fn window_geometry(&self) -> GridGeometry {
+        GridGeometry::with_size(Pos2::ZERO, self.area().size(), self.grid)
+    }
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 struct JsonBindingsForMouse {
-    move_up: String,
-    move_down: String,
-    move_left: String,
-    move_right: String,
+    move_up: JsonKeys,
+    move_down: JsonKeys,
+    move_left: JsonKeys,
+    move_right: JsonKeys,
+
+    // Jump halfway toward an edge of what's left of the display, none by default
+    #[serde(default)]
+    warp_up: Option<JsonKeys>,
+    #[serde(default)]
+    warp_down: Option<JsonKeys>,
+    #[serde(default)]
+    warp_left: Option<JsonKeys>,
+    #[serde(default)]
+    warp_right: Option<JsonKeys>,
+
+    left_click: JsonKeys,
+    left_click_and_exit: JsonKeys,
+    middle_click: JsonKeys,
+    right_click: JsonKeys,
+
+    // Left clicks `multi_click_delay_ms` apart, none by default
+    #[serde(default)]
+    double_click: Option<JsonKeys>,
+    #[serde(default)]
+    triple_click: Option<JsonKeys>,
+
+    // The side buttons browsers go back and forward with, none by default
+    #[serde(default)]
+    back_click: Option<JsonKeys>,
+    #[serde(default)]
+    forward_click: Option<JsonKeys>,
 
-    left_click: String,
-    left_click_and_exit: String,
-    middle_click: String,
-    right_click: String,
+    // Presses the left button and goes back to Screen mode to pick where to drop, none by default
+    #[serde(default)]
+    drag_start: Option<JsonKeys>,
+    #[serde(default)]
+    drag_drop: Option<JsonKeys>,
 
-    left_click_down: String,
-    left_click_up: String,
+    left_click_down: JsonKeys,
+    left_click_up: JsonKeys,
 
-    scroll_up: String,
-    scroll_down: String,
-    scroll_left: String,
-    scroll_right: String,
+    scroll_up: JsonKeys,
+    scroll_down: JsonKeys,
+    scroll_left: JsonKeys,
+    scroll_right: JsonKeys,
+    // Scroll `page_scroll_amount` steps at once, none by default
+    #[serde(default)]
+    scroll_page_up: Option<JsonKeys>,
+    #[serde(default)]
+    scroll_page_down: Option<JsonKeys>,
 
-    speed_quarter: String,
-    speed_half: String,
-    speed_twice: String,
-    speed_quadruple: String,
+    speed_quarter: JsonKeys,
+    speed_half: JsonKeys,
+    speed_twice: JsonKeys,
+    speed_quadruple: JsonKeys,
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 struct JsonKeyBindings {
-    region: [String; 16],
-    skip_to_cell: String,
-    prev_screen: String,
-    next_screen: String,
+    region: Vec<String>,
+    skip_to_cell: JsonKeys,
+    prev_screen: JsonKeys,
+    next_screen: JsonKeys,
 
-    grid: [String; 15],
+    grid: Vec<String>,
+
+    // Nine keys for the points of a 3x3 grid over the cell in Cell mode, none by default
+    #[serde(default)]
+    micro_grid: Vec<String>,
 
     mouse: JsonBindingsForMouse,
+
+    #[serde(default)]
+    reopen: Option<JsonKeys>,
+
+    #[serde(default)]
+    reopen_anyway: Option<JsonKeys>,
+
+    #[serde(default)]
+    scan_select: Option<JsonKeys>,
+
+    #[serde(default)]
+    gaze_select: Option<JsonKeys>,
+
+    #[serde(default)]
+    pause: Option<JsonKeys>,
+
+    #[serde(default)]
+    move_window: Option<JsonKeys>,
+
+    #[serde(default)]
+    resize_window: Option<JsonKeys>,
+
+    #[serde(default)]
+    activate_window: Option<JsonKeys>,
+
+    #[serde(default)]
+    next_profile: Option<JsonKeys>,
+
+    #[serde(default)]
+    back_to_narrow: Option<JsonKeys>,
+
+    #[serde(default)]
+    back_to_screen: Option<JsonKeys>,
+
+    #[serde(default)]
+    narrow_at_pointer: Option<JsonKeys>,
+
+    #[serde(default)]
+    show_hints: Option<JsonKeys>,
+
+    // Flips the bindings left to right and back, in every mode
+    #[serde(default)]
+    mirror_layout: Option<JsonKeys>,
+
+    // Jump back and forward through the positions warped or clicked at, in every mode
+    #[serde(default)]
+    history_back: Option<JsonKeys>,
+    #[serde(default)]
+    history_forward: Option<JsonKeys>,
+
+    // Typed in hint mode, as few as tell the cells apart
+    #[serde(default = "default_hint_keys")]
+    hint_keys: Vec<String>,
+
+    #[serde(default)]
+    macros: Vec<JsonMacro>,
+
+    // Easiest keys to reach first, for `kmgrid suggest-layout`
+    #[serde(default)]
+    easy_keys: Vec<String>,
+
+    #[serde(default)]
+    modes: JsonModeBindings,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+struct JsonMacro {
+    key: String,
+    // Action names, like in `modes`
+    steps: Vec<String>,
+    #[serde(default)]
+    confirm: bool,
+}
+
+// Action name to keys, applied on top of the bindings above for one mode only
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+struct JsonModeBindings {
+    #[serde(default)]
+    screen: BTreeMap<String, JsonKeys>,
+    #[serde(default)]
+    narrow: BTreeMap<String, JsonKeys>,
+    #[serde(default)]
+    recurse: BTreeMap<String, JsonKeys>,
+    #[serde(default)]
+    cell: BTreeMap<String, JsonKeys>,
+}
+
+// The key for an action, or a list of keys that all do it, like `["W", "ArrowUp"]`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum JsonKeys {
+    One(String),
+    Any(Vec<String>),
+}
+
+impl JsonKeys {
+    fn names(&self) -> &[String] {
+        match self {
+            JsonKeys::One(name) => std::slice::from_ref(name),
+            JsonKeys::Any(names) => names,
+        }
+    }
+
+    fn chords(&self) -> Vec<Chord> {
+        self.names().iter().map(|k| to_chord(k)).collect()
+    }
+}
+
+fn to_action(s: &str) -> Action {
+    let msg = format!("Unable to parse action {}", s);
+    Action::from_str(s).expect(&msg)
 }
 
 fn to_keycode(s: &str) -> Key {
     let msg = format!("Unable to parse keybinding {}", s);
-    return Key::from_name(s).expect(&msg);
+    Key::from_name(s)
+        .or_else(|| labels::key_at_position(s))
+        .expect(&msg)
+}
+
+// A key name with `ctrl+`, `shift+` or `alt+` in front, for bindings of actions
+fn to_chord(s: &str) -> Chord {
+    let (modifiers, key) = split_modifiers(s);
+    Chord {
+        key: to_keycode(key),
+        modifiers,
+    }
+}
+
+// Keys watched while the overlay is closed go through device_query, which names some keys
+// differently from egui (e.g. "Key1" instead of "1").
+fn to_device_keycode(s: &str) -> Keycode {
+    let key = to_keycode(s);
+    let msg = format!("Unable to watch keybinding {} outside the overlay", s);
+    Keycode::from_str(key.name())
+        .or_else(|_| Keycode::from_str(&format!("{key:?}")))
+        .or_else(|_| Keycode::from_str(&format!("Key{}", key.name())))
+        .expect(&msg)
 }
 
 impl JsonKeyBindings {
     fn transform(&self) -> KeyBindings {
-        let mut region = [Key::Space; 16];
-        for (i, val) in self.region.iter().enumerate() {
-            region[i] = to_keycode(val);
-        }
+        let region = self.region.iter().map(|k| to_keycode(k)).collect();
+        let grid = self.grid.iter().map(|k| to_keycode(k)).collect();
 
-        let mut grid = [Key::Space; 15];
-        for (i, val) in self.grid.iter().enumerate() {
-            grid[i] = to_keycode(val);
+        let mut modes = DispatchTable::default();
+        for (mode, overrides) in [
+            (Mode::Screen, &self.modes.screen),
+            (Mode::Narrow, &self.modes.narrow),
+            (Mode::Cell, &self.modes.cell),
+            (Mode::Recurse(1), &self.modes.recurse),
+        ] {
+            for (action, keys) in overrides {
+                let action = to_action(action);
+                for chord in keys.chords() {
+                    modes.push(mode, Binding { chord, action });
+                }
+            }
         }
 
+        let macros = self
+            .macros
+            .iter()
+            .map(|m| Macro {
+                key: to_chord(&m.key),
+                steps: m
+                    .steps
+                    .iter()
+                    .map(|step| match to_action(step) {
+                        Action::RunMacro(_) => panic!("Macros can't run other macros ({step})"),
+                        action => action,
+                    })
+                    .collect(),
+                confirm: m.confirm,
+            })
+            .collect();
+
         KeyBindings {
+            easy_keys: self.easy_keys.iter().map(|k| to_keycode(k)).collect(),
             region,
-            prev_screen: to_keycode(&self.prev_screen),
-            next_screen: to_keycode(&self.next_screen),
-            skip_to_cell: to_keycode(&self.skip_to_cell),
+            prev_screen: self.prev_screen.chords(),
+            next_screen: self.next_screen.chords(),
+            skip_to_cell: self.skip_to_cell.chords(),
             grid,
+            micro_grid: self.micro_grid.iter().map(|k| to_keycode(k)).collect(),
             mouse: MouseBindings {
-                move_up: to_keycode(&self.mouse.move_up),
-                move_down: to_keycode(&self.mouse.move_down),
-                move_left: to_keycode(&self.mouse.move_left),
-                move_right: to_keycode(&self.mouse.move_right),
-
-                left_click: to_keycode(&self.mouse.left_click),
-                left_click_and_exit: to_keycode(&self.mouse.left_click_and_exit),
-                middle_click: to_keycode(&self.mouse.middle_click),
-                right_click: to_keycode(&self.mouse.right_click),
-
-                left_click_down: to_keycode(&self.mouse.left_click_down),
-                left_click_up: to_keycode(&self.mouse.left_click_up),
-
-                scroll_up: to_keycode(&self.mouse.scroll_up),
-                scroll_down: to_keycode(&self.mouse.scroll_down),
-                scroll_left: to_keycode(&self.mouse.scroll_left),
-                scroll_right: to_keycode(&self.mouse.scroll_right),
-
-                speed_quarter: to_keycode(&self.mouse.speed_quarter),
-                speed_half: to_keycode(&self.mouse.speed_half),
-                speed_twice: to_keycode(&self.mouse.speed_twice),
-                speed_quadruple: to_keycode(&self.mouse.speed_quadruple),
+                move_up: self.mouse.move_up.chords(),
+                move_down: self.mouse.move_down.chords(),
+                move_left: self.mouse.move_left.chords(),
+                move_right: self.mouse.move_right.chords(),
+                warp_up: self
+                    .mouse
+                    .warp_up
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                warp_down: self
+                    .mouse
+                    .warp_down
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                warp_left: self
+                    .mouse
+                    .warp_left
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                warp_right: self
+                    .mouse
+                    .warp_right
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+
+                left_click: self.mouse.left_click.chords(),
+                left_click_and_exit: self.mouse.left_click_and_exit.chords(),
+                middle_click: self.mouse.middle_click.chords(),
+                right_click: self.mouse.right_click.chords(),
+                double_click: self
+                    .mouse
+                    .double_click
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                triple_click: self
+                    .mouse
+                    .triple_click
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                back_click: self
+                    .mouse
+                    .back_click
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                forward_click: self
+                    .mouse
+                    .forward_click
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                drag_start: self
+                    .mouse
+                    .drag_start
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                drag_drop: self
+                    .mouse
+                    .drag_drop
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+
+                left_click_down: self.mouse.left_click_down.chords(),
+                left_click_up: self.mouse.left_click_up.chords(),
+
+                scroll_up: self.mouse.scroll_up.chords(),
+                scroll_down: self.mouse.scroll_down.chords(),
+                scroll_left: self.mouse.scroll_left.chords(),
+                scroll_right: self.mouse.scroll_right.chords(),
+                scroll_page_up: self
+                    .mouse
+                    .scroll_page_up
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+                scroll_page_down: self
+                    .mouse
+                    .scroll_page_down
+                    .as_ref()
+                    .map_or(Vec::new(), JsonKeys::chords),
+
+                speed_quarter: self.mouse.speed_quarter.chords(),
+                speed_half: self.mouse.speed_half.chords(),
+                speed_twice: self.mouse.speed_twice.chords(),
+                speed_quadruple: self.mouse.speed_quadruple.chords(),
             },
+            reopen: self
+                .reopen
+                .as_ref()
+                .map(|keys| keys.names().iter().map(|k| to_device_keycode(k)).collect()),
+            reopen_anyway: self.reopen_anyway.as_ref().map_or(Vec::new(), |keys| {
+                keys.names().iter().map(|k| to_device_keycode(k)).collect()
+            }),
+            scan_select: self
+                .scan_select
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            gaze_select: self
+                .gaze_select
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            pause: self.pause.as_ref().map_or(Vec::new(), JsonKeys::chords),
+            move_window: self
+                .move_window
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            resize_window: self
+                .resize_window
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            activate_window: self
+                .activate_window
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            next_profile: self
+                .next_profile
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            back_to_narrow: self
+                .back_to_narrow
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            back_to_screen: self
+                .back_to_screen
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            narrow_at_pointer: self
+                .narrow_at_pointer
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            show_hints: self
+                .show_hints
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            mirror_layout: self
+                .mirror_layout
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            history_back: self
+                .history_back
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            history_forward: self
+                .history_forward
+                .as_ref()
+                .map_or(Vec::new(), JsonKeys::chords),
+            hint_keys: self.hint_keys.iter().map(|k| to_keycode(k)).collect(),
+            macros,
+            modes,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct MouseBindings {
-    move_up: Key,
-    move_down: Key,
-    move_left: Key,
-    move_right: Key,
+    move_up: Vec<Chord>,
+    move_down: Vec<Chord>,
+    move_left: Vec<Chord>,
+    move_right: Vec<Chord>,
+    warp_up: Vec<Chord>,
+    warp_down: Vec<Chord>,
+    warp_left: Vec<Chord>,
+    warp_right: Vec<Chord>,
 
-    left_click: Key,
-    left_click_and_exit: Key,
-    middle_click: Key,
-    right_click: Key,
+    left_click: Vec<Chord>,
+    left_click_and_exit: Vec<Chord>,
+    middle_click: Vec<Chord>,
+    right_click: Vec<Chord>,
+    double_click: Vec<Chord>,
+    triple_click: Vec<Chord>,
+    back_click: Vec<Chord>,
+    forward_click: Vec<Chord>,
+    drag_start: Vec<Chord>,
+    drag_drop: Vec<Chord>,
 
-    left_click_down: Key,
-    left_click_up: Key,
+    left_click_down: Vec<Chord>,
+    left_click_up: Vec<Chord>,
 
-    scroll_up: Key,
-    scroll_down: Key,
-    scroll_left: Key,
-    scroll_right: Key,
+    scroll_up: Vec<Chord>,
+    scroll_down: Vec<Chord>,
+    scroll_left: Vec<Chord>,
+    scroll_right: Vec<Chord>,
+    scroll_page_up: Vec<Chord>,
+    scroll_page_down: Vec<Chord>,
 
-    speed_quarter: Key,
-    speed_half: Key,
-    speed_twice: Key,
-    speed_quadruple: Key,
+    speed_quarter: Vec<Chord>,
+    speed_half: Vec<Chord>,
+    speed_twice: Vec<Chord>,
+    speed_quadruple: Vec<Chord>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct KeyBindings {
-    prev_screen: Key,
-    next_screen: Key,
+    prev_screen: Vec<Chord>,
+    next_screen: Vec<Chord>,
+
+    region: Vec<Key>,
+    skip_to_cell: Vec<Chord>,
 
-    region: [Key; 16],
-    skip_to_cell: Key,
+    grid: Vec<Key>,
 
-    grid: [Key; 15],
+    // Jumps to the points of `MICRO_GRID` over the cell, a last step before moving freely
+    micro_grid: Vec<Key>,
 
     mouse: MouseBindings,
+
+    // None means any key reopens the overlay
+    reopen: Option<Vec<Keycode>>,
+
+    // Reopens even over a fullscreen window, see `respect_fullscreen`
+    reopen_anyway: Vec<Keycode>,
+
+    // Switch scanning is on when this is bound
+    scan_select: Vec<Chord>,
+
+    // Picks the region under the latest `gaze_input` point
+    gaze_select: Vec<Chord>,
+
+    // Hides the overlay until pressed again, in every mode
+    pause: Vec<Chord>,
+
+    // Moves the window that had focus before the overlay opened with the movement keys
+    move_window: Vec<Chord>,
+
+    // Resizes that window from the edge or corner nearest the pointer
+    resize_window: Vec<Chord>,
+
+    // Focuses and raises the window under the pointer
+    activate_window: Vec<Chord>,
+
+    // Switches to the next of the config's `profiles`, in every mode
+    next_profile: Vec<Chord>,
+
+    // Back to Narrow mode of the same region from Cell mode or recursive narrowing
+    back_to_narrow: Vec<Chord>,
+
+    // Back to Screen mode from any other mode in one press
+    back_to_screen: Vec<Chord>,
+
+    // Narrow mode of the region under the pointer, on whichever display it is
+    narrow_at_pointer: Vec<Chord>,
+
+    show_hints: Vec<Chord>,
+    hint_keys: Vec<Key>,
+
+    mirror_layout: Vec<Chord>,
+    history_back: Vec<Chord>,
+    history_forward: Vec<Chord>,
+
+    macros: Vec<Macro>,
+
+    easy_keys: Vec<Key>,
+
+    modes: DispatchTable,
 }
 
-#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
 struct Color(u8, u8, u8, u8);
 
-#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
 struct StyleConfig {
     region_line1: Color,
     region_line2: Color,
@@ -156,646 +688,4317 @@ struct StyleConfig {
     region_grid_line2: Color,
     left_grid: Color,
     right_grid: Color,
+
+    #[serde(default = "default_scan_highlight")]
+    scan_highlight: Color,
+
+    #[serde(default = "default_gaze_highlight")]
+    gaze_highlight: Color,
+
+    // Tint of the most picked region or cell, the others get a fainter one
+    #[serde(default = "default_heatmap")]
+    heatmap: Color,
+
+    // Border with a fading glow along the edges of the overlay window, off when unset
+    #[serde(default)]
+    window_border: Option<Color>,
+
+    #[serde(default = "default_window_border_width")]
+    window_border_width: f32,
+
+    // Limits for the label font size, which otherwise follows the region and cell size
+    #[serde(default = "default_label_min_size")]
+    label_min_size: f32,
+
+    #[serde(default = "default_label_max_size")]
+    label_max_size: f32,
+
+    // Black or white label text depending on what's under each label, instead of always white
+    #[serde(default)]
+    auto_label_color: bool,
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
-struct JsonConfig {
-    primary_offset_x: i32,
-    primary_offset_y: i32,
-    key_bindings: JsonKeyBindings,
-    style: StyleConfig,
-    scroll_speed: i32,
-    movement_speed: i32,
+// Two of them label all 240 cells of the default grid
+fn default_hint_keys() -> Vec<String> {
+    [
+        "A", "S", "D", "F", "G", "H", "J", "K", "L", "Q", "W", "E", "R", "U", "I", "O",
+    ]
+    .map(String::from)
+    .to_vec()
 }
 
-impl JsonConfig {
-    fn transform(&self) -> Config {
-        Config {
-            primary_offset_x: self.primary_offset_x,
-            primary_offset_y: self.primary_offset_y,
-            key_bindings: self.key_bindings.transform(),
-            style: self.style,
-            scroll_speed: self.scroll_speed,
-            movement_speed: self.movement_speed,
-        }
-    }
+fn default_scan_highlight() -> Color {
+    Color(255, 200, 0, 255)
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Config {
+fn default_gaze_highlight() -> Color {
+    Color(0, 200, 255, 255)
+}
+
+fn default_heatmap() -> Color {
+    Color(255, 60, 0, 160)
+}
+
+fn default_window_border_width() -> f32 {
+    3.0
+}
+
+fn default_label_min_size() -> f32 {
+    8.0
+}
+
+fn default_label_max_size() -> f32 {
+    120.0
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+struct JsonConfig {
+    // Which version of the config format this is, older ones are migrated on load
+    #[serde(default = "default_version")]
+    version: u64,
+
     primary_offset_x: i32,
     primary_offset_y: i32,
-    key_bindings: KeyBindings,
+    key_bindings: JsonKeyBindings,
     style: StyleConfig,
     scroll_speed: i32,
-    movement_speed: i32,
-}
+    movement_speed: Speed,
 
-#[derive(PartialEq)]
-enum Mode {
-    Screen,
-    Narrow,
-    Cell,
-}
+    // Grows `scroll_speed` while the scroll keys stay held
+    #[serde(default)]
+    scroll_acceleration: Option<ScrollAcceleration>,
+    // Steps `scroll_page_up` and `scroll_page_down` scroll by
+    #[serde(default = "default_page_scroll_amount")]
+    page_scroll_amount: i32,
 
-fn main() -> eframe::Result {
-    let mut config = String::new();
-    let res: Result<File, std::io::Error> = File::open("config.json");
-    if let Ok(file) = res {
-        let mut res = file;
-        res.read_to_string(&mut config)
-            .expect("Unable to read config file!");
-    } else {
-        let args: Vec<String> = std::env::args().collect();
-        assert!(args.len() == 2);
-        let res: Result<File, std::io::Error> = File::open(&args[1]);
-        res.expect("Unable to find config file!")
-            .read_to_string(&mut config)
-            .expect("Unable to read config file!");
-    }
+    // Replaces `movement_speed` with one that grows while the movement keys stay held
+    #[serde(default)]
+    acceleration: Option<Acceleration>,
 
-    let config: JsonConfig = serde_json::from_str(&config).expect("Unable to deserialize config!");
-    let config = config.transform();
-    println!("Config {config:#?}");
+    // Speeds moving and scrolling up with the time spent in Cell mode
+    #[serde(default)]
+    cell_speedup: Option<CellSpeedup>,
 
-    let display_infos = DisplayInfo::all().expect("Unable to get display info!");
-    let displays: Vec<_> = display_infos
-        .iter()
-        .map(|d| Display {
-            pos: pos2(d.x as f32, d.y as f32),
-            size: vec2(d.width as f32, d.height as f32),
-            offset: if d.is_primary {
-                vec2(
-                    config.primary_offset_x as f32,
-                    config.primary_offset_y as f32,
-                )
-            } else {
-                vec2(0.0, 0.0)
-            },
-        })
-        .collect();
+    // Speed keys latch when tapped instead of only working while held, like sticky keys
+    #[serde(default)]
+    sticky_speed_keys: bool,
 
-    let mouse_pos = DeviceState::new().query_pointer().coords;
-    let mouse_pos = pos2(mouse_pos.0 as f32, mouse_pos.1 as f32);
-    let mut initial_display_idx = 0;
-    for (i, d) in displays.iter().enumerate() {
-        if egui::Rect::from_min_size(d.pos, d.size).contains(mouse_pos) {
-            initial_display_idx = i;
-            break;
-        }
-    }
+    // Regions per display and cells per region, `region` and `grid` need a key for each
+    #[serde(default = "default_region_cols")]
+    region_cols: usize,
+    #[serde(default = "default_region_rows")]
+    region_rows: usize,
+    #[serde(default = "default_cell_cols")]
+    cell_cols: usize,
+    #[serde(default = "default_cell_rows")]
+    cell_rows: usize,
 
-    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_decorations(false) // Hide the OS-specific "chrome" around the window
-            .with_window_type(egui::X11WindowType::Utility)
-            .with_mouse_passthrough(true)
-            .with_always_on_top()
-            .with_transparent(true)
-            .with_position(displays[initial_display_idx].pos)
-            .with_resizable(false)
-            .with_maximized(false)
-            .with_inner_size(displays[initial_display_idx].size)
-            .with_fullscreen(false),
-        ..Default::default()
-    };
+    #[serde(default)]
+    reopen_window_ms: u64,
 
-    let device_state = DeviceState::new();
-    let keys: Vec<Keycode> = device_state.get_keys();
-    println!("{keys:#?}");
+    // Leave fullscreen windows like presentations alone unless `reopen_anyway` is pressed
+    #[serde(default)]
+    respect_fullscreen: bool,
 
-    let app = MyApp {
-        state: SharedState {
-            displays,
-            current_display: initial_display_idx,
-            config,
-            mode: Mode::Screen,
-            region: 0,
-            cell: -1,
-            device_state: device_query::DeviceState::new(),
-            enigo: Enigo::new(&Settings::default()).unwrap(),
-            mouse_key_down: std::collections::HashSet::new(),
-        },
-    };
+    #[serde(default)]
+    locale: Option<String>,
 
-    eframe::run_native(
-        "Custom window frame", // unused title
-        options,
-        Box::new(|_cc| Ok(Box::new(app))),
-    )
-}
+    // Which preset the default bindings come from, the key bindings in the file go on top
+    #[serde(default)]
+    layout: Layout,
 
-struct MyApp {
-    state: SharedState,
-}
+    #[serde(default)]
+    layout_labels: bool,
 
-struct SharedState {
-    displays: Vec<Display>,
-    current_display: usize,
-    config: Config,
-    mode: Mode,
-    region: i32,
-    cell: i32,
-    device_state: DeviceState,
-    enigo: Enigo,
-    mouse_key_down: std::collections::HashSet<Key>,
-}
+    // Match every key on its position, whatever the active layout types with it
+    #[serde(default)]
+    physical_keys: bool,
 
-impl MyApp {
-    fn move_to_display(&mut self, ctx: &egui::Context, display_idx: usize) {
-        self.state.current_display = display_idx % self.state.displays.len();
+    #[serde(default)]
+    skip_to_cell_display: SkipToCellDisplay,
 
-        let ref display = self.state.displays[self.state.current_display];
-        let pos = display.pos + display.offset;
-        let size = display.size - display.offset;
+    #[serde(default)]
+    after_left_click: AfterLeftClick,
 
-        ctx.send_viewport_cmd(ViewportCommand::InnerSize(size));
-        ctx.send_viewport_cmd(ViewportCommand::OuterPosition(pos));
-        ctx.request_repaint();
-    }
+    // Click action names to what the overlay does after them, replacing their own behavior
+    #[serde(default)]
+    after: BTreeMap<String, AfterClick>,
 
-    fn handle_screen_input<F>(&mut self, ctx: &egui::Context, is_pressed: F)
-    where
-        F: Fn(Key) -> bool,
-    {
-        let region_bindings = self.state.config.key_bindings.region.iter().enumerate();
-        for (i, key) in region_bindings {
-            if is_pressed(*key) {
-                self.state.region = i as i32;
-                self.state.mode = Mode::Narrow;
-                self.state.cell = -1;
-                ctx.request_repaint();
-                break;
-            }
-        }
+    // Focus and raise the window under the pointer before every click, for window managers
+    // that don't focus on hover and windows that ignore a click that only focuses them
+    #[serde(default)]
+    activate_before_click: bool,
 
-        if is_pressed(Key::Backspace) {
-            ctx.send_viewport_cmd(ViewportCommand::Close);
-        }
-        if is_pressed(self.state.config.key_bindings.skip_to_cell) {
-            self.skip_to_cell(ctx);
-        }
-        if is_pressed(self.state.config.key_bindings.prev_screen) {
-            let next_display = if self.state.current_display == 0 {
-                self.state.displays.len() - 1
-            } else {
-                self.state.current_display - 1
-            };
-            self.move_to_display(&ctx, next_display);
-        } else if is_pressed(self.state.config.key_bindings.next_screen) {
-            let next_display = self.state.current_display + 1;
-            self.move_to_display(&ctx, next_display);
-        }
-    }
-
-    fn handle_grid_input<F>(&mut self, is_pressed: F) -> Result<(), enigo::InputError>
-    where
-        F: Fn(Key) -> bool,
-    {
-        let bindings: &KeyBindings = &self.state.config.key_bindings;
-        let grid_bindings = bindings.grid.iter().enumerate();
-
-        for (i, key) in grid_bindings {
-            if is_pressed(*key) {
-                self.state.cell = i as i32;
-
-                let display = self.state.displays[self.state.current_display];
-                let region = self.state.region;
-                let region_size = vec2(display.size.x * 0.25, display.size.y * 0.25);
-                let cell_size = vec2(region_size.x / 5.0, region_size.y / 3.0);
-
-                let mut pos = display.pos;
-                pos += vec2(
-                    region_size.x * (region % 4) as f32,
-                    region_size.y * (region / 4) as f32,
-                ) + vec2(
-                    cell_size.x * ((i % 5) as f32 + 0.5),
-                    cell_size.y * ((i / 5) as f32 + 0.5),
-                );
+    // A left button kmgrid left down, e.g. with `drag_and_exit`, that's still down this long
+    // after gets released when kmgrid next starts, in case it was forgotten or kmgrid crashed
+    #[serde(default = "default_held_button_timeout_ms")]
+    held_button_timeout_ms: u64,
 
-                self.state
-                    .enigo
-                    .move_mouse(pos.x as i32, pos.y as i32, enigo::Coordinate::Abs)?;
-                self.state.mode = Mode::Cell;
+    #[serde(default = "default_multi_click_delay_ms")]
+    multi_click_delay_ms: u64,
 
-                self.state.mouse_key_down.clear();
-                break;
-            }
-        }
+    // Click keys need to stay down this long before they click, 0 clicks on the press
+    #[serde(default)]
+    click_hold_ms: u64,
 
-        if is_pressed(Key::Backspace) {
-            self.state.mode = Mode::Screen;
-        }
-        if is_pressed(Key::Enter) && self.state.cell >= 0 {
-            self.state.mode = Mode::Cell;
-        }
-        return Ok(());
-    }
+    #[serde(default)]
+    region_click_modifier: RegionClickModifier,
 
-    fn handle_cell_input<F1, F2>(
-        &mut self,
-        ctx: &egui::Context,
-        is_pressed: F1,
-        is_held: F2,
-    ) -> Result<(), enigo::InputError>
-    where
-        F1: Fn(Key) -> bool,
-        F2: Fn(Key) -> bool,
-    {
-        let mut is_held_with_check = |k| -> bool {
-            if self.state.mouse_key_down.contains(&k) {
-                return is_held(k);
-            } else if !is_held(k) {
-                if !self.state.mouse_key_down.contains(&k) {
-                    self.state.mouse_key_down.insert(k);
-                }
-            }
-            false
-        };
+    #[serde(default)]
+    residual_context: ResidualContext,
 
-        let bindings = &self.state.config.key_bindings.mouse;
-        let enigo = &mut self.state.enigo;
+    #[serde(default)]
+    narrow_labels: NarrowLabels,
 
-        if is_pressed(bindings.left_click_and_exit) {
-            println!("Click and bye!");
+    #[serde(default)]
+    touch_input: bool,
 
-            enigo.button(Button::Left, enigo::Direction::Click)?;
-            ctx.send_viewport_cmd(ViewportCommand::Close);
-        }
-        if is_pressed(bindings.left_click) {
-            println!("Click");
+    // Hide the system cursor while the overlay is open, `toggle_cursor` brings it back
+    #[serde(default)]
+    hide_cursor: bool,
 
-            enigo.button(Button::Left, enigo::Direction::Click)?;
-            ctx.send_viewport_cmd(ViewportCommand::Focus);
-        } else if is_pressed(bindings.right_click) {
-            println!("Right Click");
+    // Keep the overlay on screen when switching workspaces mid-selection
+    #[serde(default)]
+    sticky: bool,
 
-            enigo.button(Button::Right, enigo::Direction::Click)?;
-            ctx.send_viewport_cmd(ViewportCommand::Close);
-        } else if is_pressed(bindings.middle_click) {
-            println!("Middle Click");
+    // Keep the overlay out of screenshots and screen sharing, where the system allows it
+    #[serde(default)]
+    exclude_from_capture: bool,
 
-            enigo.button(Button::Middle, enigo::Direction::Click)?;
-            ctx.send_viewport_cmd(ViewportCommand::Close);
-        }
+    // Keep splitting the selected cell into another grid until Enter
+    #[serde(default)]
+    recursive_narrowing: bool,
 
-        if is_held_with_check(bindings.scroll_up) {
-            println!("Scroll up");
-            enigo.scroll(-self.state.config.scroll_speed, enigo::Axis::Vertical)?;
+    // Label every cell of Screen mode with its region and cell key, to pick both in one go
+    #[serde(default)]
+    sequence_labels: bool,
 
-            enigo.move_mouse(0, 0, enigo::Coordinate::Rel)?;
-        } else if is_held_with_check(bindings.scroll_down) {
-            println!("Scroll down");
-            enigo.scroll(self.state.config.scroll_speed, enigo::Axis::Vertical)?;
+    // Open in hint mode rather than Screen mode
+    #[serde(default)]
+    start_in_hints: bool,
 
-            enigo.move_mouse(0, 0, enigo::Coordinate::Rel)?;
-        } else if is_held_with_check(bindings.scroll_left) {
-            println!("Scroll left");
-            enigo.scroll(-self.state.config.scroll_speed, enigo::Axis::Horizontal)?;
+    // Digits typed before a movement key in Cell mode repeat the move that many times
+    #[serde(default)]
+    count_prefixes: bool,
 
-            enigo.move_mouse(0, 0, enigo::Coordinate::Rel)?;
-        } else if is_held_with_check(bindings.scroll_right) {
-            println!("Scroll right");
-            enigo.scroll(self.state.config.scroll_speed, enigo::Axis::Horizontal)?;
+    #[serde(default = "default_scan_interval_ms")]
+    scan_interval_ms: u64,
 
-            enigo.move_mouse(0, 0, enigo::Coordinate::Rel)?;
-        }
+    #[serde(default)]
+    gaze_input: Option<String>,
 
-        if is_pressed(bindings.left_click_down) {
-            println!("Press down");
-            enigo.button(Button::Left, enigo::Direction::Press)?;
-        } else if is_pressed(bindings.left_click_up) {
-            println!("Press release");
+    // Commands run when the overlay opens, closes or clicks
+    #[serde(default)]
+    hooks: Hooks,
 
-            enigo.button(Button::Left, enigo::Direction::Release)?;
-        }
+    #[serde(default)]
+    command_socket: Option<String>,
 
-        let mut dist = self.state.config.movement_speed;
-        if is_held(bindings.speed_quarter) {
-            dist /= 4;
-        }
-        if is_held(bindings.speed_half) {
-            dist /= 2;
-        }
-        if is_held(bindings.speed_twice) {
-            dist *= 2;
-        }
-        if is_held(bindings.speed_quadruple) {
-            dist *= 4;
-        }
+    #[serde(default)]
+    transparency_fallback: TransparencyFallback,
 
-        if is_held_with_check(bindings.move_down) {
-            enigo.move_mouse(0, dist, enigo::Coordinate::Rel)?;
-        }
-        if is_held_with_check(bindings.move_up) {
-            enigo.move_mouse(0, -dist, enigo::Coordinate::Rel)?;
-        }
-        if is_held_with_check(bindings.move_left) {
-            enigo.move_mouse(-dist, 0, enigo::Coordinate::Rel)?;
-        }
-        if is_held_with_check(bindings.move_right) {
-            enigo.move_mouse(dist, 0, enigo::Coordinate::Rel)?;
-        }
+    #[serde(default = "default_pointer_poll_ms")]
+    pointer_poll_ms: u64,
 
-        if is_pressed(Key::Backspace) {
-            self.state.mode = Mode::Narrow;
-        }
-        return Ok(());
-    }
+    #[serde(default)]
+    relative_only_windows: Vec<String>,
 
-    fn handle_input(&mut self, ctx: &egui::Context) -> Result<(), enigo::InputError> {
-        let input = ctx.input(|i: &egui::InputState| i.clone());
+    #[serde(default)]
+    pointer_backend: PointerBackend,
 
-        let is_pressed = |k| -> bool { input.key_pressed(k) };
-        let is_held = |k| -> bool { input.key_down(k) };
+    #[serde(default)]
+    event_log: Option<String>,
 
-        if is_pressed(Key::Escape) {
-            ctx.send_viewport_cmd(ViewportCommand::Close);
-        }
-        if self.state.mode == Mode::Screen {
-            self.handle_screen_input(ctx, &is_pressed);
-        } else if self.state.mode == Mode::Narrow {
-            self.handle_grid_input(&is_pressed)?;
-        } else if self.state.mode == Mode::Cell {
-            self.handle_cell_input(ctx, &is_pressed, &is_held)?;
-        }
+    // Named spots for `search_targets`
+    #[serde(default)]
+    targets: Vec<Target>,
 
-        return Ok(());
-    }
+    // Foot pedals and other devices read through evdev, with their buttons bound to actions
+    #[serde(default)]
+    input_devices: Vec<JsonInputDevice>,
 
-    fn skip_to_cell(&mut self, ctx: &egui::Context) {
-        let mouse_pos = self.state.device_state.query_pointer().coords;
-        let mouse_pos = pos2(mouse_pos.0 as f32, mouse_pos.1 as f32);
+    // Offsets, grid sizes and styles of single displays, by output name or index
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    displays: BTreeMap<String, JsonDisplay>,
 
-        for (i, d) in self.state.displays.iter().enumerate() {
-            if egui::Rect::from_min_size(d.pos, d.size).contains(mouse_pos) {
-                let rel_pos = mouse_pos - d.pos;
-                let region_size = vec2(d.size.x * 0.25, d.size.y * 0.25);
-                let region_index = vec2(
-                    (rel_pos.x / region_size.x).floor(),
-                    (rel_pos.y / region_size.y).floor(),
-                );
-                self.state.region = (region_index.x + region_index.y * 4.0) as i32;
+    // Named overrides of the rest of the config, picked with `--profile` or `next_profile`
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    profiles: serde_json::Map<String, serde_json::Value>,
 
-                let rel_pos = rel_pos
-                    - vec2(
-                        region_size.x * region_index.x,
-                        region_size.y * region_index.y,
-                    );
-                let cell_size = vec2(region_size.x / 5.0, region_size.y / 3.0);
-                let cell_index = vec2(
-                    (rel_pos.x / cell_size.x).floor(),
-                    (rel_pos.y / cell_size.y).floor(),
-                );
-                self.state.cell = (cell_index.x + cell_index.y * 5.0) as i32;
+    // The profile applied, as an index into `profiles`
+    #[serde(skip)]
+    profile: Option<usize>,
 
-                self.state.mode = Mode::Cell;
-                if i != self.state.current_display {
-                    self.move_to_display(ctx, i);
-                }
-                self.state.mouse_key_down.clear();
-                break;
-            }
-        }
-    }
+    // The file the config came from, if not only the built-in defaults
+    #[serde(skip)]
+    source: Option<PathBuf>,
 }
 
-fn to_stroke(width: f32, col: Color) -> Stroke {
-    let col = Color32::from_rgba_unmultiplied(col.0, col.1, col.2, col.3);
-    Stroke::new(width, col)
+fn default_region_cols() -> usize {
+    GridSize::default().regions[0]
 }
 
-fn to_col(col: Color) -> Color32 {
-    Color32::from_rgba_unmultiplied(col.0, col.1, col.2, col.3)
+fn default_region_rows() -> usize {
+    GridSize::default().regions[1]
 }
 
-impl eframe::App for MyApp {
-    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
-        egui::Rgba::TRANSPARENT.to_array() // Make sure we don't paint anything behind the rounded corners
-    }
+fn default_cell_cols() -> usize {
+    GridSize::default().cells[0]
+}
 
-    // Hack: egui::input doesn't send key down events for '+' keys for some reason. Investigation needed.
-    fn raw_input_hook(&mut self, _ctx: &egui::Context, _raw_input: &mut egui::RawInput) {
-        for e in  &_raw_input.events {
-            if let egui::Event::Key { key, physical_key, pressed, repeat, .. } = e {
-                if let Some(k) = physical_key {
-                    if *k == Key::Equals && *key == Key::Equals && *pressed == false && *repeat == false {
-                        _raw_input.events.push(egui::Event::Key{
-                            key: Key::Plus,
-                            physical_key: None,
-                            pressed: false,
-                            repeat: false,
-                            modifiers: Default::default(),
-                        });
-                        return;
-                    }
-                }
-            }
-        }
-    }
+fn default_cell_rows() -> usize {
+    GridSize::default().cells[1]
+}
 
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(
-            egui::WindowLevel::AlwaysOnTop,
-        ));
-        if let Err(input_err) = self.handle_input(ctx) {
-            println!("Failed to manipluate mouse: {input_err}");
-        }
+fn default_version() -> u64 {
+    migrate::VERSION
+}
+
+fn default_scan_interval_ms() -> u64 {
+    1000
+}
+
+fn default_pointer_poll_ms() -> u64 {
+    16
+}
+
+// Well within the double click time of toolkits, 400 to 500ms by default
+fn default_multi_click_delay_ms() -> u64 {
+    60
+}
+
+fn default_page_scroll_amount() -> i32 {
+    15
+}
+
+fn default_held_button_timeout_ms() -> u64 {
+    5 * 60 * 1000
+}
+
+/// Which display `skip_to_cell` picks the cell on
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum SkipToCellDisplay {
+    /// The display under the pointer, moving the overlay there if needed
+    #[default]
+    FollowPointer,
+    /// The display the overlay is on, pulling the pointer onto it if it's elsewhere
+    Stay,
+}
+
+/// What the overlay does after `left_click`, which keeps it open
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum AfterLeftClick {
+    /// Take keyboard focus back right away, to keep clicking
+    #[default]
+    Refocus,
+    /// Leave focus with the clicked application, e.g. to type into the field that got clicked,
+    /// until the overlay gets a key press again
+    Yield,
+    Close,
+}
+
+/// What the overlay does after a click action, per action in `after`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum AfterClick {
+    Exit,
+    /// Keep the overlay up as it is and take keyboard focus back
+    Stay,
+    /// Start over in Screen mode
+    Screen,
+    /// Back to Narrow mode in the region the click was in
+    Narrow,
+}
+
+/// How much of the full-screen grid stays on screen after picking a region, in Narrow, Cell and
+/// recursive narrowing
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ResidualContext {
+    /// The picked rectangle with its grid and labels, nothing around it
+    #[default]
+    Active,
+    /// Only the picked rectangle, without the lines and labels inside it
+    Bare,
+    /// The picked rectangle over a faint copy of the region grid
+    Grid,
+    /// Like `grid`, with the region labels faded in as well
+    Labels,
+}
+
+/// Which cells of the picked region get their key drawn in Narrow mode
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum NarrowLabels {
+    /// The middle row without its outer cells, enough to find the others on the default 5x3 grid
+    #[default]
+    MiddleRow,
+    All,
+    /// The four corner cells
+    Corners,
+}
+
+impl NarrowLabels {
+    fn cells(self, cols: usize, rows: usize) -> Vec<usize> {
+        match self {
+            NarrowLabels::MiddleRow => {
+                let row = rows / 2;
+                let inner = if cols > 2 { 1..cols - 1 } else { 0..cols };
+                inner.map(|col| row * cols + col).collect()
+            }
+            NarrowLabels::All => (0..cols * rows).collect(),
+            NarrowLabels::Corners => {
+                // Fewer on a grid of a single row or column
+                let mut cells = vec![0, cols - 1, (rows - 1) * cols, rows * cols - 1];
+                cells.sort_unstable();
+                cells.dedup();
+                cells
+            }
+        }
+    }
+}
+
+/// Held with a region key in Screen mode, clicks the center of the region and exits instead of
+/// narrowing down. Off unless set, so modifiers with region keys keep doing what they did.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum RegionClickModifier {
+    Shift,
+    Ctrl,
+    Alt,
+    #[default]
+    None,
+}
+
+impl RegionClickModifier {
+    fn is_held(self, modifiers: egui::Modifiers) -> bool {
+        match self {
+            RegionClickModifier::Shift => modifiers.shift,
+            RegionClickModifier::Ctrl => modifiers.ctrl,
+            RegionClickModifier::Alt => modifiers.alt,
+            RegionClickModifier::None => false,
+        }
+    }
+}
+
+/// What one display does differently from the rest of the config, unset parts follow the config
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+struct JsonDisplay {
+    // Replaces `primary_offset_x` / `primary_offset_y`, on any display
+    #[serde(default)]
+    offset_x: Option<i32>,
+    #[serde(default)]
+    offset_y: Option<i32>,
+
+    // At most as many regions and cells as there are `region` and `grid` keys, which use the
+    // first ones
+    #[serde(default)]
+    region_cols: Option<usize>,
+    #[serde(default)]
+    region_rows: Option<usize>,
+    #[serde(default)]
+    cell_cols: Option<usize>,
+    #[serde(default)]
+    cell_rows: Option<usize>,
+
+    // Merged over `style`
+    #[serde(default)]
+    style: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JsonDisplay {
+    fn style(&self, base: &StyleConfig) -> Result<StyleConfig, serde_json::Error> {
+        let mut style = serde_json::to_value(base)?;
+        config::merge(&mut style, serde_json::Value::Object(self.style.clone()));
+        serde_json::from_value(style)
+    }
+
+    fn transform(&self, base: &JsonConfig) -> DisplayConfig {
+        let offset = match (self.offset_x, self.offset_y) {
+            (None, None) => None,
+            (x, y) => Some(vec2(
+                x.unwrap_or(base.primary_offset_x) as f32,
+                y.unwrap_or(base.primary_offset_y) as f32,
+            )),
+        };
+        DisplayConfig {
+            offset,
+            grid: GridSize {
+                regions: [
+                    self.region_cols.unwrap_or(base.region_cols),
+                    self.region_rows.unwrap_or(base.region_rows),
+                ],
+                cells: [
+                    self.cell_cols.unwrap_or(base.cell_cols),
+                    self.cell_rows.unwrap_or(base.cell_rows),
+                ],
+            },
+            // Checked by `check_config`
+            style: self.style(&base.style).unwrap(),
+        }
+    }
+}
+
+impl JsonConfig {
+    // Style overrides that don't make a style, e.g. a size given as a string
+    fn display_problems(&self) -> Vec<Problem> {
+        self.displays
+            .iter()
+            .filter_map(|(name, display)| {
+                let err = display.style(&self.style).err()?;
+                Some(Problem::new(
+                    format!("displays.{name}.style"),
+                    err.to_string(),
+                ))
+            })
+            .collect()
+    }
+
+    fn transform(&self) -> Config {
+        let grid = GridSize {
+            regions: [self.region_cols, self.region_rows],
+            cells: [self.cell_cols, self.cell_rows],
+        };
+        if grid.region_count() == 0 || grid.cell_count() == 0 {
+            panic!("The grid needs at least one region and one cell!");
+        }
+        let key_bindings = self.key_bindings.transform();
+        if key_bindings.region.len() != grid.region_count() {
+            panic!(
+                "{}x{} regions need {} region keys, not {}!",
+                self.region_cols,
+                self.region_rows,
+                grid.region_count(),
+                key_bindings.region.len()
+            );
+        }
+        if key_bindings.grid.len() != grid.cell_count() {
+            panic!(
+                "{}x{} cells need {} grid keys, not {}!",
+                self.cell_cols,
+                self.cell_rows,
+                grid.cell_count(),
+                key_bindings.grid.len()
+            );
+        }
+
+        Config {
+            primary_offset_x: self.primary_offset_x,
+            primary_offset_y: self.primary_offset_y,
+            key_bindings,
+            json_key_bindings: self.key_bindings.clone(),
+            grid,
+            style: self.style,
+            scroll_speed: self.scroll_speed,
+            scroll_acceleration: self.scroll_acceleration,
+            page_scroll_amount: self.page_scroll_amount,
+            movement_speed: self.movement_speed,
+            acceleration: self.acceleration,
+            cell_speedup: self.cell_speedup,
+            sticky_speed_keys: self.sticky_speed_keys,
+            reopen_window_ms: self.reopen_window_ms,
+            respect_fullscreen: self.respect_fullscreen,
+            locale: Locale::detect(self.locale.as_deref()),
+            layout_labels: self.layout_labels,
+            physical_keys: self.physical_keys,
+            skip_to_cell_display: self.skip_to_cell_display,
+            after_left_click: self.after_left_click,
+            after: self
+                .after
+                .iter()
+                .map(|(name, after)| {
+                    let action = name
+                        .parse()
+                        .unwrap_or_else(|err| panic!("Unable to parse action {name}: {err}"));
+                    (action, *after)
+                })
+                .collect(),
+            activate_before_click: self.activate_before_click,
+            held_button_timeout: Duration::from_millis(self.held_button_timeout_ms),
+            multi_click_delay: Duration::from_millis(self.multi_click_delay_ms),
+            click_hold: Duration::from_millis(self.click_hold_ms),
+            region_click_modifier: self.region_click_modifier,
+            residual_context: self.residual_context,
+            narrow_labels: self.narrow_labels,
+            touch_input: self.touch_input,
+            hide_cursor: self.hide_cursor,
+            sticky: self.sticky,
+            exclude_from_capture: self.exclude_from_capture,
+            recursive_narrowing: self.recursive_narrowing,
+            sequence_labels: self.sequence_labels,
+            start_in_hints: self.start_in_hints,
+            count_prefixes: self.count_prefixes,
+            scan_interval: Duration::from_millis(self.scan_interval_ms),
+            gaze_input: self.gaze_input.clone(),
+            hooks: self.hooks.clone(),
+            command_socket: self.command_socket.clone(),
+            transparency_fallback: self.transparency_fallback,
+            pointer_poll_interval: Duration::from_millis(self.pointer_poll_ms),
+            relative_only_windows: self.relative_only_windows.clone(),
+            pointer_backend: self.pointer_backend,
+            event_log: self.event_log.clone(),
+            targets: self.targets.clone(),
+            input_devices: self
+                .input_devices
+                .iter()
+                .map(JsonInputDevice::transform)
+                .collect(),
+            displays: self
+                .displays
+                .iter()
+                .map(|(name, display)| (name.clone(), display.transform(self)))
+                .collect(),
+            profiles: self.profiles.keys().cloned().collect(),
+            profile: self.profile,
+            source: self.source.clone(),
+            scroll_only: false,
+            tutorial: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    primary_offset_x: i32,
+    primary_offset_y: i32,
+    key_bindings: KeyBindings,
+    // As written, for `mirror_layout` to flip
+    json_key_bindings: JsonKeyBindings,
+    grid: GridSize,
+    style: StyleConfig,
+    scroll_speed: i32,
+    scroll_acceleration: Option<ScrollAcceleration>,
+    page_scroll_amount: i32,
+    movement_speed: Speed,
+    acceleration: Option<Acceleration>,
+    cell_speedup: Option<CellSpeedup>,
+    sticky_speed_keys: bool,
+    reopen_window_ms: u64,
+    respect_fullscreen: bool,
+    locale: Locale,
+    layout_labels: bool,
+    physical_keys: bool,
+    skip_to_cell_display: SkipToCellDisplay,
+    after_left_click: AfterLeftClick,
+    after: HashMap<Action, AfterClick>,
+    activate_before_click: bool,
+    held_button_timeout: Duration,
+    multi_click_delay: Duration,
+    click_hold: Duration,
+    region_click_modifier: RegionClickModifier,
+    residual_context: ResidualContext,
+    narrow_labels: NarrowLabels,
+    touch_input: bool,
+    hide_cursor: bool,
+    sticky: bool,
+    exclude_from_capture: bool,
+    recursive_narrowing: bool,
+    sequence_labels: bool,
+    start_in_hints: bool,
+    count_prefixes: bool,
+    scan_interval: Duration,
+    gaze_input: Option<String>,
+    hooks: Hooks,
+    command_socket: Option<String>,
+    transparency_fallback: TransparencyFallback,
+    pointer_poll_interval: Duration,
+    relative_only_windows: Vec<String>,
+    pointer_backend: PointerBackend,
+    event_log: Option<String>,
+    targets: Vec<Target>,
+    input_devices: Vec<InputDevice>,
+    // By output name or index
+    displays: BTreeMap<String, DisplayConfig>,
+    // Profile names, in the order `next_profile` goes through them
+    profiles: Vec<String>,
+    profile: Option<usize>,
+    source: Option<PathBuf>,
+    // Set by `--scroll-only` on the command line: no grid, only moving and scrolling, no reopening
+    scroll_only: bool,
+    // Set when starting without a config file: the overlay walks through picking a region, a cell
+    // and clicking, and the defaults get written to a config file afterwards
+    tutorial: bool,
+}
+
+impl Config {
+    fn profile_name(&self) -> Option<&str> {
+        self.profile.map(|i| self.profiles[i].as_str())
+    }
+
+    // Overrides for display `index`, going by its output name first
+    fn display(&self, index: usize, name: &str) -> Option<&DisplayConfig> {
+        self.displays
+            .get(name)
+            .or_else(|| self.displays.get(&index.to_string()))
+    }
+}
+
+// A display's settings with the rest of the config filled in
+#[derive(Debug, Clone)]
+struct DisplayConfig {
+    offset: Option<Vec2>,
+    grid: GridSize,
+    style: StyleConfig,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Screen,
+    Narrow,
+    Cell,
+    /// Picking a cell within the previous pick, `recursive_narrowing` levels below Narrow mode
+    Recurse(usize),
+    /// Every cell of the display labeled at once with a few `hint_keys`, typed to go there
+    Hints,
+}
+
+// Where the user was when the overlay closed, so a quick reopen can pick up from there
+#[derive(Clone, Copy)]
+struct Session {
+    display: usize,
+    mode: Mode,
+    region: i32,
+    cell: i32,
+    profile: Option<usize>,
+}
+
+// The file given on the command line, or the first config found (see `config::locate`), on top
+// of the built-in defaults, with `profile` applied
+fn read_config(path: Option<&str>, profile: Option<&str>) -> Config {
+    read_json_config(path, profile).transform()
+}
+
+// The config as written, with includes merged and defaults filled in
+fn read_json_config(path: Option<&str>, profile: Option<&str>) -> JsonConfig {
+    let path = config::locate(path.map(Path::new));
+    let source = match &path {
+        Some(path) => {
+            eprintln!("Loading config from {}", path.display());
+            path.display().to_string()
+        }
+        None => {
+            eprintln!("No config file found, using the built-in defaults");
+            "the built-in defaults".to_owned()
+        }
+    };
+    let env_profile = config::env_profile();
+    let profile = profile.or(env_profile.as_deref());
+    let overrides = config::env_overrides();
+    if !overrides.is_empty() {
+        let names: Vec<_> = overrides.keys().map(String::as_str).collect();
+        eprintln!("Overriding {} from KMGRID_* variables", names.join(", "));
+    }
+    // Everything wrong gets reported at once, rather than one panic per fix
+    parse_config(path.as_deref(), profile, &overrides)
+        .unwrap_or_else(|problems| validate::report(&source, &problems))
+}
+
+// The config at `path` over the built-in defaults with `profile` and then `overrides` merged over
+// it, or everything wrong with it
+fn parse_config(
+    path: Option<&Path>,
+    profile: Option<&str>,
+    overrides: &serde_json::Map<String, serde_json::Value>,
+) -> Result<JsonConfig, Vec<Problem>> {
+    let file = match path {
+        Some(path) => config::load(path).map_err(|err| vec![Problem::new("", err)])?,
+        None => serde_json::Value::Object(Default::default()),
+    };
+    let overrides = serde_json::Value::Object(overrides.clone());
+    // The layout preset goes under the file, so its bindings still override single keys. An
+    // unknown layout is reported by `check_config`.
+    let layout = overrides
+        .get("layout")
+        .or(file.get("layout"))
+        .and_then(|layout| serde_json::from_value::<Layout>(layout.clone()).ok())
+        .unwrap_or_default();
+    let mut value = config::defaults();
+    config::merge(
+        &mut value,
+        serde_json::json!({ "key_bindings": layout.key_bindings() }),
+    );
+    config::merge(&mut value, file);
+    let profiles = match value.as_object_mut().and_then(|o| o.remove("profiles")) {
+        None => serde_json::Map::new(),
+        Some(serde_json::Value::Object(profiles)) => profiles,
+        Some(other) => {
+            let message = format!("Needs to map profile names to config overrides, not {other}");
+            return Err(vec![Problem::new("profiles", message)]);
+        }
+    };
+
+    // Every profile gets checked, so switching to one later can't fail
+    let mut problems = Vec::new();
+    let mut selected = None;
+    let mut base = value.clone();
+    config::merge(&mut base, overrides.clone());
+    let base = check_config(base).map_err(|found| problems.extend(found));
+    for (i, (name, profile_overrides)) in profiles.iter().enumerate() {
+        if !profile_overrides["layout"].is_null() {
+            problems.push(Problem::new(
+                format!("profiles.{name}.layout"),
+                "Only the config itself can pick the layout",
+            ));
+        }
+        let mut merged = value.clone();
+        config::merge(&mut merged, profile_overrides.clone());
+        config::merge(&mut merged, overrides.clone());
+        match check_config(merged) {
+            Ok(config) if profile == Some(name) => selected = Some((i, config)),
+            Ok(_) => {}
+            Err(found) => problems.extend(found.into_iter().map(|problem| {
+                let path = match problem.path.as_str() {
+                    "" => format!("profiles.{name}"),
+                    path => format!("profiles.{name}.{path}"),
+                };
+                Problem::new(path, problem.message)
+            })),
+        }
+    }
+    if let Some(name) = profile.filter(|name| !profiles.contains_key(*name)) {
+        let names: Vec<_> = profiles.keys().map(String::as_str).collect();
+        problems.push(Problem::new(
+            "profiles",
+            format!("No profile named {name:?}, there are {names:?}"),
+        ));
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let (profile, mut config) = match selected {
+        Some((i, config)) => (Some(i), config),
+        None => (None, base.unwrap()),
+    };
+    config.profiles = profiles;
+    config.profile = profile;
+    config.source = path.map(Path::to_owned);
+    Ok(config)
+}
+
+// Whether a config, after merging, would run
+fn check_config(value: serde_json::Value) -> Result<JsonConfig, Vec<Problem>> {
+    let problems = validate::check(&value);
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    let config = serde_json::from_value::<JsonConfig>(value)
+        .map_err(|err| vec![Problem::new("", err.to_string())])?;
+    let problems = config.display_problems();
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    match DispatchTable::new(&config.transform().key_bindings) {
+        Ok(_) => Ok(config),
+        Err(conflicts) => Err(conflicts
+            .lines()
+            .map(|conflict| Problem::new("key_bindings", conflict))
+            .collect()),
+    }
+}
+
+// The config file again after it changed or to switch profiles. On problems the caller keeps the
+// config it has.
+fn reread_config(
+    path: &Path,
+    profile: Option<&str>,
+    scroll_only: bool,
+) -> Result<(Config, DispatchTable), Vec<Problem>> {
+    let mut config = parse_config(Some(path), profile, &config::env_overrides())?.transform();
+    config.scroll_only = scroll_only;
+    // Conflicts were checked by `parse_config`
+    let mut dispatch = DispatchTable::new(&config.key_bindings).unwrap();
+    if scroll_only {
+        dispatch = dispatch.scroll_only();
+    }
+    Ok((config, dispatch))
+}
+
+/// `kmgrid dump-default-config [--toml] [path]`: writes the built-in config with every setting
+/// filled in and commented, as JSON or as TOML with `--toml` or a `.toml` path
+fn dump_default_config(toml: bool, path: Option<&str>) {
+    let toml = toml || path.is_some_and(|path| path.ends_with(".toml"));
+    let text = default_config_text(toml);
+    match path {
+        Some(path) => {
+            std::fs::write(path, text).unwrap_or_else(|err| panic!("Unable to write {path}: {err}"))
+        }
+        None => print!("{text}"),
+    }
+}
+
+// The built-in defaults with a comment on every setting
+fn default_config_text(toml: bool) -> String {
+    let config = parse_config(None, None, &Default::default())
+        .unwrap_or_else(|problems| validate::report("the built-in defaults", &problems));
+    let config = serde_json::to_value(config).unwrap();
+    if toml {
+        dump::toml(&config)
+    } else {
+        dump::json(&config)
+    }
+}
+
+// After the first-run tutorial, so the next start finds a config to edit and skips the tutorial
+fn write_first_config() {
+    let Some(path) = config::new_path() else {
+        return;
+    };
+    let written = std::fs::create_dir_all(path.parent().unwrap()).and_then(|()| {
+        // Never over a config that turned up in the meantime
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?
+            .write_all(default_config_text(false).as_bytes())
+    });
+    match written {
+        Ok(()) => eprintln!(
+            "Wrote the default config to {}, edit it to change keys and colors",
+            path.display()
+        ),
+        Err(err) => eprintln!("Unable to write the config {}: {err}", path.display()),
+    }
+}
+
+/// `kmgrid suggest-layout`: prints `region` and `grid` bindings that put the regions and cells
+/// picked most in the event log on the `easy_keys`, ready to paste into the config
+fn suggest_layout(config: &Config) {
+    let path = config
+        .event_log
+        .as_deref()
+        .expect("kmgrid suggest-layout needs event_log in the config!");
+    let events = eventlog::read(path)
+        .unwrap_or_else(|err| panic!("Unable to read the event log {path}: {err}"));
+    let bindings = &config.key_bindings;
+    if bindings.easy_keys.is_empty() {
+        eprintln!("No easy_keys in the key bindings, keeping the current order");
+    }
+
+    let heatmap = Heatmap::new(&events);
+    let region_hits = heatmap.region_totals(bindings.region.len());
+    let cell_hits = heatmap.cell_totals(bindings.grid.len());
+    let names = |keys: Vec<Key>| keys.into_iter().map(Key::name).collect::<Vec<_>>();
+    let fragment = serde_json::json!({
+        "key_bindings": {
+            "region": names(suggest::suggest(&region_hits, &bindings.region, &bindings.easy_keys)),
+            "grid": names(suggest::suggest(&cell_hits, &bindings.grid, &bindings.easy_keys)),
+        }
+    });
+    println!("{}", serde_json::to_string_pretty(&fragment).unwrap());
+}
+
+/// Keyboard driven mouse control: a grid of regions and cells over the screen, picked with keys
+#[derive(clap::Parser)]
+#[command(
+    version,
+    disable_version_flag = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
+    /// Print the version and exit
+    #[arg(short = 'V', long)]
+    version: bool,
+
+    /// With `--version`: the version, commit and what this build supports as JSON
+    #[arg(long, requires = "version")]
+    json: bool,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(clap::Subcommand)]
+enum CliCommand {
+    /// Opens the overlay window once and keeps it hidden while closed, for the `reopen` key
+    Daemon(RunArgs),
+    /// Releases the left button held down by `drag_and_exit`
+    Release,
+    /// Prints the state of a running overlay from its `command_socket`
+    Status {
+        /// Keep printing every change
+        #[arg(long)]
+        follow: bool,
+        config: Option<String>,
+    },
+    /// Prints the config with includes and defaults filled in
+    DumpConfig { config: Option<String> },
+    /// Writes the built-in config with a comment on every setting
+    DumpDefaultConfig {
+        /// TOML instead of JSON, also picked by a path ending in `.toml`
+        #[arg(long)]
+        toml: bool,
+        /// Where to write it instead of standard output
+        path: Option<String>,
+    },
+    /// Shows every display's index and output name on it
+    Identify,
+    /// Lists every key event as kmgrid receives it, to find out why a key does nothing
+    InputDebug,
+    /// Prints `region` and `grid` bindings that put the most picked spots on the `easy_keys`
+    SuggestLayout { config: Option<String> },
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    /// Config file to use instead of looking for one
+    config: Option<String>,
+
+    /// No grid, only moving and scrolling from the start
+    #[arg(long)]
+    scroll_only: bool,
+
+    /// One of the config's `profiles` to start with
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Index of the display to start on, as `kmgrid identify` shows them, instead of the one
+    /// with the pointer
+    #[arg(long)]
+    screen: Option<usize>,
+
+    /// Mode to start in, on the region or cell under the pointer
+    #[arg(long, value_enum, default_value_t = StartMode::Screen)]
+    mode: StartMode,
+
+    /// Put the pointer on this spot of the screen first, e.g. `--at 800,600`
+    #[arg(long, value_name = "X,Y", value_parser = parse_point)]
+    at: Option<Pos2>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+enum StartMode {
+    Screen,
+    Narrow,
+    Cell,
+}
+
+fn parse_point(s: &str) -> Result<Pos2, String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("Needs to be X,Y, not {s:?}"))?;
+    let coordinate = |c: &str| {
+        c.trim()
+            .parse::<i32>()
+            .map_err(|err| format!("Invalid coordinate {c:?}: {err}"))
+    };
+    Ok(pos2(coordinate(x)? as f32, coordinate(y)? as f32))
+}
+
+fn main() -> eframe::Result {
+    let cli = <Cli as clap::Parser>::parse();
+    if cli.version {
+        buildinfo::print(cli.json);
+        return Ok(());
+    }
+    let (run, daemon) = match cli.command {
+        None => (cli.run, false),
+        Some(CliCommand::Daemon(run)) => (run, true),
+        Some(CliCommand::Release) => {
+            release_left_button();
+            return Ok(());
+        }
+        Some(CliCommand::Status { follow, config }) => {
+            let config = read_config(config.as_deref(), None);
+            let socket = config
+                .command_socket
+                .expect("kmgrid status needs command_socket in the config!");
+            ipc::print_status(&socket, follow);
+            return Ok(());
+        }
+        Some(CliCommand::DumpConfig { config }) => {
+            let config = read_json_config(config.as_deref(), None);
+            // Resolving bindings panics on anything invalid, better than dumping it
+            config.transform();
+            println!("{}", serde_json::to_string_pretty(&config).unwrap());
+            return Ok(());
+        }
+        Some(CliCommand::DumpDefaultConfig { toml, path }) => {
+            dump_default_config(toml, path.as_deref());
+            return Ok(());
+        }
+        Some(CliCommand::Identify) => {
+            let infos = DisplayInfo::all().expect("Unable to get display info!");
+            return identify::run(infos.into_iter().map(display_label).collect());
+        }
+        Some(CliCommand::InputDebug) => return inputdebug::run(),
+        Some(CliCommand::SuggestLayout { config }) => {
+            suggest_layout(&read_config(config.as_deref(), None));
+            return Ok(());
+        }
+    };
+
+    let scroll_only = run.scroll_only;
+    let mut config = read_config(run.config.as_deref(), run.profile.as_deref());
+    config.scroll_only = scroll_only;
+    config.tutorial = config.source.is_none() && !scroll_only && !daemon;
+    let watcher = config.source.as_deref().and_then(|path| {
+        ConfigWatcher::spawn(path)
+            .inspect_err(|err| eprintln!("Not reloading {} on changes: {err}", path.display()))
+            .ok()
+    });
+    println!("Config {config:#?}");
+    let mut dispatch = DispatchTable::new(&config.key_bindings)
+        .unwrap_or_else(|conflicts| panic!("Conflicting key bindings!\n{conflicts}"));
+    if scroll_only {
+        dispatch = dispatch.scroll_only();
+    }
+
+    let display_infos = DisplayInfo::all().expect("Unable to get display info!");
+    let mut displays = to_displays(&display_infos, &config);
+
+    // Without pointer input the overlay still opens on the display of `--at`
+    if let Some(at) = run.at {
+        let moved = Enigo::new(&Settings::default())
+            .map_err(|err| err.to_string())
+            .and_then(|mut enigo| {
+                enigo
+                    .move_mouse(at.x as i32, at.y as i32, enigo::Coordinate::Abs)
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = moved {
+            eprintln!("Unable to move the pointer to {},{}: {err}", at.x, at.y);
+        }
+    }
+    let initial_display_idx = match run.screen {
+        Some(screen) if screen >= displays.len() => {
+            eprintln!("There's no display {screen}, only {}", displays.len());
+            std::process::exit(2)
+        }
+        Some(screen) => screen,
+        None => run
+            .at
+            .and_then(|at| displays.iter().position(|d| d.bounds().contains(at)))
+            .unwrap_or_else(|| display_under_pointer(&displays)),
+    };
+
+    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+
+    let device_state = DeviceState::new();
+    let keys: Vec<Keycode> = device_state.get_keys();
+    println!("{keys:#?}");
+
+    // Neither is worth failing to start over, the keyboard still works without them
+    let gaze = config.gaze_input.as_deref().and_then(|source| {
+        GazeInput::spawn(source)
+            .map_err(|err| eprintln!("Unable to listen for gaze input on {source}: {err}"))
+            .ok()
+    });
+    let commands = config.command_socket.as_deref().and_then(|path| {
+        CommandQueue::spawn(path)
+            .map_err(|err| eprintln!("Unable to listen for commands on {path}: {err}"))
+            .ok()
+    });
+    let devices = (!config.input_devices.is_empty()).then(|| {
+        DeviceInput::spawn(&config.input_devices)
+            .unwrap_or_else(|err| panic!("Unable to read the input devices: {err}"))
+    });
+    let listeners = Listeners {
+        gaze,
+        commands,
+        devices,
+        watcher,
+    };
+
+    let mut session = Session {
+        display: initial_display_idx,
+        mode: Mode::Screen,
+        region: 0,
+        cell: -1,
+        profile: config.profile,
+    };
+    if run.mode != StartMode::Screen && !scroll_only {
+        // The spot the pointer was put on, or where it is, if it's on the starting display
+        let display = &displays[initial_display_idx];
+        let pointer = run.at.unwrap_or_else(|| {
+            let (x, y) = DeviceState::new().query_pointer().coords;
+            pos2(x as f32, y as f32)
+        });
+        let (region, cell) = display
+            .geometry()
+            .locate(pointer)
+            .unwrap_or_else(|| display.geometry().locate(display.area().center()).unwrap());
+        session.region = region as i32;
+        if run.mode == StartMode::Cell {
+            session.mode = Mode::Cell;
+            session.cell = cell as i32;
+        } else {
+            session.mode = Mode::Narrow;
+        }
+    }
+    clean_up_held_button(&config);
+    if daemon {
+        return run_daemon(config, dispatch, displays, listeners, session);
+    }
+    let mut config_generation = listeners
+        .watcher
+        .as_ref()
+        .map_or(0, ConfigWatcher::generation);
+    loop {
+        // Changes to the file made while the overlay was closed, or a profile picked in it
+        let generation = listeners
+            .watcher
+            .as_ref()
+            .map_or(0, ConfigWatcher::generation);
+        if generation != config_generation || session.profile != config.profile {
+            config_generation = generation;
+            let profile = session.profile.map(|i| config.profiles[i].clone());
+            let path = config.source.clone().unwrap();
+            match reread_config(&path, profile.as_deref(), scroll_only) {
+                Ok((new_config, new_dispatch)) => {
+                    config = new_config;
+                    dispatch = new_dispatch;
+                    let new_displays = to_displays(&display_infos, &config);
+                    if new_displays != displays {
+                        // The region or cell to reopen at may be gone with the old grid
+                        session = Session {
+                            display: session.display.min(new_displays.len() - 1),
+                            mode: Mode::Screen,
+                            region: 0,
+                            cell: -1,
+                            profile: config.profile,
+                        };
+                        displays = new_displays;
+                    }
+                }
+                Err(problems) => {
+                    session.profile = config.profile;
+                    validate::print(&path.display().to_string(), &problems)
+                }
+            }
+            if let Some(watcher) = &listeners.watcher {
+                watcher.refresh();
+            }
+        }
+
+        let last_session = Rc::new(Cell::new(None));
+        run_overlay(
+            config.clone(),
+            dispatch.clone(),
+            displays.clone(),
+            listeners.clone(),
+            session,
+            last_session.clone(),
+        )?;
+        if config.tutorial {
+            config.tutorial = false;
+            write_first_config();
+        }
+
+        match last_session.get() {
+            Some(s) if config.reopen_window_ms > 0 && !scroll_only && wait_for_reopen(&config) => {
+                session = s
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+// `kmgrid release`: finishes a drag started with `drag_and_exit`
+// Runs before any window exists, so failures are printed rather than panicked over. The button
+// stays recorded as held then, for the next run to try again.
+fn release_left_button() {
+    let released = Enigo::new(&Settings::default())
+        .map_err(|err| err.to_string())
+        .and_then(|mut enigo| {
+            enigo
+                .button(Button::Left, enigo::Direction::Release)
+                .map_err(|err| err.to_string())
+        });
+    match released {
+        Ok(()) => state::record_left_button_released(),
+        Err(err) => eprintln!("Unable to release the left button: {err}"),
+    }
+}
+
+// A left button an earlier run left down is forgotten if it's been let go of since, and released
+// once it's been down for `held_button_timeout`. Before that, it's likely a drag still underway.
+fn clean_up_held_button(config: &Config) {
+    let Some(held_for) = state::left_button_held_for() else {
+        return;
+    };
+    if !DeviceState::new().get_mouse().button_pressed[1] {
+        state::record_left_button_released();
+    } else if held_for >= config.held_button_timeout {
+        println!(
+            "Releasing the left button, held since {}s ago",
+            held_for.as_secs()
+        );
+        release_left_button();
+    } else {
+        println!(
+            "The left button is still held from {}s ago, `left_click_up` releases it",
+            held_for.as_secs()
+        );
+    }
+}
+
+// A line of text on a dark backing, centered at the top of the overlay
+fn draw_banner(painter: &egui::Painter, window: Rect, text: String, color: Color32) {
+    let galley = painter.layout_no_wrap(text, egui::FontId::proportional(28.0), color);
+    let rect = Align2::CENTER_TOP.anchor_size(window.center_top() + vec2(0.0, 40.0), galley.size());
+    painter.rect_filled(
+        rect.expand(8.0),
+        Rounding::same(4.0),
+        Color32::from_black_alpha(200),
+    );
+    painter.galley(rect.min, galley, color);
+}
+
+fn to_displays(display_infos: &[DisplayInfo], config: &Config) -> Vec<Display> {
+    display_infos
+        .iter()
+        .enumerate()
+        .map(|(i, d)| {
+            let own = config.display(i, &d.name);
+            let offset = match own.and_then(|own| own.offset) {
+                Some(offset) => offset,
+                None if d.is_primary => vec2(
+                    config.primary_offset_x as f32,
+                    config.primary_offset_y as f32,
+                ),
+                None => vec2(0.0, 0.0),
+            };
+            let display = Display::new(
+                pos2(d.x as f32, d.y as f32),
+                vec2(d.width as f32, d.height as f32),
+                vec2(d.width_mm as f32, d.height_mm as f32),
+                offset,
+                d.is_primary,
+                own.map_or(config.grid, |own| own.grid),
+                own.map_or(config.style, |own| own.style),
+            );
+            Display {
+                scale: outline_scale(d),
+                ..display
+            }
+        })
+        .collect()
+}
+
+// X servers without `Xft.dpi` report a scale factor of 1 on HiDPI displays too, the physical size
+// tells those apart
+fn outline_scale(info: &DisplayInfo) -> f32 {
+    if info.scale_factor > 1.0 {
+        info.scale_factor
+    } else if info.width_mm > 0 {
+        let dpi = info.width as f32 / (info.width_mm as f32 / 25.4);
+        (dpi / 96.0).round().max(1.0)
+    } else {
+        1.0
+    }
+}
+
+// Bounds and output name of a display, for `Identify`
+fn display_label(info: DisplayInfo) -> (Rect, String) {
+    let rect = Rect::from_min_size(
+        pos2(info.x as f32, info.y as f32),
+        vec2(info.width as f32, info.height as f32),
+    );
+    (rect, info.name)
+}
+
+// Index of the display the pointer is on
+fn display_under_pointer(displays: &[Display]) -> usize {
+    let mouse_pos = DeviceState::new().query_pointer().coords;
+    let mouse_pos = pos2(mouse_pos.0 as f32, mouse_pos.1 as f32);
+    // Right after a RandR change the pointer can briefly be outside every display
+    match displays.iter().position(|d| d.bounds().contains(mouse_pos)) {
+        Some(i) => i,
+        None => {
+            let primary = primary_display(displays);
+            eprintln!(
+                "Pointer at {},{} is on no display, starting on the primary display {primary}",
+                mouse_pos.x, mouse_pos.y
+            );
+            primary
+        }
+    }
+}
+
+// Index of the primary display, or the first one if none is marked primary
+fn primary_display(displays: &[Display]) -> usize {
+    displays.iter().position(|d| d.primary).unwrap_or(0)
+}
+
+// Started once, they have to outlive the overlay reopening
+#[derive(Clone)]
+struct Listeners {
+    gaze: Option<GazeInput>,
+    commands: Option<CommandQueue>,
+    devices: Option<DeviceInput>,
+    watcher: Option<ConfigWatcher>,
+}
+
+fn run_overlay(
+    config: Config,
+    dispatch: DispatchTable,
+    displays: Vec<Display>,
+    listeners: Listeners,
+    session: Session,
+    last_session: Rc<Cell<Option<Session>>>,
+) -> eframe::Result {
+    let Listeners {
+        gaze,
+        commands,
+        devices,
+        watcher,
+    } = listeners;
+    let display = displays[session.display];
+    let options = eframe::NativeOptions {
+        viewport: overlay_viewport(&display),
+        ..Default::default()
+    };
+    let mut state = overlay_state(config, dispatch, displays, gaze, commands, devices);
+    start_session(&mut state, session);
+    let mut app = MyApp {
+        state,
+        last_session,
+        daemon: None,
+        config_generation: watcher.as_ref().map_or(0, ConfigWatcher::generation),
+        watcher,
+    };
+    app.warn_clamped_offsets();
+    app.run_hook(HookEvent::Open, None);
+
+    eframe::run_native(
+        "Custom window frame", // unused title
+        options,
+        Box::new(|_cc| Ok(Box::new(app))),
+    )
+}
+
+/// `kmgrid daemon`: opens the overlay window once and keeps it hidden while closed, so the
+/// `reopen` key brings the grid up without starting a new window
+fn run_daemon(
+    config: Config,
+    dispatch: DispatchTable,
+    displays: Vec<Display>,
+    listeners: Listeners,
+    session: Session,
+) -> eframe::Result {
+    let Listeners {
+        gaze,
+        commands,
+        devices,
+        watcher,
+    } = listeners;
+    let device_reopen = config.input_devices.iter().any(|device| {
+        device
+            .bindings
+            .iter()
+            .any(|(_, action)| *action == DeviceAction::Reopen)
+    });
+    let keys = config.key_bindings.reopen.clone().unwrap_or_default();
+    let forced_keys = config.key_bindings.reopen_anyway.clone();
+    if keys.is_empty() && forced_keys.is_empty() && !device_reopen {
+        panic!("kmgrid daemon needs key_bindings.reopen or an input device bound to reopen!");
+    }
+    let mut options = eframe::NativeOptions {
+        viewport: overlay_viewport(&displays[session.display]),
+        ..Default::default()
+    };
+    options.viewport = options.viewport.with_visible(false);
+    // The session starts with `show`, the hidden overlay has none
+    let state = overlay_state(config, dispatch, displays, gaze, commands, devices.clone());
+
+    eframe::run_native(
+        "Custom window frame", // unused title
+        options,
+        Box::new(move |cc| {
+            let activation = Activation::spawn(keys, cc.egui_ctx.clone());
+            let forced = (!forced_keys.is_empty())
+                .then(|| Activation::spawn(forced_keys, cc.egui_ctx.clone()));
+            if let Some(devices) = &devices {
+                devices.wake(cc.egui_ctx.clone());
+            }
+            Ok(Box::new(MyApp {
+                state,
+                last_session: Rc::new(Cell::new(None)),
+                daemon: Some(Daemon {
+                    activation,
+                    forced,
+                    hidden: true,
+                }),
+                config_generation: watcher.as_ref().map_or(0, ConfigWatcher::generation),
+                watcher,
+            }))
+        }),
+    )
+}
+
+// The window on display `i` other than the root one
+fn display_viewport(i: usize) -> ViewportId {
+    ViewportId::from_hash_of(("kmgrid display", i))
+}
+
+fn overlay_viewport(display: &Display) -> egui::ViewportBuilder {
+    egui::ViewportBuilder::default()
+        .with_decorations(false) // Hide the OS-specific "chrome" around the window
+        .with_window_type(egui::X11WindowType::Utility)
+        .with_mouse_passthrough(true)
+        .with_always_on_top()
+        .with_transparent(true)
+        .with_position(display.area().min)
+        .with_resizable(false)
+        .with_maximized(false)
+        .with_inner_size(display.area().size())
+        .with_fullscreen(false)
+}
+
+// Applies `sticky` and `exclude_from_capture` to `handle`, returning false if it couldn't be left
+// out of captures
+fn set_up_native_window(config: &Config, handle: RawWindowHandle) -> bool {
+    if config.sticky {
+        match handle {
+            RawWindowHandle::Xlib(handle) => window::make_sticky(handle.window),
+            RawWindowHandle::Xcb(handle) => window::make_sticky(handle.window.get().into()),
+            _ => {}
+        }
+    }
+    !config.exclude_from_capture || window::exclude_from_capture(handle)
+}
+
+// What the overlay keeps from one session to the next: the config, the workers and connections
+// it starts and the toggles made in it. `start_session` fills in the rest.
+fn overlay_state(
+    config: Config,
+    dispatch: DispatchTable,
+    displays: Vec<Display>,
+    gaze: Option<GazeInput>,
+    commands: Option<CommandQueue>,
+    devices: Option<DeviceInput>,
+) -> SharedState {
+    let x_connection = window::XConnection::open();
+    let layout_group = x_connection
+        .as_ref()
+        .and_then(labels::layout_group)
+        .unwrap_or(0);
+    let labels = KeyLabels::new(config.layout_labels, layout_group);
+    let pointer = PointerTracker::spawn(config.pointer_poll_interval);
+    let mouse = spawn_mouse(&config, &displays);
+    let event_log = config.event_log.as_deref().map(|path| {
+        EventLog::open(path)
+            .unwrap_or_else(|err| panic!("Unable to open the event log {path}: {err}"))
+    });
+    let touch = config.touch_input;
+    SharedState {
+        displays,
+        current_display: 0,
+        root_display: 0,
+        taps: Vec::new(),
+        config,
+        mode: Mode::Screen,
+        region: 0,
+        cell: -1,
+        pointer,
+        mouse,
+        mouse_key_down: std::collections::HashSet::new(),
+        key_modifiers: HashMap::new(),
+        latched_speeds: Vec::new(),
+        dispatch,
+        toasts: Toasts::default(),
+        labels,
+        move_remainder: Vec2::ZERO,
+        moving_for: 0.0,
+        scrolling_for: 0.0,
+        cell_since: None,
+        touch,
+        passthrough: Vec::new(),
+        window_set_up: false,
+        viewports_set_up: HashSet::new(),
+        focus_yielded: false,
+        mirrored: false,
+        pending_macro: None,
+        held_click: None,
+        relative_only: false,
+        event_log,
+        heatmap: None,
+        hidden_cursor: None,
+        identify: None,
+        search: None,
+        nudge: None,
+        warp: None,
+        history: PositionHistory::new(state::positions()),
+        sequence: None,
+        hint_typed: Vec::new(),
+        count: None,
+        drag_from: None,
+        paused: false,
+        target_window: None,
+        window_grab: None,
+        picks: Vec::new(),
+        layout_group,
+        x_connection,
+        scanner: None,
+        gaze,
+        commands,
+        devices,
+        backdrop: None,
+        label_shades: None,
+        last_display_check: Instant::now(),
+    }
+}
+
+// Displays left of or above the primary one can sit at negative coordinates, the pointer backend
+// covers all of them
+fn spawn_mouse(config: &Config, displays: &[Display]) -> MouseWorker {
+    let desktop = Desktop::spanning(displays.iter().map(Display::bounds));
+    let mouse = MouseWorker::spawn(config.pointer_backend, desktop);
+    if let Some(err) = mouse.failure() {
+        eprintln!(
+            "Unable to send pointer input: {err}\nThe overlay opens anyway, clicks print the position they would click at"
+        );
+    }
+    mouse
+}
+
+// Starts the overlay over at `session`, as freshly opened: what was picked, typed or held in the
+// last one is gone. Has to run before the overlay shows, for the screenshots.
+fn start_session(state: &mut SharedState, session: Session) {
+    let config = &state.config;
+    // Checked before the overlay takes focus itself
+    let relative_only = config.scroll_only
+        || window::active_window_class().is_some_and(|class| {
+            config
+                .relative_only_windows
+                .iter()
+                .any(|pattern| class.matches(pattern))
+        });
+    let mode = if relative_only {
+        Mode::Cell
+    } else if config.start_in_hints && session.mode == Mode::Screen {
+        Mode::Hints
+    } else {
+        session.mode
+    };
+
+    // Commands sent while the overlay was closed are stale by now, and so are device presses,
+    // which nothing reads in between overlays outside of daemon mode
+    if let Some(commands) = &state.commands {
+        commands.take();
+    }
+    if let Some(devices) = &state.devices {
+        devices.take_presses();
+    }
+
+    let areas: Vec<_> = state.displays.iter().map(Display::area).collect();
+    state.backdrop = Backdrop::new(config.transparency_fallback, &areas);
+    state.label_shades = state
+        .displays
+        .iter()
+        .any(|d| d.style.auto_label_color)
+        .then(|| LabelShades::capture(&areas));
+    state.hidden_cursor = config.hide_cursor.then(HiddenCursor::new).flatten();
+    state.scanner = config
+        .key_bindings
+        .scan_select
+        .first()
+        .map(|_| Scanner::new(config.scan_interval, mode));
+    state.target_window = window::active_window_id();
+
+    state.current_display = session.display;
+    state.root_display = session.display;
+    state.mode = mode;
+    state.region = session.region;
+    state.cell = session.cell;
+    state.relative_only = relative_only;
+    state.taps.clear();
+    state.mouse_key_down.clear();
+    state.key_modifiers.clear();
+    state.latched_speeds.clear();
+    state.move_remainder = Vec2::ZERO;
+    state.moving_for = 0.0;
+    state.scrolling_for = 0.0;
+    state.cell_since = None;
+    // The windows of a hidden overlay may be new or have lost their settings
+    state.passthrough.clear();
+    state.window_set_up = false;
+    state.viewports_set_up.clear();
+    state.focus_yielded = false;
+    state.pending_macro = None;
+    state.held_click = None;
+    state.heatmap = None;
+    state.identify = None;
+    state.search = None;
+    state.nudge = None;
+    state.warp = None;
+    state.sequence = None;
+    state.hint_typed.clear();
+    state.count = None;
+    state.drag_from = None;
+    state.paused = false;
+    state.window_grab = None;
+    state.picks.clear();
+    state.last_display_check = Instant::now();
+}
+
+// Whether `respect_fullscreen` keeps the overlay from reopening right now
+fn over_fullscreen(config: &Config) -> bool {
+    config.respect_fullscreen && window::active_window_fullscreen()
+}
+
+// After the overlay closes, keep watching the keyboard for `reopen_window_ms`. Returns true if
+// the reopen key (or any key, if none is bound) got pressed in time, or `reopen_anyway` did.
+fn wait_for_reopen(config: &Config) -> bool {
+    let device_state = DeviceState::new();
+    let deadline = Instant::now() + Duration::from_millis(config.reopen_window_ms);
+
+    // Keys still held from closing the overlay don't count until they are released
+    let mut held = device_state.get_keys();
+    while Instant::now() < deadline {
+        let keys = device_state.get_keys();
+        let pressed = |k: &&Keycode| !held.contains(k);
+        if keys
+            .iter()
+            .filter(pressed)
+            .any(|k| config.key_bindings.reopen_anyway.contains(k))
+        {
+            return true;
+        }
+        let reopen = keys.iter().filter(pressed).any(|k| {
+            config
+                .key_bindings
+                .reopen
+                .as_ref()
+                .is_none_or(|r| r.contains(k))
+        });
+        if reopen && !over_fullscreen(config) {
+            return true;
+        }
+        held = keys;
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    false
+}
+
+struct MyApp {
+    state: SharedState,
+    last_session: Rc<Cell<Option<Session>>>,
+    daemon: Option<Daemon>,
+    // Watches the config file for hot reloading, with the change the overlay last applied
+    watcher: Option<ConfigWatcher>,
+    config_generation: u64,
+}
+
+// A click key that went down with `click_hold_ms` set, which clicks once it has been down long
+// enough. Kept until the key is let go of, so it clicks only once however long it's held.
+#[derive(Clone, Copy)]
+struct HeldClick {
+    binding: Binding,
+    since: Instant,
+    clicked: bool,
+}
+
+// What's left of the display for the warp keys to halve, and where they last put the pointer
+#[derive(Clone, Copy)]
+struct Warp {
+    area: Rect,
+    pos: Pos2,
+}
+
+// The window `move_window` or `resize_window` has hold of, with where its contents started and
+// where they are now, in screen coordinates
+struct WindowGrab {
+    // Kept open while the window is moved, rather than connecting again every frame
+    connection: window::XConnection,
+    window: x11::xlib::Window,
+    start: Rect,
+    rect: Rect,
+    // Sides the movement keys drag when resizing, per axis: -1 for left or top, 1 for right or
+    // bottom, 0 for neither. None moves the whole window.
+    edges: Option<[i8; 2]>,
+}
+
+impl WindowGrab {
+    fn new(window: x11::xlib::Window, edges: Option<[i8; 2]>) -> Option<Self> {
+        let connection = window::XConnection::open()?;
+        let (x, y, width, height) = connection.window_geometry(window)?;
+        let start =
+            Rect::from_min_size(pos2(x as f32, y as f32), vec2(width as f32, height as f32));
+        Some(WindowGrab {
+            connection,
+            window,
+            start,
+            rect: start,
+            edges,
+        })
+    }
+
+    fn shift(&mut self, delta: Vec2) {
+        let Some(edges) = self.edges else {
+            self.rect = self.rect.translate(delta);
+            return;
+        };
+        let mut rect = self.rect;
+        for axis in 0..2 {
+            match edges[axis] {
+                -1 => rect.min[axis] += delta[axis],
+                1 => rect.max[axis] += delta[axis],
+                _ => {}
+            }
+        }
+        if rect.width() >= MIN_WINDOW_SIZE && rect.height() >= MIN_WINDOW_SIZE {
+            self.rect = rect;
+        }
+    }
+
+    fn apply(&self, rect: Rect) {
+        let (min, size) = (rect.min.round(), rect.size().round());
+        self.connection.move_resize_window(
+            self.window,
+            min.x as i32,
+            min.y as i32,
+            size.x as u32,
+            size.y as u32,
+        );
+    }
+}
+
+// The sides of `rect` closest to `pointer`, for `resize_window`: a corner or an edge, depending
+// on which third of the window the pointer is in, and the bottom right corner from the middle
+fn nearest_edges(rect: Rect, pointer: Pos2) -> [i8; 2] {
+    let side = |axis: usize| {
+        let t = (pointer[axis] - rect.min[axis]) / rect.size()[axis];
+        if t < 1.0 / 3.0 {
+            -1
+        } else if t > 2.0 / 3.0 {
+            1
+        } else {
+            0
+        }
+    };
+    match [side(0), side(1)] {
+        [0, 0] => [1, 1],
+        edges => edges,
+    }
+}
+
+// Keeps the window around while the overlay is closed, see `kmgrid daemon`
+struct Daemon {
+    activation: Activation,
+    // Watches `reopen_anyway`, when bound
+    forced: Option<Activation>,
+    hidden: bool,
+}
+
+struct SharedState {
+    displays: Vec<Display>,
+    current_display: usize,
+    config: Config,
+    mode: Mode,
+    region: i32,
+    cell: i32,
+    pointer: PointerTracker,
+    mouse: MouseWorker,
+    mouse_key_down: std::collections::HashSet<Key>,
+    // Modifiers each key last went down with, see `MyApp::key_modifiers`
+    key_modifiers: HashMap<Key, Modifiers>,
+    // Speed keys tapped with `sticky_speed_keys`, and whether they're locked rather than latched
+    latched_speeds: Vec<(Action, bool)>,
+    dispatch: DispatchTable,
+    toasts: Toasts,
+    labels: KeyLabels,
+    move_remainder: Vec2,
+    // Seconds the movement keys have been held in a row, for `acceleration`
+    moving_for: f32,
+    // Seconds the scroll keys have been held in a row, for `scroll_acceleration`
+    scrolling_for: f32,
+    // When the overlay last went into Cell mode, for `cell_speedup`
+    cell_since: Option<Instant>,
+    last_display_check: Instant,
+    touch: bool,
+    // Whether the window on each display lets the mouse through
+    passthrough: Vec<bool>,
+    // Actions of touch taps on the active display, for the next `handle_input`
+    taps: Vec<Action>,
+    // Display of the root window, which gets the keyboard input
+    root_display: usize,
+    // Whether the native window got `sticky` and `exclude_from_capture` applied yet
+    window_set_up: bool,
+    // The same for the viewports of the other displays
+    viewports_set_up: HashSet<ViewportId>,
+    // Stop pulling keyboard focus back every frame, see `AfterLeftClick::Yield`
+    focus_yielded: bool,
+    // The bindings are the mirror image of the config's, see `mirror_layout`
+    mirrored: bool,
+    // Macro shown for confirmation, see `Macro::confirm`
+    pending_macro: Option<usize>,
+    held_click: Option<HeldClick>,
+    // Opened over a window that captures the pointer, see `relative_only_windows`
+    relative_only: bool,
+    event_log: Option<EventLog>,
+    // Hit counts from the event log while the heatmap is shown
+    heatmap: Option<Heatmap>,
+    // Shown again when dropped, which closing the overlay does
+    hidden_cursor: Option<HiddenCursor>,
+    identify: Option<Identify>,
+    // What has been typed into the open search prompt
+    search: Option<String>,
+    // Cell highlighted with the arrow keys in Narrow mode, in the selected region
+    nudge: Option<usize>,
+    // Starts over from the whole display when anything else moves the pointer
+    warp: Option<Warp>,
+    // Where the pointer got warped or clicked at, also over earlier runs
+    history: PositionHistory,
+    // Region typed as the first key of a `sequence_labels` label, waiting for the cell key
+    sequence: Option<usize>,
+    // Hint keys typed so far in hint mode, as indices into `hint_keys`
+    hint_typed: Vec<usize>,
+    // Digits typed before a movement key, see `count_prefixes`
+    count: Option<u32>,
+    // Where `drag_start` pressed the left button, in screen coordinates, until `drag_drop`
+    drag_from: Option<Pos2>,
+    // Hidden for a screen capture, only `toggle_pause` and exit keys do anything
+    paused: bool,
+    // Window that had focus when the overlay opened, for `move_window` and `resize_window`
+    target_window: Option<x11::xlib::Window>,
+    // Set while one of those has it
+    window_grab: Option<WindowGrab>,
+    // Cells picked by recursive narrowing so far, each within the last, in screen coordinates
+    picks: Vec<Rect>,
+    // Active XKB layout group as of the last key event
+    layout_group: u8,
+    // Kept open for `layout_group`, which is asked on every frame with key events
+    x_connection: Option<window::XConnection>,
+    scanner: Option<Scanner>,
+    gaze: Option<GazeInput>,
+    commands: Option<CommandQueue>,
+    devices: Option<DeviceInput>,
+    backdrop: Option<Backdrop>,
+    // What's under the labels, for `auto_label_color`
+    label_shades: Option<LabelShades>,
+}
+
+impl MyApp {
+    // Monitors can be plugged or rearranged while the overlay is up, keep the grid in sync
+    fn check_displays(&mut self, ctx: &egui::Context) {
+        if self.state.last_display_check.elapsed() < DISPLAY_CHECK_INTERVAL {
+            return;
+        }
+        self.state.last_display_check = Instant::now();
+
+        let displays = match DisplayInfo::all() {
+            Ok(infos) => to_displays(&infos, &self.state.config),
+            Err(err) => {
+                let msg = self.state.config.locale.tr(Msg::DisplayInfoError, &[&err]);
+                self.state.toasts.push(msg);
+                return;
+            }
+        };
+        if displays.is_empty() || displays == self.state.displays {
+            return;
+        }
+
+        let msg = self.state.config.locale.tr(Msg::DisplayChanged, &[]);
+        self.state.toasts.push(msg);
+        self.state.displays = displays;
+        self.warn_clamped_offsets();
+        if let Some(backdrop) = &mut self.state.backdrop {
+            backdrop.invalidate();
+        }
+        // The screenshots don't match the displays anymore
+        self.state.label_shades = None;
+        if self.state.root_display >= self.state.displays.len() {
+            self.state.root_display = primary_display(&self.state.displays);
+        }
+        self.place_root(ctx);
+        // The display the overlay was on may be gone
+        let display_idx = if self.state.current_display < self.state.displays.len() {
+            self.state.current_display
+        } else {
+            primary_display(&self.state.displays)
+        };
+        self.move_to_display(ctx, display_idx);
+    }
+
+    // Applies a changed config file: bindings, colors, speeds and the grid. What got started along
+    // with the overlay, like the socket or the pointer backend, keeps its settings until a restart.
+    fn reload_config(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+        let generation = watcher.generation();
+        if generation == self.config_generation {
+            return;
+        }
+        self.config_generation = generation;
+        // The includes may have changed along with the file
+        watcher.refresh();
+
+        let profile = self.state.config.profile_name().map(str::to_owned);
+        if self.apply_config_file(profile.as_deref()) {
+            let msg = self.state.config.locale.tr(Msg::ConfigReloaded, &[]);
+            self.state.toasts.push(msg);
+        }
+    }
+
+    // Reads the config file again with `profile` applied and switches to it, unless it has
+    // problems. Returns whether it switched.
+    fn apply_config_file(&mut self, profile: Option<&str>) -> bool {
+        let Some(path) = self.state.config.source.clone() else {
+            return false;
+        };
+        let (config, dispatch) = match reread_config(&path, profile, self.state.config.scroll_only)
+        {
+            Ok(reloaded) => reloaded,
+            Err(problems) => {
+                validate::print(&path.display().to_string(), &problems);
+                let msg = self
+                    .state
+                    .config
+                    .locale
+                    .tr(Msg::ConfigNotReloaded, &[&problems.len()]);
+                self.state.toasts.push(msg);
+                return false;
+            }
+        };
+        self.state.labels = KeyLabels::new(config.layout_labels, self.state.layout_group);
+        self.state.dispatch = dispatch;
+        self.state.config = config;
+        if self.state.mirrored {
+            self.set_mirrored(true);
+        }
+
+        // A new grid or offsets change the displays, and the region or cell picked so far may not
+        // exist anymore
+        if let Ok(infos) = DisplayInfo::all() {
+            let displays = to_displays(&infos, &self.state.config);
+            if !displays.is_empty() && displays != self.state.displays {
+                self.state.displays = displays;
+                self.warn_clamped_offsets();
+                self.state.current_display = self
+                    .state
+                    .current_display
+                    .min(self.state.displays.len() - 1);
+                self.state.root_display =
+                    self.state.root_display.min(self.state.displays.len() - 1);
+                self.start_over();
+            }
+        }
+        true
+    }
+
+    // Closes the overlay after a click action, unless `after` keeps it up for `action`
+    fn after_click(&mut self, ctx: &egui::Context, action: Action) {
+        let after = self.state.config.after.get(&action);
+        match after.copied().unwrap_or(AfterClick::Exit) {
+            AfterClick::Exit => {
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+                return;
+            }
+            AfterClick::Stay => {}
+            AfterClick::Screen => self.start_over(),
+            AfterClick::Narrow if !self.state.relative_only => {
+                self.state.picks.clear();
+                self.state.cell = -1;
+                self.state.mode = Mode::Narrow;
+            }
+            AfterClick::Narrow => {}
+        }
+        ctx.send_viewport_cmd(ViewportCommand::Focus);
+    }
+
+    // Back to picking a region, for when the region or cell picked so far may not exist anymore
+    fn start_over(&mut self) {
+        if !self.state.relative_only {
+            self.state.mode = Mode::Screen;
+        }
+        self.state.region = 0;
+        self.state.cell = -1;
+        self.state.picks.clear();
+        self.state.nudge = None;
+        self.state.sequence = None;
+    }
+
+    // Switches to the mirror image of the configured bindings, or back to them
+    fn set_mirrored(&mut self, mirrored: bool) {
+        let config = &self.state.config;
+        let mut bindings = serde_json::to_value(&config.json_key_bindings).unwrap();
+        if mirrored {
+            presets::mirror(&mut bindings, config.grid.regions[0], config.grid.cells[0]);
+        }
+        let key_bindings = serde_json::from_value::<JsonKeyBindings>(bindings)
+            .unwrap()
+            .transform();
+        match DispatchTable::new(&key_bindings) {
+            Ok(dispatch) if config.scroll_only => self.state.dispatch = dispatch.scroll_only(),
+            Ok(dispatch) => self.state.dispatch = dispatch,
+            // Two names for the same key can end up bound twice
+            Err(conflicts) => {
+                eprintln!("Unable to mirror the bindings:\n{conflicts}");
+                return;
+            }
+        }
+        self.state.config.key_bindings = key_bindings;
+        self.state.mirrored = mirrored;
+    }
+
+    fn warn_clamped_offsets(&mut self) {
+        for (i, d) in self.state.displays.iter().enumerate() {
+            if d.offset_clamped {
+                let offset = format!("{},{}", d.offset.x, d.offset.y);
+                let msg = self
+                    .state
+                    .config
+                    .locale
+                    .tr(Msg::OffsetClamped, &[&i, &offset]);
+                self.state.toasts.push(msg);
+            }
+        }
+    }
+
+    fn move_to_display(&mut self, ctx: &egui::Context, display_idx: usize) {
+        self.state.current_display = display_idx % self.state.displays.len();
+        self.state.sequence = None;
+        // A display with a smaller grid of its own may not have the region or cell picked so far
+        let grid = self.state.displays[self.state.current_display].grid;
+        if self.state.region >= grid.region_count() as i32
+            || self.state.cell >= grid.cell_count() as i32
+        {
+            self.start_over();
+        }
+        ctx.request_repaint();
+    }
+
+    // The root window stays on `root_display`, and only moves when that display does
+    fn place_root(&self, ctx: &egui::Context) {
+        let area = self.state.displays[self.state.root_display].area();
+        ctx.send_viewport_cmd_to(ViewportId::ROOT, ViewportCommand::InnerSize(area.size()));
+        ctx.send_viewport_cmd_to(ViewportId::ROOT, ViewportCommand::OuterPosition(area.min));
+    }
+
+    fn select_region(&mut self, region: usize) {
+        self.state.region = region as i32;
+        self.state.mode = Mode::Narrow;
+        self.state.cell = -1;
+        self.state.nudge = None;
+    }
+
+    // Remembers where the user was, and lets go of what the overlay holds while open
+    fn end_session(&mut self) {
+        self.state.hidden_cursor = None;
+        // Closing mid-drag drops it where the pointer is rather than leave the button down
+        if self.state.drag_from.take().is_some() {
+            self.state
+                .mouse
+                .button(Button::Left, enigo::Direction::Release);
+            state::record_left_button_released();
+        }
+        self.run_hook(HookEvent::Close, None);
+
+        // A relative-only overlay has no grid position worth coming back to, and the picks of
+        // recursive narrowing aren't kept
+        let mode = match self.state.mode {
+            _ if self.state.relative_only => Mode::Screen,
+            Mode::Recurse(_) => Mode::Narrow,
+            mode => mode,
+        };
+        self.last_session.set(Some(Session {
+            display: self.state.current_display,
+            mode,
+            region: self.state.region,
+            cell: self.state.cell,
+            profile: self.state.config.profile,
+        }));
+
+        if let Some(commands) = &self.state.commands {
+            commands.publish_status(ipc::CLOSED_STATUS.to_owned());
+        }
+    }
+
+    // `kmgrid daemon` hides the window instead of closing it
+    fn hide(&mut self, ctx: &egui::Context) {
+        self.end_session();
+        ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+        ctx.send_viewport_cmd(ViewportCommand::Visible(false));
+        if let Some(daemon) = &mut self.daemon {
+            // The reopen key may have been pressed while the overlay was open
+            daemon.activation.take();
+            if let Some(forced) = &daemon.forced {
+                forced.take();
+            }
+            daemon.hidden = true;
+        }
+    }
+
+    // Starts over on the display under the pointer, like a freshly opened overlay, with the
+    // workers and toggles of the last one
+    fn show(&mut self, ctx: &egui::Context) {
+        let displays = match DisplayInfo::all() {
+            Ok(infos) => to_displays(&infos, &self.state.config),
+            Err(err) => {
+                eprintln!("Unable to get display info, keeping the previous displays: {err}");
+                self.state.displays.clone()
+            }
+        };
+        let session = Session {
+            display: display_under_pointer(&displays),
+            mode: Mode::Screen,
+            region: 0,
+            cell: -1,
+            profile: self.state.config.profile,
+        };
+        if displays != self.state.displays {
+            let desktop =
+                |displays: &[Display]| Desktop::spanning(displays.iter().map(Display::bounds));
+            if desktop(&displays) != desktop(&self.state.displays) {
+                self.state.mouse = spawn_mouse(&self.state.config, &displays);
+            }
+            self.state.displays = displays;
+        }
+        start_session(&mut self.state, session);
+        self.warn_clamped_offsets();
+
+        self.place_root(ctx);
+        ctx.send_viewport_cmd(ViewportCommand::Visible(true));
+        ctx.send_viewport_cmd(ViewportCommand::Focus);
+        if let Some(daemon) = &mut self.daemon {
+            daemon.hidden = false;
+        }
+        self.run_hook(HookEvent::Open, None);
+    }
+
+    // Draws the overlay window on display `display_idx`. Only the active display shows the grid,
+    // the windows on the others stay empty and let the mouse through.
+    fn draw_display(&mut self, ctx: &egui::Context, display_idx: usize) {
+        self.update_passthrough(ctx, display_idx);
+        if self.state.touch && display_idx == self.state.current_display {
+            let tap = ctx.input(|input| self.tap_action(input));
+            self.state.taps.extend(tap);
+        }
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                let painter = ui.painter();
+                let display = &self.state.displays[display_idx];
+                let origin = Pos2::ZERO;
+                let area_size = display.area().size();
+                let style = &display.style;
+                let scale = display.scale;
+                let window = Rect::from_min_size(Pos2::ZERO, area_size);
+
+                // Nothing drawn while paused, so captures only show what's under the overlay
+                if self.state.paused {
+                    return;
+                }
+                if let Some(backdrop) = &mut self.state.backdrop {
+                    backdrop.draw(ctx, painter, display_idx, window);
+                }
+                if display_idx != self.state.current_display {
+                    if let Some(identify) = &self.state.identify {
+                        identify.draw(display_idx, painter, window);
+                    }
+                    return;
+                }
+
+                let region_line1_stroke = to_stroke(5.0 * scale, style.region_line1);
+                let region_line2_stroke = to_stroke(3.0 * scale, style.region_line2);
+
+                let geometry = display.window_geometry();
+                let region_size = geometry.region_size();
+                let [region_cols, region_rows] = geometry.regions();
+                let [cell_cols, cell_rows] = geometry.cells();
+
+                let residual = self.state.config.residual_context;
+                if !self.state.relative_only
+                    && !matches!(self.state.mode, Mode::Screen | Mode::Hints)
+                {
+                    self.draw_residual_context(painter, &geometry, residual);
+                }
+
+                if self.state.relative_only {
+                    // No grid, only the movement, click and scroll keys of Cell mode
+                } else if self.state.mode == Mode::Screen {
+                    // Draw screen borders
+                    let screen_border = Rect::from_min_size(origin, area_size).shrink(5.0);
+                    painter.rect_stroke(screen_border, Rounding::ZERO, region_line1_stroke);
+                    painter.rect_stroke(screen_border, Rounding::ZERO, region_line2_stroke);
+
+                    let region_grid_line1_stroke = to_stroke(1.5 * scale, style.region_grid_line1);
+                    let region_grid_line2_stroke = to_stroke(1.5 * scale, style.region_grid_line2);
+
+                    // Draw horizontal lines
+                    let horizontal_line_count = region_rows * cell_rows;
+                    for i in 1..horizontal_line_count {
+                        let percentage = i as f32 / horizontal_line_count as f32;
+                        let left = origin + vec2(0.0, area_size.y * percentage);
+                        let right = origin + vec2(area_size.x, area_size.y * percentage);
+
+                        painter.line_segment([left, right], region_grid_line1_stroke);
+                        painter.line_segment([left, right], region_grid_line2_stroke);
+                    }
+
+                    // Draw vertical lines
+                    let vertical_line_count = region_cols * cell_cols;
+                    for i in 1..vertical_line_count {
+                        let percentage = i as f32 / vertical_line_count as f32;
+                        let top = origin + vec2(area_size.x * percentage, 0.0);
+                        let btm = origin + vec2(area_size.x * percentage, area_size.y);
+
+                        painter.line_segment([top, btm], region_grid_line1_stroke);
+                        painter.line_segment([top, btm], region_grid_line2_stroke);
+                    }
+
+                    // Draw region stripes
+                    for i in 0..region_rows {
+                        let rect = egui::Rect::from_min_size(
+                            origin + vec2(0.0, i as f32 * region_size.y),
+                            vec2(area_size.x, region_size.y),
+                        );
+                        let color = if i % 2 == 0 {
+                            style.left_grid
+                        } else {
+                            style.right_grid
+                        };
+
+                        painter.rect(rect, Rounding::ZERO, to_col(color), Stroke::NONE);
+                    }
+
+                    let region_line1_stroke = to_stroke(2.0 * scale, style.region_line1);
+                    let region_line2_stroke = to_stroke(1.0 * scale, style.region_line2);
+                    for (i, key) in self.state.config.key_bindings.region.iter().enumerate() {
+                        let region_rect = geometry.region_rect(i);
+                        let text_pos = region_rect.center();
+                        let text = self.state.labels.label(*key);
+                        if self.state.config.sequence_labels {
+                            painter.rect_stroke(region_rect, Rounding::ZERO, region_line1_stroke);
+                            painter.rect_stroke(region_rect, Rounding::ZERO, region_line2_stroke);
+                            continue;
+                        }
+                        let font_size = label_font_size(painter, text, region_rect.size(), style);
+                        let black_font = egui::FontId::proportional(font_size);
+                        let white_font = egui::FontId::proportional(font_size);
+                        let (text_color, outline_color) = self.label_colors(region_rect);
+
+                        // Draw region text
+                        for i in 0..9 {
+                            painter.text(
+                                text_pos
+                                    + vec2(((i % 3) - 1) as f32, ((i / 3) - 1) as f32)
+                                        * 3.0
+                                        * scale,
+                                Align2::CENTER_CENTER,
+                                text,
+                                black_font.clone(),
+                                outline_color,
+                            );
+                        }
+                        painter.text(
+                            text_pos,
+                            Align2::CENTER_CENTER,
+                            text,
+                            white_font,
+                            text_color,
+                        );
+
+                        // Draw region outline
+                        painter.rect_stroke(region_rect, Rounding::ZERO, region_line1_stroke);
+                        painter.rect_stroke(region_rect, Rounding::ZERO, region_line2_stroke);
+                    }
+
+                    // Draw a label of region key and cell key on every cell, only those of the
+                    // region typed so far once there is one
+                    if self.state.config.sequence_labels {
+                        self.draw_sequence_labels(painter, &geometry, style);
+                    }
+
+                    // Draw the region the tracker points at
+                    if let Some((display_idx, region)) = self.gaze_target() {
+                        if display_idx == self.state.current_display {
+                            let rect = geometry.region_rect(region).shrink(3.0);
+                            let stroke = to_stroke(4.0 * scale, style.gaze_highlight);
+                            painter.rect_stroke(rect, Rounding::ZERO, stroke);
+                        }
+                    }
+                } else if self.state.mode == Mode::Narrow {
+                    let right_rect = geometry.region_rect(self.state.region as usize);
+                    let origin = right_rect.min;
+                    let cell_size = geometry.cell_size();
+
+                    // Draw region background
+                    let right_color = to_col(style.right_grid);
+                    painter.rect(right_rect, Rounding::ZERO, right_color, Stroke::NONE);
+
+                    if residual == ResidualContext::Bare {
+                        painter.rect_stroke(right_rect, Rounding::ZERO, region_line1_stroke);
+                        painter.rect_stroke(right_rect, Rounding::ZERO, region_line2_stroke);
+                    }
+
+                    // Draw cell vertical lines
+                    for i in (0..=cell_cols).filter(|_| residual != ResidualContext::Bare) {
+                        let i = i as f32;
+                        let start = origin + vec2(i * cell_size.x, 0.0);
+                        let end = origin + vec2(i * cell_size.x, region_size.y);
+                        painter.line_segment([start, end], region_line1_stroke);
+                        painter.line_segment([start, end], region_line2_stroke);
+                    }
+
+                    // Draw cell horizontal lines
+                    for i in (0..=cell_rows).filter(|_| residual != ResidualContext::Bare) {
+                        let i = i as f32;
+                        let start = origin + vec2(0.0, i * cell_size.y);
+                        let end = origin + vec2(region_size.x, i * cell_size.y);
+                        painter.line_segment([start, end], region_line1_stroke);
+                        painter.line_segment([start, end], region_line2_stroke);
+                    }
+
+                    // Draw cell text, on the cells `narrow_labels` asks for
+                    let cells = if residual == ResidualContext::Bare {
+                        Vec::new()
+                    } else {
+                        self.state.config.narrow_labels.cells(cell_cols, cell_rows)
+                    };
+                    for cell in cells {
+                        let cell_rect = geometry.cell_rect(self.state.region as usize, cell);
+                        let pos = cell_rect.center();
+                        let key = self.state.config.key_bindings.grid[cell];
+                        let text = self.state.labels.label(key);
+                        let font_size = label_font_size(painter, text, cell_size, style);
+                        let black_font = egui::FontId::proportional(font_size * 1.35);
+                        let white_font = egui::FontId::proportional(font_size);
+                        let (text_color, outline_color) = self.label_colors(cell_rect);
+
+                        for j in 0..9 {
+                            painter.text(
+                                pos + vec2(((j % 3) - 1) as f32, ((j / 3) - 1) as f32)
+                                    * 1.5
+                                    * scale,
+                                Align2::CENTER_CENTER,
+                                text,
+                                black_font.clone(),
+                                outline_color,
+                            );
+                        }
+
+                        painter.text(pos, Align2::CENTER_CENTER, text, white_font, text_color);
+                    }
+
+                    // Draw the cell the arrow keys picked
+                    if let Some(cell) = self.state.nudge {
+                        let rect = geometry.cell_rect(self.state.region as usize, cell);
+                        let stroke = to_stroke(4.0 * scale, style.scan_highlight);
+                        painter.rect_stroke(rect.shrink(2.0), Rounding::ZERO, stroke);
+                    }
+                } else if let Mode::Recurse(_) = self.state.mode {
+                    let within = self.state.picks.last().copied().unwrap_or(Rect::NOTHING);
+                    let within = within.translate(-display.area().min.to_vec2());
+                    let grid = GridSize {
+                        regions: [1, 1],
+                        cells: display.grid.cells,
+                    };
+                    let sub_geometry = GridGeometry::with_size(within.min, within.size(), grid);
+
+                    // Draw the previous pick, split into the next grid
+                    painter.rect(
+                        within,
+                        Rounding::ZERO,
+                        to_col(style.right_grid),
+                        Stroke::NONE,
+                    );
+                    let line_stroke = to_stroke(1.0 * scale, style.region_line2);
+                    let cells = sub_geometry
+                        .iter_cells()
+                        .filter(|_| residual != ResidualContext::Bare);
+                    for (_, cell, rect) in cells {
+                        painter.rect_stroke(rect, Rounding::ZERO, line_stroke);
+
+                        let key = self.state.config.key_bindings.grid[cell];
+                        let text = self.state.labels.label(key);
+                        let font_size = label_font_size(painter, text, rect.size(), style);
+                        let font = egui::FontId::proportional(font_size);
+                        let (text_color, outline_color) = self.label_colors(rect);
+                        painter.text(
+                            rect.center() + vec2(1.0, 1.0) * scale,
+                            Align2::CENTER_CENTER,
+                            text,
+                            font.clone(),
+                            outline_color,
+                        );
+                        painter.text(rect.center(), Align2::CENTER_CENTER, text, font, text_color);
+                    }
+                    painter.rect_stroke(within, Rounding::ZERO, region_line1_stroke);
+                } else if self.state.mode == Mode::Hints {
+                    self.draw_hints(painter, &geometry, style);
+                } else if self.state.mode == Mode::Cell {
+                    let rect = self.active_cell().translate(-display.area().min.to_vec2());
+
+                    // Draw cell borders
+                    let cell_border = rect.shrink(5.0);
+                    painter.rect_stroke(cell_border, Rounding::ZERO, region_line1_stroke);
+                    painter.rect_stroke(cell_border, Rounding::ZERO, region_line2_stroke);
+
+                    // Draw cell background
+                    painter.rect(rect, Rounding::ZERO, to_col(style.right_grid), Stroke::NONE);
+
+                    self.draw_micro_grid(painter, rect, style);
+                }
+
+                // Draw how often each region or cell got picked
+                if let Some(heatmap) = &self.state.heatmap {
+                    let display_idx = self.state.current_display;
+                    let region = self.state.region as usize;
+                    let tint = |heat: f32| {
+                        let Color(r, g, b, a) = style.heatmap;
+                        Color32::from_rgba_unmultiplied(r, g, b, (a as f32 * heat) as u8)
+                    };
+                    match self.state.mode {
+                        Mode::Screen if !self.state.relative_only => {
+                            for i in 0..region_cols * region_rows {
+                                let heat = heatmap.region_heat(display_idx, i);
+                                painter.rect_filled(
+                                    geometry.region_rect(i),
+                                    Rounding::ZERO,
+                                    tint(heat),
+                                );
+                            }
+                        }
+                        Mode::Narrow => {
+                            for i in 0..cell_cols * cell_rows {
+                                let heat = heatmap.cell_heat(display_idx, region, i);
+                                painter.rect_filled(
+                                    geometry.cell_rect(region, i),
+                                    Rounding::ZERO,
+                                    tint(heat),
+                                );
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                // Draw switch scanning highlight
+                let scan_stroke = to_stroke(6.0 * scale, style.scan_highlight);
+                match self.scan_target() {
+                    Some(Action::SelectRegion(region)) => {
+                        let rect = geometry.region_rect(region).shrink(3.0);
+                        painter.rect_stroke(rect, Rounding::ZERO, scan_stroke);
+                    }
+                    Some(Action::SelectCell(cell)) => {
+                        let rect = geometry.cell_rect(self.state.region as usize, cell);
+                        painter.rect_stroke(rect.shrink(3.0), Rounding::ZERO, scan_stroke);
+                    }
+                    Some(action) => {
+                        let (_, msg) = scan::CELL_ACTIONS
+                            .iter()
+                            .find(|(a, _)| *a == action)
+                            .unwrap();
+                        let rect = geometry
+                            .cell_rect(self.state.region as usize, self.state.cell as usize);
+                        painter.text(
+                            rect.center_bottom() + vec2(0.0, 8.0),
+                            Align2::CENTER_TOP,
+                            self.state.config.locale.tr(*msg, &[]),
+                            egui::FontId::new(24.0, egui::FontFamily::Proportional),
+                            to_col(style.scan_highlight),
+                        );
+                    }
+                    None => {}
+                }
+
+                let color = Color32::from_rgba_premultiplied(28, 92, 48, 120);
+                let rect = egui::Rect::from_two_pos(pos2(0.0, 0.0), pos2(50.0, 50.0));
+                painter.rect(rect, Rounding::ZERO, color, Stroke::new(0.0, color));
+
+                // Draw where a macro waiting for confirmation is going to click
+                if let Some(i) = self.state.pending_macro {
+                    let font = egui::FontId::proportional(18.0);
+                    let stroke = to_stroke(3.0 * scale, style.scan_highlight);
+                    for (n, pos) in self.planned_clicks(i).into_iter().enumerate() {
+                        let pos = pos - display.area().min.to_vec2();
+                        painter.circle_stroke(pos, 12.0 * scale, stroke);
+                        painter.text(
+                            pos + vec2(14.0, -14.0) * scale,
+                            Align2::LEFT_BOTTOM,
+                            (n + 1).to_string(),
+                            font.clone(),
+                            to_col(style.scan_highlight),
+                        );
+                    }
+                    painter.text(
+                        window.center_top() + vec2(0.0, 40.0),
+                        Align2::CENTER_TOP,
+                        self.state.config.locale.tr(Msg::ConfirmMacro, &[]),
+                        egui::FontId::proportional(24.0),
+                        to_col(style.scan_highlight),
+                    );
+                }
+
+                // Draw the search prompt, and where its matches are with the best one thickest
+                if let Some(query) = &self.state.search {
+                    let color = to_col(style.scan_highlight);
+                    let font = egui::FontId::proportional(18.0);
+                    let matches = search::matches(&self.state.config.targets, query);
+                    for (n, target) in matches.iter().enumerate() {
+                        let pos = pos2(target.x, target.y) - display.area().min.to_vec2();
+                        let width = if n == 0 { 4.0 } else { 2.0 };
+                        let stroke = to_stroke(width * scale, style.scan_highlight);
+                        painter.circle_stroke(pos, 10.0 * scale, stroke);
+                        painter.text(
+                            pos + vec2(14.0, 0.0) * scale,
+                            Align2::LEFT_CENTER,
+                            &target.name,
+                            font.clone(),
+                            color,
+                        );
+                    }
+
+                    let prompt = self.state.config.locale.tr(Msg::SearchPrompt, &[query]);
+                    draw_banner(painter, window, prompt, color);
+                }
+
+                // Draw the latched speed keys next to the pointer, the locked ones in brackets
+                if !self.state.latched_speeds.is_empty() {
+                    let pos = self.state.pointer.position() - display.area().min.to_vec2();
+                    let text = self
+                        .state
+                        .latched_speeds
+                        .iter()
+                        .map(|&(action, locked)| {
+                            let factor = match action {
+                                Action::SpeedQuarter => "¼×",
+                                Action::SpeedHalf => "½×",
+                                Action::SpeedTwice => "2×",
+                                _ => "4×",
+                            };
+                            if locked {
+                                format!("[{factor}]")
+                            } else {
+                                factor.to_string()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    painter.text(
+                        pos + vec2(16.0, -16.0) * scale,
+                        Align2::LEFT_BOTTOM,
+                        text,
+                        egui::FontId::proportional(20.0),
+                        to_col(style.scan_highlight),
+                    );
+                }
+
+                // Draw the count typed so far, next to the pointer it's going to move
+                if let Some(count) = self.state.count {
+                    let pos = self.state.pointer.position() - display.area().min.to_vec2();
+                    painter.text(
+                        pos + vec2(16.0, 16.0) * scale,
+                        Align2::LEFT_TOP,
+                        format!("{count}×"),
+                        egui::FontId::proportional(24.0),
+                        to_col(style.scan_highlight),
+                    );
+                }
+
+                // Draw the position history while going through it, the current position circled
+                // and numbered
+                if let Some((n, count)) = self.state.history.place() {
+                    let offset = display.area().min.to_vec2();
+                    let color = to_col(style.scan_highlight);
+                    for (i, pos) in self.state.history.positions().enumerate() {
+                        let (radius, width) = if i + 1 == n { (12.0, 3.0) } else { (6.0, 1.0) };
+                        let stroke = to_stroke(width * scale, style.scan_highlight);
+                        painter.circle_stroke(pos - offset, radius * scale, stroke);
+                        if i + 1 == n {
+                            painter.text(
+                                pos - offset + vec2(16.0, -16.0) * scale,
+                                Align2::LEFT_BOTTOM,
+                                format!("{n}/{count}"),
+                                egui::FontId::proportional(24.0),
+                                color,
+                            );
+                        }
+                    }
+                }
+
+                // Draw what's left of the display for the warp keys
+                if let Some(warp) = self.state.warp {
+                    let area = warp.area.translate(-display.area().min.to_vec2());
+                    let stroke = to_stroke(2.0 * scale, style.scan_highlight);
+                    painter.rect_stroke(area, Rounding::ZERO, stroke);
+                }
+
+                // Draw how far along a held click key is, as a ring filling up around the pointer
+                let held_click = self.state.held_click.filter(|held| !held.clicked);
+                if let Some(held) = held_click {
+                    let hold = self.state.config.click_hold.as_secs_f32();
+                    let t = (held.since.elapsed().as_secs_f32() / hold).min(1.0);
+                    let pos = self.state.pointer.position() - display.area().min.to_vec2();
+                    let radius = 16.0 * scale;
+                    painter.circle_stroke(
+                        pos,
+                        radius,
+                        to_stroke(1.0 * scale, style.scan_highlight),
+                    );
+                    let points = (0..=32)
+                        .map(|i| {
+                            let angle = -FRAC_PI_2 + TAU * t * i as f32 / 32.0;
+                            pos + radius * vec2(angle.cos(), angle.sin())
+                        })
+                        .collect();
+                    let stroke = to_stroke(4.0 * scale, style.scan_highlight);
+                    painter.add(egui::Shape::line(points, stroke));
+                }
+
+                // Draw the drag underway, from where it started to the pointer
+                if let Some(from) = self.state.drag_from {
+                    let color = to_col(style.scan_highlight);
+                    let offset = display.area().min.to_vec2();
+                    let to = self.state.pointer.position() - offset;
+                    let stroke = to_stroke(3.0 * scale, style.scan_highlight);
+                    painter.circle_stroke(from - offset, 10.0 * scale, stroke);
+                    painter.line_segment([from - offset, to], stroke);
+
+                    let label = self
+                        .state
+                        .config
+                        .key_bindings
+                        .mouse
+                        .drag_drop
+                        .first()
+                        .map_or(String::new(), |chord| {
+                            format!(
+                                "{}{}",
+                                chord.modifier_prefix(),
+                                self.state.labels.label(chord.key)
+                            )
+                        });
+                    let text = self.state.config.locale.tr(Msg::Dragging, &[&label]);
+                    draw_banner(painter, window, text, color);
+                }
+
+                // No pointer input, which makes the overlay a way to pick positions at most
+                if self.state.mouse.failure().is_some() {
+                    let text = self.state.config.locale.tr(Msg::NoPointerInput, &[]);
+                    draw_banner(painter, window, text, to_col(style.scan_highlight));
+                } else if self.state.config.tutorial {
+                    // The first-run tutorial, a step for every mode
+                    let locale = self.state.config.locale;
+                    let text = match self.state.mode {
+                        Mode::Screen => locale.tr(Msg::TutorialRegion, &[]),
+                        Mode::Narrow | Mode::Recurse(_) => locale.tr(Msg::TutorialCell, &[]),
+                        Mode::Hints => locale.tr(Msg::TutorialHints, &[]),
+                        Mode::Cell => {
+                            let chord = self.state.config.key_bindings.mouse.left_click_and_exit[0];
+                            let label = self.state.labels.label(chord.key);
+                            let label = format!("{}{label}", chord.modifier_prefix());
+                            locale.tr(Msg::TutorialClick, &[&label])
+                        }
+                    };
+                    draw_banner(painter, window, text, to_col(style.scan_highlight));
+                }
+
+                // Draw the overlay bounds, so it's clear how much of the display the grid covers
+                if let Some(border) = style.window_border {
+                    let width = style.window_border_width * scale;
+                    for i in 1..=4 {
+                        let alpha = border.3 as f32 / (i as f32 + 1.0);
+                        let glow = Color(border.0, border.1, border.2, alpha as u8);
+                        let rect = window.shrink(width * (i as f32 + 0.5));
+                        painter.rect_stroke(rect, Rounding::ZERO, to_stroke(width, glow));
+                    }
+                    let rect = window.shrink(width / 2.0);
+                    painter.rect_stroke(rect, Rounding::ZERO, to_stroke(width, border));
+                }
+
+                // Where the window being moved or resized is going
+                if let Some(grab) = &self.state.window_grab {
+                    let rect = grab.rect.translate(-display.area().min.to_vec2());
+                    painter.rect_stroke(rect, Rounding::ZERO, region_line1_stroke);
+                    painter.rect_stroke(rect, Rounding::ZERO, region_line2_stroke);
+                }
+
+                if let Some(identify) = &self.state.identify {
+                    identify.draw(display_idx, painter, window);
+                }
+                self.state.toasts.draw(painter, window);
+            });
+    }
+
+    // The labels of hint mode that start with the keys typed so far, without those keys
+    fn draw_hints(&self, painter: &egui::Painter, geometry: &GridGeometry, style: &StyleConfig) {
+        let scale = self.state.displays[self.state.current_display].scale;
+        let keys = &self.state.config.key_bindings.hint_keys;
+        let typed = &self.state.hint_typed;
+        let cells: Vec<_> = geometry.iter_cells().collect();
+        let labels = hints::labels(cells.len(), keys.len());
+        let line_stroke = to_stroke(1.0 * scale, style.region_grid_line1);
+        for i in hints::matching(&labels, typed) {
+            let (_, _, rect) = cells[i];
+            painter.rect_stroke(rect, Rounding::ZERO, line_stroke);
+
+            let text: String = labels[i][typed.len()..]
+                .iter()
+                .map(|&key| self.state.labels.label(keys[key]))
+                .collect();
+            let font_size = label_font_size(painter, &text, rect.size(), style);
+            let font = egui::FontId::proportional(font_size);
+            let (text_color, outline_color) = self.label_colors(rect);
+            painter.text(
+                rect.center() + vec2(1.0, 1.0) * scale,
+                Align2::CENTER_CENTER,
+                &text,
+                font.clone(),
+                outline_color,
+            );
+            painter.text(rect.center(), Align2::CENTER_CENTER, text, font, text_color);
+        }
+    }
+
+    fn draw_sequence_labels(
+        &self,
+        painter: &egui::Painter,
+        geometry: &GridGeometry,
+        style: &StyleConfig,
+    ) {
+        let scale = self.state.displays[self.state.current_display].scale;
+        let bindings = &self.state.config.key_bindings;
+        if let Some(region) = self.state.sequence {
+            let rect = geometry.region_rect(region).shrink(3.0);
+            painter.rect_stroke(
+                rect,
+                Rounding::ZERO,
+                to_stroke(4.0 * scale, style.scan_highlight),
+            );
+        }
+        for (region, cell, rect) in geometry.iter_cells() {
+            if self.state.sequence.is_some_and(|typed| typed != region) {
+                continue;
+            }
+            let text = format!(
+                "{}{}",
+                self.state.labels.label(bindings.region[region]),
+                self.state.labels.label(bindings.grid[cell])
+            );
+            let font_size = label_font_size(painter, &text, rect.size(), style);
+            let font = egui::FontId::proportional(font_size);
+            let (text_color, outline_color) = self.label_colors(rect);
+            painter.text(
+                rect.center() + vec2(1.0, 1.0) * scale,
+                Align2::CENTER_CENTER,
+                &text,
+                font.clone(),
+                outline_color,
+            );
+            painter.text(rect.center(), Align2::CENTER_CENTER, text, font, text_color);
+        }
+    }
+
+    // The region grid around the picked rectangle, faded, as much of it as `residual_context` keeps
+    fn draw_residual_context(
+        &self,
+        painter: &egui::Painter,
+        geometry: &GridGeometry,
+        residual: ResidualContext,
+    ) {
+        let scale = self.state.displays[self.state.current_display].scale;
+        if matches!(residual, ResidualContext::Active | ResidualContext::Bare) {
+            return;
+        }
+        let style = &self.state.displays[self.state.current_display].style;
+        let stroke = Stroke::new(scale, to_col(style.region_grid_line1).gamma_multiply(FAINT));
+        let [region_cols, region_rows] = geometry.regions();
+        for region in 0..region_cols * region_rows {
+            let rect = geometry.region_rect(region);
+            painter.rect_stroke(rect, Rounding::ZERO, stroke);
+
+            if residual == ResidualContext::Labels {
+                let key = self.state.config.key_bindings.region[region];
+                let text = self.state.labels.label(key);
+                let font_size = label_font_size(painter, text, rect.size(), style);
+                let (text_color, _) = self.label_colors(rect);
+                painter.text(
+                    rect.center(),
+                    Align2::CENTER_CENTER,
+                    text,
+                    egui::FontId::proportional(font_size),
+                    text_color.gamma_multiply(FAINT),
+                );
+            }
+        }
+    }
+
+    // Text and outline colors of a label over `rect` of the active display's window
+    fn label_colors(&self, rect: Rect) -> (Color32, Color32) {
+        let display = self.state.current_display;
+        match &self.state.label_shades {
+            Some(shades) if self.state.displays[display].style.auto_label_color => {
+                shades.colors(display, rect)
+            }
+            _ => (Color32::WHITE, Color32::BLACK),
+        }
+    }
+
+    // Settings that need the native window, applied once it exists
+    fn set_up_window(&mut self, frame: &eframe::Frame) {
+        let Ok(handle) = frame.window_handle().map(|h| h.as_raw()) else {
+            return;
+        };
+        if !set_up_native_window(&self.state.config, handle) {
+            let msg = self.state.config.locale.tr(Msg::CaptureNotExcluded, &[]);
+            self.state.toasts.push(msg);
+        }
+    }
+
+    // The same for the windows of the other displays, found among the windows of this process.
+    // Whether leaving them out of captures works was already told with the root window.
+    fn set_up_viewport_windows(&self) {
+        for handle in window::own_windows() {
+            set_up_native_window(&self.state.config, handle);
+        }
+    }
+
+    // The cell Cell mode is in, in screen coordinates: the last pick of recursive narrowing, or
+    // the selected cell
+    fn active_cell(&self) -> Rect {
+        match self.state.picks.last() {
+            Some(pick) => *pick,
+            None => self.state.displays[self.state.current_display]
+                .geometry()
+                .cell_rect(self.state.region as usize, self.state.cell as usize),
+        }
+    }
+
+    // Faint lines and labels of the points `micro_grid` keys jump to within `cell`
+    fn draw_micro_grid(&self, painter: &egui::Painter, cell: Rect, style: &StyleConfig) {
+        let keys = &self.state.config.key_bindings.micro_grid;
+        if keys.is_empty() {
+            return;
+        }
+        let scale = self.state.displays[self.state.current_display].scale;
+        let stroke = Stroke::new(scale, to_col(style.region_grid_line1).gamma_multiply(FAINT));
+        let geometry = GridGeometry::with_size(cell.min, cell.size(), MICRO_GRID);
+        for (_, point, rect) in geometry.iter_cells() {
+            painter.rect_stroke(rect, Rounding::ZERO, stroke);
+
+            let text = self.state.labels.label(keys[point]);
+            let font_size = label_font_size(painter, text, rect.size() / 2.0, style);
+            let (text_color, _) = self.label_colors(rect);
+            painter.text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                text,
+                egui::FontId::proportional(font_size),
+                text_color.gamma_multiply(FAINT),
+            );
+        }
+    }
+
+    // Starts recursive narrowing within the selected cell
+    fn pick_within_cell(&mut self) {
+        let geometry = self.state.displays[self.state.current_display].geometry();
+        let rect = geometry.cell_rect(self.state.region as usize, self.state.cell as usize);
+        self.state.picks.push(rect);
+        self.state.mode = Mode::Recurse(1);
+    }
+
+    // Picks a cell of the grid over the previous pick, ending up in Cell mode once the picks get
+    // too small to split any further
+    fn pick_within(&mut self, cell: usize, depth: usize) {
+        let Some(&within) = self.state.picks.last() else {
+            return;
+        };
+        let grid = GridSize {
+            regions: [1, 1],
+            cells: self.state.displays[self.state.current_display].grid.cells,
+        };
+        let rect = GridGeometry::with_size(within.min, within.size(), grid).cell_rect(0, cell);
+        let pos = rect.center();
+        self.state
+            .mouse
+            .move_mouse(pos.x as i32, pos.y as i32, enigo::Coordinate::Abs);
+        self.state.picks.push(rect);
+
+        let next = GridGeometry::with_size(rect.min, rect.size(), grid).cell_rect(0, 0);
+        self.state.mode = if next.width().min(next.height()) < MIN_RECURSE_SIZE {
+            Mode::Cell
+        } else {
+            Mode::Recurse(depth + 1)
+        };
+        self.state.mouse_key_down.clear();
+    }
+
+    // Jumps the pointer halfway toward an edge of what's left of the display, and leaves only the
+    // half it jumped into for the next warp, narrowing down on a spot like a binary search
+    fn warp(&mut self, action: Action) {
+        let Warp { mut area, mut pos } = self.state.warp.unwrap_or_else(|| {
+            let area = self.state.displays[self.state.current_display].area();
+            let pos = self.state.pointer.position().clamp(area.min, area.max);
+            Warp { area, pos }
+        });
+        match action {
+            Action::WarpUp => area.max.y = pos.y,
+            Action::WarpDown => area.min.y = pos.y,
+            Action::WarpLeft => area.max.x = pos.x,
+            _ => area.min.x = pos.x,
+        }
+        if matches!(action, Action::WarpUp | Action::WarpDown) {
+            pos.y = area.center().y;
+        } else {
+            pos.x = area.center().x;
+        }
+        self.state.mouse.move_mouse(
+            pos.x.round() as i32,
+            pos.y.round() as i32,
+            enigo::Coordinate::Abs,
+        );
+        self.state.warp = Some(Warp { area, pos });
+    }
+
+    // Moves the Narrow mode highlight by a cell, into the next region at the edges. The first
+    // nudge only puts the highlight on the middle cell.
+    fn nudge(&mut self, dx: i32, dy: i32) {
+        let geometry = self.state.displays[self.state.current_display].geometry();
+        let [region_cols, region_rows] = geometry.regions();
+        let [cell_cols, cell_rows] = geometry.cells();
+        let region = self.state.region as usize;
+        let Some(cell) = self.state.nudge else {
+            self.state.nudge = Some(cell_rows / 2 * cell_cols + cell_cols / 2);
+            return;
+        };
+
+        // Column and row across the whole display
+        let col = (region % region_cols) * cell_cols + cell % cell_cols;
+        let row = (region / region_cols) * cell_rows + cell / cell_cols;
+        let col = (col as i32 + dx).clamp(0, (region_cols * cell_cols) as i32 - 1) as usize;
+        let row = (row as i32 + dy).clamp(0, (region_rows * cell_rows) as i32 - 1) as usize;
+
+        self.state.region = ((row / cell_rows) * region_cols + col / cell_cols) as i32;
+        self.state.nudge = Some((row % cell_rows) * cell_cols + col % cell_cols);
+    }
+
+    fn select_cell(&mut self, cell: usize) {
+        self.state.cell = cell as i32;
+        self.state.nudge = None;
+        self.state.picks.clear();
+
+        let display = self.state.displays[self.state.current_display];
+        let pos = display
+            .geometry()
+            .cell_center(self.state.region as usize, cell);
+
+        self.state
+            .mouse
+            .move_mouse(pos.x as i32, pos.y as i32, enigo::Coordinate::Abs);
+        self.state.mode = Mode::Cell;
+
+        self.state.mouse_key_down.clear();
+    }
+
+    // Modifiers held with `key`, including the ones it went down with. With sticky keys, a latched
+    // modifier only comes with the key event after it and may be let go of by the end of the frame,
+    // or while the key is still held.
+    fn key_modifiers(&self, input: &egui::InputState, key: Key) -> Modifiers {
+        let pressed_with = self.state.key_modifiers.get(&key).copied();
+        input.modifiers | pressed_with.unwrap_or_default()
+    }
+
+    // A tap of a speed key with `sticky_speed_keys` latches it for the next move, a second locks it
+    // for every move after that, a third lets go of it, like sticky keys do with modifiers
+    fn latch_speed(&mut self, action: Action) {
+        let latched = &mut self.state.latched_speeds;
+        match latched.iter().position(|&(a, _)| a == action) {
+            None => latched.push((action, false)),
+            Some(i) if !latched[i].1 => latched[i].1 = true,
+            Some(i) => {
+                latched.remove(i);
+            }
+        }
+    }
+
+    // Held keys only count once they have been released since entering Cell mode, so the key
+    // that selected the cell doesn't start moving the pointer right away.
+    fn is_held_with_check(&mut self, key: Key, held: bool) -> bool {
+        if self.state.mouse_key_down.contains(&key) {
+            return held;
+        } else if !held {
+            self.state.mouse_key_down.insert(key);
+        }
+        false
+    }
+
+    // Bindings are written for the first layout. Under any other, e.g. after switching to a
+    // Russian layout mid-session, keys are matched on where they are instead of what they type,
+    // which also keeps keys like `/` (`.` on a Russian layout) on their own binding. With
+    // `physical_keys` that goes for the first layout too.
+    fn remap_to_physical_keys(&mut self, raw_input: &mut egui::RawInput) {
+        let has_keys = raw_input
+            .events
+            .iter()
+            .any(|e| matches!(e, egui::Event::Key { .. }));
+        if !has_keys {
+            return;
+        }
+
+        let group = self
+            .state
+            .x_connection
+            .as_ref()
+            .and_then(labels::layout_group)
+            .unwrap_or(0);
+        if group != self.state.layout_group {
+            self.state.layout_group = group;
+            if self.state.config.layout_labels {
+                self.state.labels.refresh(group);
+            }
+        }
+        if group == 0 && !self.state.config.physical_keys {
+            return;
+        }
+
+        for e in &mut raw_input.events {
+            if let egui::Event::Key {
+                key,
+                physical_key: Some(physical),
+                ..
+            } = e
+            {
+                *key = *physical;
+            }
+        }
+    }
+
+    // Opposite directions cancel out and diagonals cover the same distance as straight moves.
+    // Fractional pixels carry over to the next frame, so the speed is the same in every direction.
+    fn move_pointer(&mut self, direction: Vec2, dist: f32) {
+        if direction == Vec2::ZERO {
+            self.state.move_remainder = Vec2::ZERO;
+            return;
+        }
+        self.state.warp = None;
+
+        let delta = direction.normalized() * dist + self.state.move_remainder;
+        let step = delta.round();
+        self.state.move_remainder = delta - step;
+        if step != Vec2::ZERO {
+            self.state
+                .mouse
+                .move_mouse(step.x as i32, step.y as i32, enigo::Coordinate::Rel);
+        }
+    }
+
+    fn perform(&mut self, ctx: &egui::Context, action: Action, dist: f32) {
+        // Absolute positions mean nothing to an application that captured the pointer
+        if self.state.relative_only && action.uses_grid() {
+            return;
+        }
+        if action.uses_grid() && !action.warps() {
+            self.state.warp = None;
+        }
+        // Displays with a smaller grid of their own leave the last region and cell keys unused
+        let grid = self.state.displays[self.state.current_display].grid;
+        match action {
+            Action::SelectRegion(i) | Action::ClickRegion(i) if i >= grid.region_count() => return,
+            Action::SelectCell(i) if i >= grid.cell_count() => return,
+            Action::MicroPoint(i) if i >= MICRO_GRID.cell_count() => return,
+            _ => {}
+        }
+
+        let scroll_speed = (self.scroll_steps() * self.cell_speedup()).round() as i32;
+        let page = self.state.config.page_scroll_amount;
+        let mouse = &self.state.mouse;
+        let activate = self.state.config.activate_before_click;
+        // Clicks and presses, after activating the window they land on when the config asks
+        let click = |button, direction| {
+            if activate {
+                mouse.activate_window();
+            }
+            mouse.button(button, direction);
+        };
+
+        match action {
+            Action::Exit => ctx.send_viewport_cmd(ViewportCommand::Close),
+            Action::ShowScreen => {
+                self.state.picks.clear();
+                self.state.mode = Mode::Screen;
+            }
+            Action::ShowNarrow => {
+                self.state.picks.clear();
+                self.state.mode = Mode::Narrow;
+            }
+            Action::ShowCell => {
+                if let Some(cell) = self.state.nudge.filter(|_| self.state.mode == Mode::Narrow) {
+                    self.select_cell(cell);
+                } else if self.state.cell >= 0 {
+                    self.state.mode = Mode::Cell;
+                }
+            }
+            Action::ShowHints => {
+                self.start_over();
+                self.state.mode = Mode::Hints;
+                self.state.hint_typed.clear();
+            }
+            Action::NudgeUp => self.nudge(0, -1),
+            Action::NudgeDown => self.nudge(0, 1),
+            Action::NudgeLeft => self.nudge(-1, 0),
+            Action::NudgeRight => self.nudge(1, 0),
+
+            Action::SelectRegion(i)
+                if self.state.config.sequence_labels && self.state.mode == Mode::Screen =>
+            {
+                self.state.region = i as i32;
+                self.state.sequence = Some(i);
+            }
+            Action::SelectRegion(i) => {
+                self.select_region(i);
+                ctx.request_repaint();
+            }
+            Action::ClickRegion(i) => {
+                println!("Click region and bye!");
+
+                let center = self.state.displays[self.state.current_display]
+                    .geometry()
+                    .region_rect(i)
+                    .center();
+                mouse.move_mouse(center.x as i32, center.y as i32, enigo::Coordinate::Abs);
+                click(Button::Left, enigo::Direction::Click);
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+            }
+            Action::SkipToCell => self.skip_to_cell(ctx),
+            Action::PrevScreen => {
+                let next_display = if self.state.current_display == 0 {
+                    self.state.displays.len() - 1
+                } else {
+                    self.state.current_display - 1
+                };
+                self.move_to_display(ctx, next_display);
+            }
+            Action::NextScreen => {
+                let next_display = self.state.current_display + 1;
+                self.move_to_display(ctx, next_display);
+            }
+
+            Action::SelectCell(i) => match self.state.mode {
+                Mode::Recurse(depth) => self.pick_within(i, depth),
+                _ if self.state.config.recursive_narrowing => {
+                    self.select_cell(i);
+                    self.pick_within_cell();
+                }
+                _ => self.select_cell(i),
+            },
+
+            Action::MicroPoint(i) => {
+                if self.state.mode == Mode::Cell {
+                    let cell = self.active_cell();
+                    let pos = GridGeometry::with_size(cell.min, cell.size(), MICRO_GRID)
+                        .cell_rect(0, i)
+                        .center();
+                    mouse.move_mouse(pos.x as i32, pos.y as i32, enigo::Coordinate::Abs);
+                }
+            }
+
+            Action::MoveUp | Action::MoveDown | Action::MoveLeft | Action::MoveRight => {
+                self.move_pointer(action.direction(), dist)
+            }
+            Action::WarpUp | Action::WarpDown | Action::WarpLeft | Action::WarpRight => {
+                self.warp(action)
+            }
+
+            Action::LeftClick => {
+                println!("Click");
+
+                click(Button::Left, enigo::Direction::Click);
+                if self.state.config.after.contains_key(&action) {
+                    self.after_click(ctx, action);
+                } else {
+                    match self.state.config.after_left_click {
+                        AfterLeftClick::Refocus => ctx.send_viewport_cmd(ViewportCommand::Focus),
+                        AfterLeftClick::Yield => self.state.focus_yielded = true,
+                        AfterLeftClick::Close => ctx.send_viewport_cmd(ViewportCommand::Close),
+                    }
+                }
+            }
+            Action::LeftClickAndExit => {
+                println!("Click and bye!");
+
+                click(Button::Left, enigo::Direction::Click);
+                self.after_click(ctx, action);
+            }
+            Action::MiddleClick => {
+                println!("Middle Click");
+
+                click(Button::Middle, enigo::Direction::Click);
+                self.after_click(ctx, action);
+            }
+            Action::BackClick | Action::ForwardClick => {
+                println!("Side button click");
+
+                let button = if action == Action::BackClick {
+                    Button::Back
+                } else {
+                    Button::Forward
+                };
+                click(button, enigo::Direction::Click);
+                self.after_click(ctx, action);
+            }
+            Action::DoubleClick | Action::TripleClick => {
+                println!("Multi click");
+
+                click(Button::Left, enigo::Direction::Click);
+                let count = if action == Action::DoubleClick { 2 } else { 3 };
+                for _ in 1..count {
+                    mouse.pause(self.state.config.multi_click_delay);
+                    mouse.button(Button::Left, enigo::Direction::Click);
+                }
+                self.after_click(ctx, action);
+            }
+            Action::RightClick => {
+                println!("Right Click");
+
+                click(Button::Right, enigo::Direction::Click);
+                self.after_click(ctx, action);
+            }
+
+            Action::LeftClickDown => {
+                println!("Press down");
+                click(Button::Left, enigo::Direction::Press);
+                state::record_left_button_held();
+            }
+            Action::LeftClickUp => {
+                println!("Press release");
+
+                mouse.button(Button::Left, enigo::Direction::Release);
+                state::record_left_button_released();
+                self.state.drag_from = None;
+            }
+            Action::DragAndExit => {
+                println!("Press down and bye!");
+
+                click(Button::Left, enigo::Direction::Press);
+                state::record_left_button_held();
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+            }
+            Action::DragStart => {
+                println!("Drag start");
+
+                click(Button::Left, enigo::Direction::Press);
+                state::record_left_button_held();
+                self.state.drag_from = Some(self.state.pointer.position());
+                self.start_over();
+            }
+            Action::DragDrop => {
+                if self.state.drag_from.take().is_some() {
+                    println!("Drop and bye!");
+
+                    mouse.button(Button::Left, enigo::Direction::Release);
+                    state::record_left_button_released();
+                    ctx.send_viewport_cmd(ViewportCommand::Close);
+                }
+            }
+
+            Action::ScrollUp | Action::ScrollDown | Action::ScrollLeft | Action::ScrollRight => {
+                let (amount, axis) = match action {
+                    Action::ScrollUp => (-scroll_speed, enigo::Axis::Vertical),
+                    Action::ScrollDown => (scroll_speed, enigo::Axis::Vertical),
+                    Action::ScrollLeft => (-scroll_speed, enigo::Axis::Horizontal),
+                    _ => (scroll_speed, enigo::Axis::Horizontal),
+                };
+                println!("{action:?}");
+                mouse.scroll(amount, axis);
+            }
+            Action::ScrollPageUp | Action::ScrollPageDown => {
+                let amount = if action == Action::ScrollPageUp {
+                    -page
+                } else {
+                    page
+                };
+                println!("{action:?}");
+                mouse.scroll(amount, enigo::Axis::Vertical);
+            }
+
+            Action::ToggleTouch => self.state.touch = !self.state.touch,
+            Action::ToggleHeatmap => self.toggle_heatmap(),
+            Action::SearchTargets => self.state.search = Some(String::new()),
+            Action::IdentifyDisplays => match DisplayInfo::all() {
+                Ok(infos) => {
+                    self.state.identify = Some(Identify::new(infos.into_iter().map(display_label)))
+                }
+                Err(err) => {
+                    let msg = self.state.config.locale.tr(Msg::DisplayInfoError, &[&err]);
+                    self.state.toasts.push(msg);
+                }
+            },
+            Action::TogglePause => {
+                self.state.paused = !self.state.paused;
+                ctx.request_repaint();
+            }
+            Action::MirrorLayout => self.set_mirrored(!self.state.mirrored),
+            Action::HistoryBack | Action::HistoryForward => {
+                let pos = if action == Action::HistoryBack {
+                    self.state.history.back()
+                } else {
+                    self.state.history.forward()
+                };
+                if let Some(pos) = pos {
+                    self.jump_to(ctx, pos);
+                }
+            }
+            Action::NextProfile => {
+                let profiles = &self.state.config.profiles;
+                // After the last profile comes the config without one
+                let next = match self.state.config.profile {
+                    None => profiles.first(),
+                    Some(i) => profiles.get(i + 1),
+                }
+                .cloned();
+                if profiles.is_empty() {
+                    let msg = self.state.config.locale.tr(Msg::NoProfiles, &[]);
+                    self.state.toasts.push(msg);
+                } else if self.apply_config_file(next.as_deref()) {
+                    let locale = self.state.config.locale;
+                    let msg = match &next {
+                        Some(name) => locale.tr(Msg::ProfileSwitched, &[name]),
+                        None => locale.tr(Msg::ProfileCleared, &[]),
+                    };
+                    self.state.toasts.push(msg);
+                }
+            }
+            Action::ActivateWindow => mouse.activate_window(),
+            Action::MoveWindow | Action::ResizeWindow => {
+                let grab = self.state.target_window.and_then(|window| {
+                    let mut grab = WindowGrab::new(window, None)?;
+                    if action == Action::ResizeWindow {
+                        let pointer = self.state.pointer.position();
+                        grab.edges = Some(nearest_edges(grab.start, pointer));
+                    }
+                    Some(grab)
+                });
+                let msg = match (&grab, action) {
+                    (None, _) => Msg::NoFocusedWindow,
+                    (Some(_), Action::MoveWindow) => Msg::MovingWindow,
+                    (Some(_), _) => Msg::ResizingWindow,
+                };
+                self.state.window_grab = grab;
+                let msg = self.state.config.locale.tr(msg, &[]);
+                self.state.toasts.push(msg);
+            }
+            Action::ToggleCursor => {
+                if self.state.hidden_cursor.take().is_none() {
+                    self.state.hidden_cursor = HiddenCursor::new();
+                }
+            }
+            Action::ScanSelect => {
+                if let Some(target) = self.scan_target() {
+                    self.perform(ctx, target, dist);
+                }
+            }
+            Action::RunMacro(i) => match self.state.config.key_bindings.macros.get(i) {
+                Some(m) if m.confirm => self.state.pending_macro = Some(i),
+                Some(_) => self.run_macro(ctx, i, dist),
+                None => {}
+            },
+            Action::NarrowAtPointer => {
+                let pointer = self.state.pointer.position();
+                if let Some((display_idx, region)) = self.region_at(pointer) {
+                    if display_idx != self.state.current_display {
+                        self.move_to_display(ctx, display_idx);
+                    }
+                    self.state.picks.clear();
+                    self.select_region(region);
+                }
+            }
+            Action::GazeSelect => {
+                if let Some((display_idx, region)) = self.gaze_target() {
+                    if display_idx != self.state.current_display {
+                        self.move_to_display(ctx, display_idx);
+                    }
+                    self.select_region(region);
+                }
+            }
+
+            // Applied to the movement distance in `handle_input`
+            Action::SpeedQuarter
+            | Action::SpeedHalf
+            | Action::SpeedTwice
+            | Action::SpeedQuadruple => {}
+        }
+
+        if action.selects() {
+            self.log_event(action);
+        }
+        let warped = matches!(
+            action,
+            Action::SelectCell(_) | Action::ClickRegion(_) | Action::MicroPoint(_)
+        );
+        if warped || action.warps() || action.clicks() {
+            let pos = self.action_position(action);
+            self.state.history.push(pos);
+            state::record_positions(self.state.history.positions());
+        }
+        if action.clicks() {
+            self.run_hook(HookEvent::Click, Some(action));
+        }
+    }
+
+    // Tells the hook where the pointer is, and for clicks, which action clicked
+    fn run_hook(&self, event: HookEvent, action: Option<Action>) {
+        let pos = self.state.pointer.position();
+        let mut vars = vec![
+            ("KMGRID_X", pos.x.to_string()),
+            ("KMGRID_Y", pos.y.to_string()),
+            ("KMGRID_DISPLAY", self.state.current_display.to_string()),
+        ];
+        vars.extend(action.map(|action| ("KMGRID_ACTION", action.name())));
+        self.state.config.hooks.run(event, &vars);
+    }
+
+    // Read fresh every time it's shown, so it includes the selections of this session
+    fn toggle_heatmap(&mut self) {
+        if self.state.heatmap.take().is_some() {
+            return;
+        }
+        let Some(path) = &self.state.config.event_log else {
+            let msg = self.state.config.locale.tr(Msg::HeatmapNoLog, &[]);
+            self.state.toasts.push(msg);
+            return;
+        };
+        match eventlog::read(path) {
+            Ok(events) => self.state.heatmap = Some(Heatmap::new(&events)),
+            Err(err) => {
+                let msg = self.state.config.locale.tr(Msg::HeatmapError, &[&err]);
+                self.state.toasts.push(msg);
+            }
+        }
+    }
+
+    fn log_event(&mut self, action: Action) {
+        let pos = self.action_position(action);
+        let Some(log) = &mut self.state.event_log else {
+            return;
+        };
+
+        let event = Event::now(
+            action.name(),
+            self.state.current_display,
+            self.state.region,
+            self.state.cell,
+            pos.x,
+            pos.y,
+        );
+        if let Err(err) = log.record(&event) {
+            let msg = self.state.config.locale.tr(Msg::EventLogError, &[&err]);
+            self.state.toasts.push(msg);
+        }
+    }
+
+    // Where the pointer is after `action`. Warps are still queued for the mouse worker, so this takes
+    // their target over the pointer.
+    fn action_position(&self, action: Action) -> Pos2 {
+        let geometry = self.state.displays[self.state.current_display].geometry();
+        match action {
+            Action::SelectCell(c) => geometry.cell_center(self.state.region as usize, c),
+            Action::ClickRegion(r) => geometry.region_rect(r).center(),
+            Action::MicroPoint(i) if self.state.mode == Mode::Cell => {
+                let cell = self.active_cell();
+                GridGeometry::with_size(cell.min, cell.size(), MICRO_GRID)
+                    .cell_rect(0, i)
+                    .center()
+            }
+            _ if action.warps() => self
+                .state
+                .warp
+                .map_or(self.state.pointer.position(), |warp| warp.pos),
+            _ => self.state.pointer.position(),
+        }
+    }
+
+    // Movement distance for this frame before any speed modifiers
+    fn base_speed(&self, input: &egui::InputState) -> f32 {
+        let display = &self.state.displays[self.state.current_display];
+        let pixels =
+            |speed: Speed| speed.pixels_per_frame(display.size, display.size_mm, input.stable_dt);
+        let speed = match &self.state.config.acceleration {
+            Some(acceleration) => {
+                let t = acceleration.progress(self.state.moving_for);
+                let (initial, max) = (pixels(acceleration.initial), pixels(acceleration.max));
+                initial + (max - initial) * t
+            }
+            None => pixels(self.state.config.movement_speed),
+        };
+        speed * self.cell_speedup()
+    }
 
-        egui::CentralPanel::default()
-            .frame(egui::Frame::none())
-            .show(ctx, |ui| {
-                let painter = ui.painter();
-                let ref display = self.state.displays[self.state.current_display];
-                let origin = Pos2::ZERO - display.offset;
-                let style = &self.state.config.style;
+    // Scroll steps for this frame before `cell_speedup`
+    fn scroll_steps(&self) -> f32 {
+        let config = &self.state.config;
+        match &config.scroll_acceleration {
+            Some(acceleration) => acceleration.steps(config.scroll_speed, self.state.scrolling_for),
+            None => config.scroll_speed as f32,
+        }
+    }
 
-                let region_line1_stroke = to_stroke(5.0, style.region_line1);
-                let region_line2_stroke = to_stroke(3.0, style.region_line2);
+    // What `cell_speedup` multiplies moving and scrolling by right now
+    fn cell_speedup(&self) -> f32 {
+        match (&self.state.config.cell_speedup, self.state.cell_since) {
+            (Some(speedup), Some(since)) => speedup.factor(since.elapsed()),
+            _ => 1.0,
+        }
+    }
 
-                let region_size = vec2(display.size.x * 0.25, display.size.y * 0.25);
-                let cell_size = vec2(region_size.x / 5.0, region_size.y / 3.0);
+    fn run_macro(&mut self, ctx: &egui::Context, i: usize, dist: f32) {
+        let steps = self.state.config.key_bindings.macros[i].steps.clone();
+        for step in steps {
+            self.perform(ctx, step, dist);
+        }
+    }
 
-                if self.state.mode == Mode::Screen {
-                    // Draw screen borders
-                    let screen_border = Rect::from_min_size(origin, display.size).shrink(5.0);
-                    painter.rect_stroke(screen_border, Rounding::ZERO, region_line1_stroke);
-                    painter.rect_stroke(screen_border, Rounding::ZERO, region_line2_stroke);
+    // Screen positions the clicks of a macro will land on, following its region and cell steps
+    fn planned_clicks(&self, i: usize) -> Vec<Pos2> {
+        let geometry = self.state.displays[self.state.current_display].geometry();
+        let mut region = self.state.region as usize;
+        let mut pos = self.state.pointer.position();
 
-                    let region_grid_line1_stroke = to_stroke(1.5, style.region_grid_line1);
-                    let region_grid_line2_stroke = to_stroke(1.5, style.region_grid_line2);
+        let mut clicks = Vec::new();
+        for step in &self.state.config.key_bindings.macros[i].steps {
+            match *step {
+                Action::SelectRegion(r) => region = r,
+                Action::SelectCell(c) => pos = geometry.cell_center(region, c),
+                action if action.clicks() => clicks.push(pos),
+                _ => {}
+            }
+        }
+        clicks
+    }
 
-                    // Draw horizontal lines
-                    let horizontal_line_count = 12;
-                    for i in 1..horizontal_line_count {
-                        let percentage = i as f32 / horizontal_line_count as f32;
-                        let left = origin + vec2(0.0, display.size.y * percentage);
-                        let right = origin + vec2(display.size.x, display.size.y * percentage);
+    fn handle_input(&mut self, ctx: &egui::Context) {
+        let input = ctx.input(|i: &egui::InputState| i.clone());
 
-                        painter.line_segment([left, right], region_grid_line1_stroke);
-                        painter.line_segment([left, right], region_grid_line2_stroke);
-                    }
+        if self.state.mode != Mode::Cell {
+            self.state.cell_since = None;
+            self.state.warp = None;
+        } else if self.state.cell_since.is_none() {
+            self.state.cell_since = Some(Instant::now());
+        }
 
-                    // Draw vertical lines
-                    let vertical_line_count = 20;
-                    for i in 1..vertical_line_count {
-                        let percentage = i as f32 / vertical_line_count as f32;
-                        let top = origin + vec2(display.size.x * percentage, 0.0);
-                        let btm = origin + vec2(display.size.x * percentage, display.size.y);
+        // Keys only reach the overlay once it has focus again
+        let key_pressed = input
+            .events
+            .iter()
+            .any(|e| matches!(e, egui::Event::Key { pressed: true, .. }));
+        if key_pressed {
+            self.state.focus_yielded = false;
+        }
+        for event in &input.events {
+            if let egui::Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } = event
+            {
+                self.state.key_modifiers.insert(*key, *modifiers);
+            }
+        }
 
-                        painter.line_segment([top, btm], region_grid_line1_stroke);
-                        painter.line_segment([top, btm], region_grid_line2_stroke);
+        // A macro waiting for confirmation takes over the keyboard
+        if let Some(i) = self.state.pending_macro {
+            if input.key_pressed(Key::Enter) {
+                self.state.pending_macro = None;
+                self.run_macro(ctx, i, self.base_speed(&input));
+            } else if input.key_pressed(Key::Escape) {
+                self.state.pending_macro = None;
+            }
+            return;
+        }
+
+        // So does moving or resizing a window, until it's placed with Enter or put back with Escape
+        if let Some(grab) = &mut self.state.window_grab {
+            if input.key_pressed(Key::Enter) {
+                self.state.window_grab = None;
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+            } else if input.key_pressed(Key::Escape) {
+                grab.apply(grab.start);
+                self.state.window_grab = None;
+            } else {
+                let mouse = &self.state.config.key_bindings.mouse;
+                let direction = [
+                    (&mouse.move_up, Action::MoveUp),
+                    (&mouse.move_down, Action::MoveDown),
+                    (&mouse.move_left, Action::MoveLeft),
+                    (&mouse.move_right, Action::MoveRight),
+                ]
+                .into_iter()
+                .filter(|(chords, _)| {
+                    chords.iter().any(|chord| {
+                        chord.modifiers_match(input.modifiers) && input.key_down(chord.key)
+                    })
+                })
+                .fold(Vec2::ZERO, |direction, (_, action)| {
+                    direction + action.direction()
+                });
+                if direction != Vec2::ZERO {
+                    let display = &self.state.displays[self.state.current_display];
+                    let dist = self.state.config.movement_speed.pixels_per_frame(
+                        display.size,
+                        display.size_mm,
+                        input.stable_dt,
+                    );
+                    grab.shift(direction.normalized() * dist);
+                    grab.apply(grab.rect);
+                }
+            }
+            return;
+        }
+
+        // So does the search prompt, from the frame after it opened
+        if let Some(query) = &mut self.state.search {
+            for event in &input.events {
+                match event {
+                    egui::Event::Text(text) => query.push_str(text),
+                    egui::Event::Key {
+                        key: Key::Backspace,
+                        pressed: true,
+                        ..
+                    } => {
+                        query.pop();
                     }
+                    _ => {}
+                }
+            }
+            if input.key_pressed(Key::Enter) {
+                let query = self.state.search.take().unwrap();
+                let target = search::matches(&self.state.config.targets, &query)
+                    .first()
+                    .map(|t| pos2(t.x, t.y));
+                if let Some(pos) = target {
+                    self.jump_to(ctx, pos);
+                }
+            } else if input.key_pressed(Key::Escape) {
+                self.state.search = None;
+            }
+            return;
+        }
 
-                    // Draw region stripes
-                    for i in 0..4 {
-                        let rect = egui::Rect::from_min_size(
-                            origin + vec2(0.0, i as f32 * region_size.y),
-                            vec2(display.size.x, region_size.y),
-                        );
-                        let color = if i % 2 == 0 {
-                            self.state.config.style.left_grid.clone()
-                        } else {
-                            self.state.config.style.right_grid.clone()
-                        };
+        // Hint keys are typed until they make up a whole label, which goes to its cell. Anything
+        // else does what it's bound to, Backspace takes back a typed key first.
+        if self.state.mode == Mode::Hints {
+            let geometry = self.state.displays[self.state.current_display].geometry();
+            let keys = &self.state.config.key_bindings.hint_keys;
+            let labels = hints::labels(geometry.region_count() * geometry.cell_count(), keys.len());
+            if let Some(key) = keys.iter().position(|key| input.key_pressed(*key)) {
+                self.state.hint_typed.push(key);
+                let typed = self.state.hint_typed.len();
+                let first = hints::matching(&labels, &self.state.hint_typed).next();
+                match first {
+                    Some(i) if labels[i].len() == typed => {
+                        let (region, cell, _) = geometry.iter_cells().nth(i).unwrap();
+                        self.state.hint_typed.clear();
+                        self.state.region = region as i32;
+                        self.perform(ctx, Action::SelectCell(cell), self.base_speed(&input));
+                    }
+                    Some(_) => {}
+                    // Not the start of any label
+                    None => {
+                        self.state.hint_typed.pop();
+                    }
+                }
+                return;
+            } else if input.key_pressed(Key::Backspace) && self.state.hint_typed.pop().is_some() {
+                return;
+            }
+        }
 
-                        painter.rect(rect, Rounding::ZERO, to_col(color), Stroke::NONE);
+        if self.state.mode != Mode::Cell {
+            self.state.count = None;
+        } else if self.state.config.count_prefixes && self.count_prefix(&input) {
+            return;
+        }
+
+        // The second key of a sequence label is a cell key, or Escape to pick another region
+        let sequence = self
+            .state
+            .sequence
+            .filter(|_| self.state.mode == Mode::Screen);
+        if let Some(region) = sequence {
+            let grid = &self.state.config.key_bindings.grid;
+            let cell = grid.iter().position(|key| input.key_pressed(*key));
+            if let Some(cell) = cell {
+                self.state.sequence = None;
+                self.state.region = region as i32;
+                self.perform(ctx, Action::SelectCell(cell), self.base_speed(&input));
+                return;
+            } else if input.key_pressed(Key::Escape) || input.key_pressed(Key::Backspace) {
+                self.state.sequence = None;
+                return;
+            }
+        }
+
+        // Look up everything first, actions may switch modes but only the bindings of the mode
+        // the frame started in apply
+        let bindings = self.state.dispatch.for_mode(self.state.mode).to_vec();
+        let mut fired = Vec::new();
+        let sticky_speed_keys = self.state.config.sticky_speed_keys;
+        for binding in bindings {
+            let key = binding.chord.key;
+            let modifiers = self.key_modifiers(&input, key);
+            let down = match binding.action.trigger() {
+                Trigger::Press => input.key_pressed(key),
+                Trigger::Hold => self.is_held_with_check(key, input.key_down(key)),
+                Trigger::Modifier if sticky_speed_keys => {
+                    if freshly_pressed(&input, key) && binding.chord.modifiers_match(modifiers) {
+                        self.latch_speed(binding.action);
                     }
+                    false
+                }
+                Trigger::Modifier => input.key_down(key),
+            };
+            if down && binding.chord.modifiers_match(modifiers) {
+                fired.push(binding);
+            }
+        }
+        // A key bound both plain and with the held modifiers only does the latter
+        let chorded: Vec<_> = fired
+            .iter()
+            .filter(|b| b.chord.has_modifiers())
+            .map(|b| b.chord.key)
+            .collect();
+        fired.retain(|b| b.chord.has_modifiers() || !chorded.contains(&b.chord.key));
+        // First match wins, as if keys came one at a time: of the keys pressed in the same frame
+        // only the first binding in table order fires, and of the held scroll keys only the
+        // first. Held movement and speed keys add up.
+        let (mut pressed, mut scrolled) = (false, false);
+        fired.retain(|b| match b.action.trigger() {
+            Trigger::Press => !std::mem::replace(&mut pressed, true),
+            Trigger::Hold if b.action.direction() == Vec2::ZERO => {
+                !std::mem::replace(&mut scrolled, true)
+            }
+            Trigger::Hold | Trigger::Modifier => true,
+        });
 
-                    let black_font = egui::FontId::new(60.0, egui::FontFamily::Proportional);
-                    let white_font = egui::FontId::new(60.0, egui::FontFamily::Proportional);
+        // With `click_hold_ms`, click keys only click once held that long, so brief accidental
+        // taps do nothing
+        let click_hold = self.state.config.click_hold;
+        if !click_hold.is_zero() {
+            let pressed = fired.iter().find(|b| b.action.clicks()).copied();
+            fired.retain(|b| !b.action.clicks());
+            if let (Some(binding), None) = (pressed, self.state.held_click) {
+                self.state.held_click = Some(HeldClick {
+                    binding,
+                    since: Instant::now(),
+                    clicked: false,
+                });
+            }
+            if let Some(held) = &mut self.state.held_click {
+                if !input.key_down(held.binding.chord.key) {
+                    self.state.held_click = None;
+                } else if !held.clicked && held.since.elapsed() >= click_hold {
+                    held.clicked = true;
+                    fired.push(held.binding);
+                }
+            }
+        }
 
-                    let region_line1_stroke = to_stroke(2.0, style.region_line1);
-                    let region_line2_stroke = to_stroke(1.0, style.region_line2);
-                    for (i, key) in self.state.config.key_bindings.region.iter().enumerate() {
-                        let region_x = (i % 4) as f32;
-                        let region_y = (i / 4) as f32;
+        let mut actions = Vec::new();
+        let region_click = self.state.config.region_click_modifier;
+        let in_screen = self.state.mode == Mode::Screen;
+        for binding in fired {
+            let action = match binding.action {
+                Action::SelectRegion(i)
+                    if in_screen
+                        && region_click.is_held(self.key_modifiers(&input, binding.chord.key)) =>
+                {
+                    Action::ClickRegion(i)
+                }
+                action => action,
+            };
+            // Once however many of its keys are down
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+        for action in self.device_actions(true) {
+            if let DeviceAction::Action(action) = action {
+                if !actions.contains(&action) {
+                    actions.push(action);
+                }
+            }
+        }
+        actions.append(&mut self.state.taps);
+        if let Some(commands) = &self.state.commands {
+            for command in commands.take() {
+                actions.extend(self.command_actions(command));
+            }
+        }
 
-                        let text_pos = origin
-                            + vec2(
-                                (region_x + 0.5) * region_size.x,
-                                (region_y + 0.5) * region_size.y,
-                            );
+        if self.state.paused {
+            actions.retain(|a| matches!(a, Action::TogglePause | Action::Exit));
+        }
 
-                        // Draw region text
-                        for i in 0..9 {
-                            painter.text(
-                                text_pos
-                                    + vec2(((i % 3) - 1) as f32 * 3.0, ((i / 3) - 1) as f32 * 3.0),
-                                Align2::CENTER_CENTER,
-                                key.name(),
-                                black_font.clone(),
-                                Color32::BLACK,
-                            );
-                        }
-                        painter.text(
-                            text_pos,
-                            Align2::CENTER_CENTER,
-                            key.name(),
-                            white_font.clone(),
-                            Color32::WHITE,
-                        );
+        for &(action, _) in &self.state.latched_speeds {
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
 
-                        // Draw region outline
-                        let rect_pos =
-                            origin + vec2(region_x * region_size.x, region_y * region_size.y);
-                        painter.rect_stroke(
-                            Rect::from_min_size(rect_pos, region_size),
-                            Rounding::ZERO,
-                            region_line1_stroke,
-                        );
-                        painter.rect_stroke(
-                            Rect::from_min_size(rect_pos, region_size),
-                            Rounding::ZERO,
-                            region_line2_stroke,
-                        );
-                    }
-                } else if self.state.mode == Mode::Narrow {
-                    let origin = origin
-                        + vec2(
-                            region_size.x * (self.state.region % 4) as f32,
-                            region_size.y * (self.state.region / 4) as f32,
-                        );
+        let speed = self.base_speed(&input);
+        let dist = actions.iter().fold(speed, |dist, a| a.apply_speed(dist));
+        let scrolling = actions
+            .iter()
+            .any(|a| a.trigger() == Trigger::Hold && a.direction() == Vec2::ZERO);
+        // All held movement keys add up to a single move for the frame
+        let mut direction = Vec2::ZERO;
+        for action in actions {
+            if action.direction() != Vec2::ZERO {
+                direction += action.direction();
+            } else {
+                self.perform(ctx, action, dist);
+            }
+        }
+        self.move_pointer(direction, dist);
+        if direction == Vec2::ZERO {
+            // A move that just ended uses up the speed keys latched for it
+            if self.state.moving_for > 0.0 {
+                self.state.latched_speeds.retain(|&(_, locked)| locked);
+            }
+            self.state.moving_for = 0.0;
+        } else {
+            self.state.moving_for += input.stable_dt;
+        }
+        if scrolling {
+            self.state.scrolling_for += input.stable_dt;
+        } else {
+            self.state.scrolling_for = 0.0;
+        }
+    }
 
-                    // Draw region background
-                    let right_color = to_col(style.right_grid);
-                    let right_rect =
-                        egui::Rect::from_min_size(origin, vec2(region_size.x, region_size.y));
-                    painter.rect(right_rect, Rounding::ZERO, right_color, Stroke::NONE);
+    // Digits typed in Cell mode add up to a count, and the movement key after them moves that many
+    // times as far at once. Escape drops the count, any other key drops it and does what it's
+    // bound to. Returns whether the keys of this frame were taken.
+    fn count_prefix(&mut self, input: &egui::InputState) -> bool {
+        let digits: Vec<u32> = input
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                egui::Event::Key {
+                    key, pressed: true, ..
+                } => key.name().parse().ok(),
+                _ => None,
+            })
+            .collect();
+        if !digits.is_empty() {
+            let count = digits
+                .iter()
+                .fold(self.state.count.unwrap_or(0), |count, d| {
+                    (count * 10 + d).min(MAX_COUNT)
+                });
+            // A leading zero counts nothing
+            self.state.count = (count > 0).then_some(count);
+            return true;
+        }
+        let Some(count) = self.state.count else {
+            return false;
+        };
 
-                    // Draw cell vertical lines
-                    for i in 0..6 {
-                        let i = i as f32;
-                        let start = origin + vec2(i * cell_size.x, 0.0);
-                        let end = origin + vec2(i * cell_size.x, region_size.y);
-                        painter.line_segment([start, end], region_line1_stroke);
-                        painter.line_segment([start, end], region_line2_stroke);
-                    }
+        let mouse = &self.state.config.key_bindings.mouse;
+        let moved = [
+            (&mouse.move_up, Action::MoveUp),
+            (&mouse.move_down, Action::MoveDown),
+            (&mouse.move_left, Action::MoveLeft),
+            (&mouse.move_right, Action::MoveRight),
+        ]
+        .into_iter()
+        .flat_map(|(chords, action)| chords.iter().map(move |chord| (*chord, action)))
+        .find(|(chord, _)| chord.modifiers_match(input.modifiers) && input.key_pressed(chord.key));
+        if let Some((chord, action)) = moved {
+            self.state.count = None;
+            // Moving on while the key stays down takes letting go of it first
+            self.state.mouse_key_down.remove(&chord.key);
+            let dist = self.base_speed(input) * count as f32;
+            self.move_pointer(action.direction(), dist);
+            return true;
+        }
+        if input.key_pressed(Key::Escape) {
+            self.state.count = None;
+            return true;
+        }
+        let key_pressed = input
+            .events
+            .iter()
+            .any(|e| matches!(e, egui::Event::Key { pressed: true, .. }));
+        if key_pressed {
+            self.state.count = None;
+        }
+        false
+    }
 
-                    // Draw cell horizontal lines
-                    for i in 0..4 {
-                        let i = i as f32;
-                        let start = origin + vec2(0.0, i * cell_size.y);
-                        let end = origin + vec2(region_size.x, i * cell_size.y);
-                        painter.line_segment([start, end], region_line1_stroke);
-                        painter.line_segment([start, end], region_line2_stroke);
+    // What the buttons of the input devices do this frame: their presses since the last frame, and
+    // with `held`, the actions that repeat while a button stays down
+    fn device_actions(&self, held: bool) -> Vec<DeviceAction> {
+        let Some(devices) = &self.state.devices else {
+            return Vec::new();
+        };
+        let bound = |path: &str| {
+            let device = self
+                .state
+                .config
+                .input_devices
+                .iter()
+                .find(|d| d.path == path);
+            device.into_iter().flat_map(|d| d.bindings.iter().copied())
+        };
+        let mut actions: Vec<_> = devices
+            .take_presses()
+            .into_iter()
+            .flat_map(|(path, code)| {
+                bound(&path)
+                    .filter(|(c, action)| *c == code && !action.while_held())
+                    .map(|(_, action)| action)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if held {
+            for device in &self.state.config.input_devices {
+                for &(code, action) in &device.bindings {
+                    if action.while_held() && devices.is_held(&device.path, code) {
+                        actions.push(action);
                     }
+                }
+            }
+        }
+        actions
+    }
 
-                    // Draw cell text
-                    let black_font = egui::FontId::new(27.0, egui::FontFamily::Proportional);
-                    let white_font = egui::FontId::new(20.0, egui::FontFamily::Proportional);
-                    let text_offset = 6;
-                    for i in 0..3 {
-                        let pos = origin + vec2((i as f32 + 1.5) * cell_size.x, cell_size.y * 1.5);
-                        let text = self.state.config.key_bindings.grid[text_offset + i].name();
+    // The action the switch scanning highlight currently points at
+    fn scan_target(&self) -> Option<Action> {
+        if self.state.relative_only {
+            return None;
+        }
+        let scanner = self.state.scanner.as_ref()?;
+        let geometry = self.state.displays[self.state.current_display].geometry();
+        Some(match self.state.mode {
+            Mode::Screen => Action::SelectRegion(scanner.index(geometry.region_count())),
+            Mode::Narrow | Mode::Recurse(_) => {
+                Action::SelectCell(scanner.index(geometry.cell_count()))
+            }
+            Mode::Cell => scan::CELL_ACTIONS[scanner.index(scan::CELL_ACTIONS.len())].0,
+            // Scanning through the hints would take longer than the regions and cells
+            Mode::Hints => return None,
+        })
+    }
 
-                        for j in 0..9 {
-                            painter.text(
-                                pos
-                                    + vec2(((j % 3) - 1) as f32 * 1.5, ((j / 3) - 1) as f32 * 1.5),
-                                Align2::CENTER_CENTER,
-                                text,
-                                black_font.clone(),
-                                Color32::BLACK,
-                            );
-                        }
+    // Control socket commands name keys, so they do whatever those keys are bound to
+    fn command_actions(&mut self, command: Command) -> Vec<Action> {
+        let bindings = &self.state.config.key_bindings;
+        let bound = |keys: &[Key], key: Key| keys.iter().position(|k| *k == key);
 
-                        painter.text(
-                            pos,
-                            Align2::CENTER_CENTER,
-                            text,
-                            white_font.clone(),
-                            Color32::WHITE,
-                        );
+        match command {
+            Command::SelectRegion(key) | Command::SelectCell(key) => {
+                let action = match command {
+                    Command::SelectRegion(_) => {
+                        bound(&bindings.region, key).map(Action::SelectRegion)
                     }
-                } else if self.state.mode == Mode::Cell {
-                    let origin = origin
-                        + vec2(
-                            region_size.x * (self.state.region % 4) as f32,
-                            region_size.y * (self.state.region / 4) as f32,
-                        )
-                        + vec2(
-                            cell_size.x * (self.state.cell % 5) as f32,
-                            cell_size.y * (self.state.cell / 5) as f32,
-                        );
+                    _ => bound(&bindings.grid, key).map(Action::SelectCell),
+                };
+                if action.is_none() {
+                    let msg = self.state.config.locale.tr(Msg::UnboundKey, &[&key.name()]);
+                    self.state.toasts.push(msg);
+                }
+                action.into_iter().collect()
+            }
+            Command::Click(ClickButton::Left) => vec![Action::LeftClickAndExit],
+            Command::Click(ClickButton::Right) => vec![Action::RightClick],
+            Command::Click(ClickButton::Middle) => vec![Action::MiddleClick],
+            Command::Release => vec![Action::LeftClickUp],
+            Command::Pause if !self.state.paused => vec![Action::TogglePause],
+            Command::Resume if self.state.paused => vec![Action::TogglePause],
+            Command::Pause | Command::Resume => Vec::new(),
+            // All at once, rather than `amount` scroll actions
+            Command::Scroll(_, _) if self.state.paused => Vec::new(),
+            Command::Scroll(dir, amount) => {
+                let steps = self.state.config.scroll_speed.saturating_mul(amount as i32);
+                let (amount, axis) = match dir {
+                    ScrollDirection::Up => (-steps, enigo::Axis::Vertical),
+                    ScrollDirection::Down => (steps, enigo::Axis::Vertical),
+                    ScrollDirection::Left => (-steps, enigo::Axis::Horizontal),
+                    ScrollDirection::Right => (steps, enigo::Axis::Horizontal),
+                };
+                println!("Scroll {dir:?} {steps}");
+                self.state.mouse.scroll(amount, axis);
+                Vec::new()
+            }
+        }
+    }
 
-                    // Draw cell borders
-                    let cell_border = Rect::from_min_size(origin, cell_size).shrink(5.0);
-                    painter.rect_stroke(cell_border, Rounding::ZERO, region_line1_stroke);
-                    painter.rect_stroke(cell_border, Rounding::ZERO, region_line2_stroke);
+    // For `kmgrid status`, one JSON line
+    fn status_line(&self) -> String {
+        let pos = self.state.pointer.position();
+        serde_json::json!({
+            "mode": format!("{:?}", self.state.mode).to_lowercase(),
+            "display": self.state.current_display,
+            "region": self.state.region,
+            "cell": self.state.cell,
+            "paused": self.state.paused,
+            "profile": self.state.config.profile_name(),
+            "x": pos.x,
+            "y": pos.y,
+        })
+        .to_string()
+    }
 
-                    // Draw cell background
-                    let rect = egui::Rect::from_min_size(origin, cell_size);
-                    painter.rect(rect, Rounding::ZERO, to_col(style.right_grid), Stroke::NONE);
+    // Display and region under the latest point from the eye / head tracker
+    fn gaze_target(&self) -> Option<(usize, usize)> {
+        let point = self.state.gaze.as_ref()?.latest()?;
+        self.region_at(point)
+    }
+
+    // Display and region at `point`, in screen coordinates
+    fn region_at(&self, point: Pos2) -> Option<(usize, usize)> {
+        self.state
+            .displays
+            .iter()
+            .enumerate()
+            .find_map(|(i, d)| d.geometry().locate(point).map(|(region, _)| (i, region)))
+    }
+
+    // With touch input on, tapping a region or cell picks it just like its key would
+    fn tap_action(&self, input: &egui::InputState) -> Option<Action> {
+        if !input.pointer.primary_clicked() {
+            return None;
+        }
+        let pos = input.pointer.interact_pos()?;
+        let display = &self.state.displays[self.state.current_display];
+        let (region, cell) = display.window_geometry().locate(pos)?;
+
+        match self.state.mode {
+            Mode::Screen => Some(Action::SelectRegion(region)),
+            Mode::Narrow if region as i32 == self.state.region => Some(Action::SelectCell(cell)),
+            Mode::Narrow => Some(Action::SelectRegion(region)),
+            Mode::Cell | Mode::Recurse(_) | Mode::Hints => None,
+        }
+    }
+
+    // The windows let the mouse through, except on the active display while touch input is
+    // picking a region or cell. Once in Cell mode taps go to the application under the overlay
+    // again, and so do all clicks while paused.
+    fn update_passthrough(&mut self, ctx: &egui::Context, display_idx: usize) {
+        let passthrough = display_idx != self.state.current_display
+            || self.state.paused
+            || !self.state.touch
+            || self.state.mode == Mode::Cell;
+        self.state
+            .passthrough
+            .resize(self.state.displays.len(), true);
+        if passthrough != self.state.passthrough[display_idx] {
+            ctx.send_viewport_cmd(ViewportCommand::MousePassthrough(passthrough));
+            self.state.passthrough[display_idx] = passthrough;
+        }
+    }
+
+    fn skip_to_cell(&mut self, ctx: &egui::Context) {
+        let mut mouse_pos = self.state.pointer.position();
+
+        let display_idx = match self.state.config.skip_to_cell_display {
+            SkipToCellDisplay::Stay => Some(self.state.current_display),
+            SkipToCellDisplay::FollowPointer => self
+                .state
+                .displays
+                .iter()
+                .position(|d| d.bounds().contains(mouse_pos)),
+        };
+        let Some(display_idx) = display_idx else {
+            return;
+        };
+
+        // Pull the pointer into the grid if it's on another display or under the offset. Stay a
+        // pixel inside, the far edges belong to the next display.
+        let geometry = self.state.displays[display_idx].geometry();
+        let rect = geometry.rect();
+        let clamped = mouse_pos.clamp(rect.min, rect.max - vec2(1.0, 1.0));
+        if clamped != mouse_pos {
+            mouse_pos = clamped;
+            self.state.mouse.move_mouse(
+                mouse_pos.x as i32,
+                mouse_pos.y as i32,
+                enigo::Coordinate::Abs,
+            );
+        }
+
+        if let Some((region, cell)) = geometry.locate(mouse_pos) {
+            self.state.region = region as i32;
+            self.state.cell = cell as i32;
+
+            self.state.mode = Mode::Cell;
+            if display_idx != self.state.current_display {
+                self.move_to_display(ctx, display_idx);
+            }
+            self.state.mouse_key_down.clear();
+        }
+    }
+
+    // Puts the pointer on a spot given in screen coordinates, and the overlay in Cell mode on the
+    // cell around it
+    fn jump_to(&mut self, ctx: &egui::Context, pos: Pos2) {
+        self.state
+            .mouse
+            .move_mouse(pos.x as i32, pos.y as i32, enigo::Coordinate::Abs);
+
+        let located = self.state.displays.iter().enumerate().find_map(|(i, d)| {
+            d.geometry()
+                .locate(pos)
+                .map(|(region, cell)| (i, region, cell))
+        });
+        if let Some((display_idx, region, cell)) = located {
+            self.state.region = region as i32;
+            self.state.cell = cell as i32;
+            self.state.mode = Mode::Cell;
+            if display_idx != self.state.current_display {
+                self.move_to_display(ctx, display_idx);
+            }
+            self.state.mouse_key_down.clear();
+        }
+    }
+}
+
+// Whether `key` went down this frame, not counting key repeat
+fn freshly_pressed(input: &egui::InputState, key: Key) -> bool {
+    input.events.iter().any(
+        |e| matches!(e, egui::Event::Key { key: k, pressed: true, repeat: false, .. } if *k == key),
+    )
+}
+
+fn to_stroke(width: f32, col: Color) -> Stroke {
+    let col = Color32::from_rgba_unmultiplied(col.0, col.1, col.2, col.3);
+    Stroke::new(width, col)
+}
+
+// Scales a label to the height of its region or cell, shrinking it further if it would be wider
+// than the box
+fn label_font_size(painter: &egui::Painter, text: &str, size: Vec2, style: &StyleConfig) -> f32 {
+    let font_size = size.y * LABEL_HEIGHT_RATIO;
+    let font = egui::FontId::proportional(font_size);
+    let width = painter
+        .layout_no_wrap(text.to_owned(), font, Color32::WHITE)
+        .size()
+        .x;
+    let max_width = size.x * LABEL_WIDTH_RATIO;
+    let fitted = if width > max_width {
+        font_size * max_width / width
+    } else {
+        font_size
+    };
+    fitted.max(style.label_min_size).min(style.label_max_size)
+}
+
+fn to_col(col: Color) -> Color32 {
+    Color32::from_rgba_unmultiplied(col.0, col.1, col.2, col.3)
+}
+
+impl eframe::App for MyApp {
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        egui::Rgba::TRANSPARENT.to_array() // Make sure we don't paint anything behind the rounded corners
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.end_session();
+    }
+
+    // Hack: egui::input doesn't send key down events for '+' keys for some reason. Investigation needed.
+    fn raw_input_hook(&mut self, _ctx: &egui::Context, _raw_input: &mut egui::RawInput) {
+        self.remap_to_physical_keys(_raw_input);
+
+        for e in &_raw_input.events {
+            if let egui::Event::Key {
+                key,
+                physical_key: Some(k),
+                pressed,
+                repeat,
+                ..
+            } = e
+            {
+                if *k == Key::Equals && *key == Key::Equals && !*pressed && !*repeat {
+                    _raw_input.events.push(egui::Event::Key {
+                        key: Key::Plus,
+                        physical_key: None,
+                        pressed: false,
+                        repeat: false,
+                        modifiers: Default::default(),
+                    });
+                    return;
                 }
+            }
+        }
+    }
 
-                let color = Color32::from_rgba_premultiplied(28, 92, 48, 120);
-                let rect = egui::Rect::from_two_pos(pos2(0.0, 0.0), pos2(50.0, 50.0));
-                painter.rect(rect, Rounding::ZERO, color, Stroke::new(0.0, color));
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(
+            egui::WindowLevel::AlwaysOnTop,
+        ));
+        if let Some(daemon) = &self.daemon {
+            if daemon.hidden {
+                // Whatever else the input devices did while hidden is dropped
+                let device_reopen = self.device_actions(false).contains(&DeviceAction::Reopen);
+                let forced = daemon.forced.as_ref().is_some_and(Activation::take);
+                let requested = daemon.activation.take() || device_reopen;
+                if forced || requested && !over_fullscreen(&self.state.config) {
+                    self.show(ctx);
+                } else if requested {
+                    eprintln!("Not reopening over a fullscreen window, reopen_anyway still does");
+                }
+                return;
+            }
+            if ctx.input(|i| i.viewport().close_requested()) {
+                self.hide(ctx);
+                return;
+            }
+        }
+        if !self.state.window_set_up {
+            self.state.window_set_up = true;
+            self.set_up_window(frame);
+        }
+        self.check_displays(ctx);
+        self.reload_config();
+        self.handle_input(ctx);
+        if let Some(commands) = &self.state.commands {
+            commands.publish_status(self.status_line());
+        }
+        for input_err in self.state.mouse.errors() {
+            let msg = self.state.config.locale.tr(Msg::MouseError, &[&input_err]);
+            self.state.toasts.push(msg);
+        }
+        if self.state.mouse.stalled() {
+            let msg = match self.state.config.pointer_backend {
+                PointerBackend::Xtest => Msg::PointerStalledXtest,
+                PointerBackend::Uinput => Msg::PointerStalledUinput,
+            };
+            let msg = self.state.config.locale.tr(msg, &[]);
+            self.state.toasts.push(msg);
+        }
+        if let Some(scanner) = &mut self.state.scanner {
+            scanner.sync(self.state.mode);
+        }
 
-                ctx.send_viewport_cmd(ViewportCommand::Focus);
-                ctx.request_repaint();
-            });
+        // Every display has an overlay window of its own, so switching displays doesn't move a
+        // window around. Keys always go to the root window.
+        let root = self.state.root_display;
+        let mut viewports = HashSet::new();
+        for i in 0..self.state.displays.len() {
+            if i != root {
+                let builder = overlay_viewport(&self.state.displays[i]).with_title("kmgrid");
+                ctx.show_viewport_immediate(display_viewport(i), builder, |ctx, _| {
+                    self.draw_display(ctx, i)
+                });
+                viewports.insert(display_viewport(i));
+            }
+        }
+        self.draw_display(ctx, root);
+        // eframe closes viewports that weren't shown, so one shown again has a new window
+        if !viewports.is_subset(&self.state.viewports_set_up) {
+            self.set_up_viewport_windows();
+        }
+        self.state.viewports_set_up = viewports;
+
+        if self.state.identify.as_ref().is_some_and(Identify::is_over) {
+            self.state.identify = None;
+        }
+        if !self.state.focus_yielded {
+            ctx.send_viewport_cmd(ViewportCommand::Focus);
+        }
+        ctx.request_repaint();
     }
 }
@@ -0,0 +1,89 @@
+use serde_json::{Map, Value};
+
+/// The config version this kmgrid reads as is and writes with `dump-config`
+pub const VERSION: u64 = 1;
+
+// One step up to version `to`, applied to the config itself and to each of its profiles. Returns
+// what it changed, one line each.
+struct Migration {
+    to: u64,
+    apply: fn(&mut Map<String, Value>) -> Vec<String>,
+}
+
+// In order. Renaming or reshaping a setting bumps VERSION and adds a step here, so configs written
+// for an older kmgrid keep loading rather than failing to deserialize.
+const MIGRATIONS: &[Migration] = &[
+    // Configs from before `version` are version 0, and read the same as version 1
+    Migration {
+        to: 1,
+        apply: |_| Vec::new(),
+    },
+];
+
+/// Brings a config file's contents, profiles included, up to [`VERSION`]. Returns the version the
+/// file was written for and what changed, or why it can't be read.
+pub fn migrate(value: &mut Value) -> Result<(u64, Vec<String>), String> {
+    let Some(config) = value.as_object_mut() else {
+        return Ok((VERSION, Vec::new()));
+    };
+    let version = match config.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("Invalid version {version}, needs to be a whole number"))?,
+    };
+    if version > VERSION {
+        return Err(format!(
+            "Written for config version {version}, this kmgrid reads up to {VERSION}"
+        ));
+    }
+
+    let mut changes = Vec::new();
+    for step in MIGRATIONS.iter().filter(|step| step.to > version) {
+        changes.extend((step.apply)(config));
+        if let Some(Value::Object(profiles)) = config.get_mut("profiles") {
+            for (name, profile) in profiles {
+                if let Value::Object(profile) = profile {
+                    changes.extend(
+                        (step.apply)(profile)
+                            .into_iter()
+                            .map(|change| format!("profiles.{name}: {change}")),
+                    );
+                }
+            }
+        }
+    }
+    config.insert("version".to_owned(), VERSION.into());
+    Ok((version, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_reach_the_current_version() {
+        for (i, step) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(step.to, i as u64 + 1);
+        }
+        assert_eq!(MIGRATIONS.last().map_or(0, |step| step.to), VERSION);
+    }
+
+    #[test]
+    fn unversioned_configs_get_the_current_version() {
+        let mut config = serde_json::json!({ "scroll_speed": 2 });
+        assert_eq!(migrate(&mut config), Ok((0, Vec::new())));
+        assert_eq!(
+            config,
+            serde_json::json!({ "scroll_speed": 2, "version": VERSION })
+        );
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let mut config = serde_json::json!({ "version": VERSION + 1 });
+        assert!(migrate(&mut config).is_err());
+        let mut config = serde_json::json!({ "version": "2" });
+        assert!(migrate(&mut config).is_err());
+    }
+}
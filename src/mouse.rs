@@ -0,0 +1,334 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use device_query::DeviceState;
+use egui::pos2;
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, InputError, Mouse, Settings};
+use kmgrid::geometry::Desktop;
+
+use crate::uinput::UinputPointer;
+use crate::window;
+
+// Time for the window manager to act on an activation before the click that follows it
+const ACTIVATION_DELAY: Duration = Duration::from_millis(50);
+
+// Moves in a row that leave the pointer where it was before the backend counts as ignored
+const STALLED_MOVES: u32 = 5;
+
+/// How pointer input reaches the display server
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerBackend {
+    /// XTest through enigo
+    #[default]
+    Xtest,
+    /// A virtual absolute device, for VMs and remote viewers that only follow those
+    Uinput,
+}
+
+enum Injector {
+    Enigo(Enigo),
+    Uinput(UinputPointer),
+    Print(PrintOnly),
+}
+
+impl Injector {
+    fn new(backend: PointerBackend, desktop: Desktop) -> Result<Self, String> {
+        match backend {
+            PointerBackend::Xtest => Enigo::new(&Settings::default())
+                .map(Injector::Enigo)
+                .map_err(|err| err.to_string()),
+            PointerBackend::Uinput => UinputPointer::new(desktop).map(Injector::Uinput),
+        }
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), InputError> {
+        match self {
+            Injector::Enigo(enigo) => enigo.move_mouse(x, y, coordinate),
+            Injector::Uinput(uinput) => uinput.move_mouse(x, y, coordinate),
+            Injector::Print(print) => print.move_mouse(x, y, coordinate),
+        }
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), InputError> {
+        match self {
+            Injector::Enigo(enigo) => enigo.button(button, direction),
+            Injector::Uinput(uinput) => uinput.button(button, direction),
+            Injector::Print(print) => print.button(button, direction),
+        }
+    }
+
+    fn scroll(&mut self, amount: i32, axis: Axis) -> Result<(), InputError> {
+        match self {
+            Injector::Enigo(enigo) => enigo.scroll(amount, axis),
+            Injector::Uinput(uinput) => uinput.scroll(amount, axis),
+            Injector::Print(_) => Ok(()),
+        }
+    }
+
+    // Wheel events through XTest can sit in the X connection until other input follows, an empty
+    // pointer motion flushes them. uinput events are flushed with every write.
+    fn flush(&mut self) -> Result<(), InputError> {
+        match self {
+            Injector::Enigo(enigo) => enigo.move_mouse(0, 0, Coordinate::Rel),
+            Injector::Uinput(_) | Injector::Print(_) => Ok(()),
+        }
+    }
+}
+
+/// Stands in when no backend starts, e.g. without XTest or without access to `/dev/uinput`.
+/// Nothing reaches the display server, clicks print where they would have landed instead.
+struct PrintOnly {
+    device_state: DeviceState,
+    // Where the moves so far would have put the pointer
+    at: Option<(i32, i32)>,
+}
+
+impl PrintOnly {
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), InputError> {
+        self.at = Some(match coordinate {
+            Coordinate::Abs => (x, y),
+            Coordinate::Rel => {
+                let (px, py) = self.position();
+                (px + x, py + y)
+            }
+        });
+        Ok(())
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), InputError> {
+        if direction != Direction::Release {
+            let (x, y) = self.position();
+            println!("{button:?} {direction:?} at {x},{y}");
+        }
+        Ok(())
+    }
+
+    fn position(&self) -> (i32, i32) {
+        self.at
+            .unwrap_or_else(|| self.device_state.query_pointer().coords)
+    }
+}
+
+enum Command {
+    Move(i32, i32, Coordinate),
+    Button(Button, Direction),
+    Scroll(i32, Axis),
+    ActivateWindow,
+    Pause(Duration),
+}
+
+/// Sends pointer input from a worker thread. Some XTest servers block for a while on injected
+/// input, which must not freeze the overlay, so calls only queue the input and failures are
+/// picked up later with `errors`.
+pub struct MouseWorker {
+    commands: Sender<Command>,
+    errors: Receiver<InputError>,
+    stalls: Receiver<()>,
+    // Why the backend didn't start, leaving only `PrintOnly`
+    failure: Option<String>,
+}
+
+impl MouseWorker {
+    /// `desktop` spans every display, absolute devices cover all of it. When the backend doesn't
+    /// start, clicks only print where they would land, see `failure`.
+    pub fn spawn(backend: PointerBackend, desktop: Desktop) -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let (error_tx, errors) = mpsc::channel();
+        let (stall_tx, stalls) = mpsc::channel();
+        let (init_tx, init_rx) = mpsc::channel();
+
+        // The backend stays on the worker thread, the connection it holds isn't meant to be shared
+        std::thread::spawn(move || {
+            let mut injector = match Injector::new(backend, desktop) {
+                Ok(injector) => {
+                    let _ = init_tx.send(None);
+                    injector
+                }
+                Err(err) => {
+                    let _ = init_tx.send(Some(err));
+                    Injector::Print(PrintOnly {
+                        device_state: DeviceState::new(),
+                        at: None,
+                    })
+                }
+            };
+            let mut watchdog = Watchdog::new(desktop, stall_tx);
+
+            // Runs until the overlay drops the worker, finishing input queued before that
+            while let Ok(first) = command_rx.recv() {
+                let batch: Vec<_> = std::iter::once(first)
+                    .chain(command_rx.try_iter())
+                    .collect();
+                for result in run_batch(&mut injector, &mut watchdog, coalesce(batch)) {
+                    if let Err(err) = result {
+                        let _ = error_tx.send(err);
+                    }
+                }
+            }
+        });
+
+        let failure = init_rx
+            .recv()
+            .unwrap_or(Some("Mouse worker thread died".to_owned()));
+        MouseWorker {
+            commands,
+            errors,
+            stalls,
+            failure,
+        }
+    }
+
+    /// Why no pointer input reaches the display server, if it doesn't
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    pub fn move_mouse(&self, x: i32, y: i32, coordinate: Coordinate) {
+        self.send(Command::Move(x, y, coordinate));
+    }
+
+    pub fn button(&self, button: Button, direction: Direction) {
+        self.send(Command::Button(button, direction));
+    }
+
+    pub fn scroll(&self, amount: i32, axis: Axis) {
+        self.send(Command::Scroll(amount, axis));
+    }
+
+    /// Focuses and raises the window under the pointer, once the input queued before it is in
+    pub fn activate_window(&self) {
+        self.send(Command::ActivateWindow);
+    }
+
+    /// Holds back the input queued after this for `duration`, e.g. between the clicks of a
+    /// double click
+    pub fn pause(&self, duration: Duration) {
+        self.send(Command::Pause(duration));
+    }
+
+    /// Failures of the input sent so far
+    pub fn errors(&self) -> Vec<InputError> {
+        self.errors.try_iter().collect()
+    }
+
+    /// True once, when moves stop having any effect on the pointer. Backends the display server
+    /// ignores fail that way rather than with an error, XTest on Wayland mostly.
+    pub fn stalled(&self) -> bool {
+        self.stalls.try_recv().is_ok()
+    }
+
+    fn send(&self, command: Command) {
+        // The worker only stops once this sender is dropped
+        let _ = self.commands.send(command);
+    }
+}
+
+// Relative moves that queued up back to back while the worker was busy become one move, so a
+// slow server doesn't get flooded with tiny steps to catch up on
+fn coalesce(batch: Vec<Command>) -> Vec<Command> {
+    let mut merged: Vec<Command> = Vec::with_capacity(batch.len());
+    for command in batch {
+        if let (
+            Some(Command::Move(x, y, Coordinate::Rel)),
+            Command::Move(dx, dy, Coordinate::Rel),
+        ) = (merged.last_mut(), &command)
+        {
+            *x += dx;
+            *y += dy;
+            continue;
+        }
+        merged.push(command);
+    }
+    merged
+}
+
+// Checks that moves move the pointer. Each move is judged when the next one comes in, giving the
+// display server time to act on it, and only if it should have changed the position: a move past
+// the edge of the screen doesn't.
+struct Watchdog {
+    device_state: DeviceState,
+    desktop: Desktop,
+    // Where the pointer was before the last move that should have changed it
+    moved_from: Option<(i32, i32)>,
+    stalled_moves: u32,
+    stalls: Option<Sender<()>>,
+}
+
+impl Watchdog {
+    fn new(desktop: Desktop, stalls: Sender<()>) -> Self {
+        Watchdog {
+            device_state: DeviceState::new(),
+            desktop,
+            moved_from: None,
+            stalled_moves: 0,
+            stalls: Some(stalls),
+        }
+    }
+
+    fn before_move(&mut self, x: i32, y: i32, coordinate: Coordinate) {
+        let (px, py) = self.device_state.query_pointer().coords;
+        match self.moved_from.take() {
+            Some(from) if from == (px, py) => self.stalled_moves += 1,
+            Some(_) => self.stalled_moves = 0,
+            None => {}
+        }
+        if self.stalled_moves >= STALLED_MOVES {
+            // Reported once, the overlay keeps the message up
+            if let Some(stalls) = self.stalls.take() {
+                let _ = stalls.send(());
+            }
+        }
+
+        let (x, y) = match coordinate {
+            Coordinate::Abs => (x, y),
+            Coordinate::Rel => (px + x, py + y),
+        };
+        let target = self.desktop.clamp(pos2(x as f32, y as f32));
+        if (target.x as i32, target.y as i32) != (px, py) {
+            self.moved_from = Some((px, py));
+        }
+    }
+}
+
+fn run_batch(
+    injector: &mut Injector,
+    watchdog: &mut Watchdog,
+    batch: Vec<Command>,
+) -> Vec<Result<(), InputError>> {
+    let mut scrolled = false;
+    let mut results: Vec<_> = batch
+        .into_iter()
+        .map(|command| match command {
+            Command::Move(0, 0, Coordinate::Rel) => Ok(()),
+            Command::Move(x, y, coordinate) => {
+                // Printing never moves the pointer, that's no stall
+                if !matches!(injector, Injector::Print(_)) {
+                    watchdog.before_move(x, y, coordinate);
+                }
+                injector.move_mouse(x, y, coordinate)
+            }
+            Command::Button(button, direction) => injector.button(button, direction),
+            Command::Scroll(amount, axis) => {
+                scrolled = true;
+                injector.scroll(amount, axis)
+            }
+            Command::ActivateWindow => {
+                if window::activate_window_under_pointer() {
+                    std::thread::sleep(ACTIVATION_DELAY);
+                }
+                Ok(())
+            }
+            Command::Pause(duration) => {
+                std::thread::sleep(duration);
+                Ok(())
+            }
+        })
+        .collect();
+
+    // Once per batch is enough
+    if scrolled {
+        results.push(injector.flush());
+    }
+    results
+}
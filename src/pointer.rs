@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use device_query::DeviceState;
+use egui::{pos2, Pos2};
+
+/// Polls the physical pointer position on a background thread, so querying the X server doesn't
+/// hold up rendering. Polling stops when the tracker is dropped.
+pub struct PointerTracker {
+    position: Arc<Mutex<Pos2>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl PointerTracker {
+    pub fn spawn(interval: Duration) -> Self {
+        let device_state = DeviceState::new();
+        let position = Arc::new(Mutex::new(query(&device_state)));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let latest = position.clone();
+        let stopped = stop.clone();
+        std::thread::spawn(move || {
+            let device_state = DeviceState::new();
+            while !stopped.load(Ordering::Relaxed) {
+                *latest.lock().unwrap() = query(&device_state);
+                std::thread::sleep(interval);
+            }
+        });
+
+        PointerTracker { position, stop }
+    }
+
+    /// Where the pointer was at the last poll, at most one interval ago
+    pub fn position(&self) -> Pos2 {
+        *self.position.lock().unwrap()
+    }
+}
+
+impl Drop for PointerTracker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn query(device_state: &DeviceState) -> Pos2 {
+    let (x, y) = device_state.query_pointer().coords;
+    pos2(x as f32, y as f32)
+}
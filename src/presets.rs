@@ -0,0 +1,295 @@
+use kmgrid::geometry::GridSize;
+use serde_json::Value;
+
+use crate::action::split_modifiers;
+use crate::config;
+
+/// Keyboard layout the built-in bindings are laid out for. Every preset puts the keys on the same
+/// spots as the QWERTY defaults, home row and all, named after what that layout types there.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    #[default]
+    Qwerty,
+    Colemak,
+    Dvorak,
+    /// French AZERTY
+    Azerty,
+    /// Every binding within reach of the left hand on a QWERTY keyboard
+    LeftHand,
+    /// The left hand preset mirrored, for the right hand
+    RightHand,
+}
+
+// What each layout types on the keys the QWERTY defaults use, by their US names. Keys missing
+// from a table stay as they are.
+const COLEMAK: &[(&str, &str)] = &[
+    ("E", "F"),
+    ("R", "P"),
+    ("T", "G"),
+    ("Y", "J"),
+    ("U", "L"),
+    ("I", "U"),
+    ("O", "Y"),
+    ("P", ";"),
+    ("S", "R"),
+    ("D", "S"),
+    ("F", "T"),
+    ("G", "D"),
+    ("J", "N"),
+    ("K", "E"),
+    ("L", "I"),
+    (";", "O"),
+    ("N", "K"),
+];
+
+const DVORAK: &[(&str, &str)] = &[
+    ("Q", "'"),
+    ("W", ","),
+    ("E", "."),
+    ("R", "P"),
+    ("T", "Y"),
+    ("Y", "F"),
+    ("U", "G"),
+    ("I", "C"),
+    ("O", "R"),
+    ("P", "L"),
+    ("S", "O"),
+    ("D", "E"),
+    ("F", "U"),
+    ("G", "I"),
+    ("H", "D"),
+    ("J", "H"),
+    ("K", "T"),
+    ("L", "N"),
+    (";", "S"),
+    ("V", "K"),
+    ("N", "B"),
+    (",", "W"),
+    (".", "V"),
+    ("/", "Z"),
+];
+
+// The number row types symbols without Shift, which egui only knows by the digit on the key, and
+// the same goes for `!` next to the right Shift, so those keep their US names
+const AZERTY: &[(&str, &str)] = &[
+    ("Q", "A"),
+    ("W", "Z"),
+    ("A", "Q"),
+    (";", "M"),
+    ("M", ","),
+    (",", ";"),
+    (".", ":"),
+];
+
+// Keys of a US keyboard and their mirror images across its middle, each pair once
+const MIRROR: &[(&str, &str)] = &[
+    ("1", "0"),
+    ("2", "9"),
+    ("3", "8"),
+    ("4", "7"),
+    ("5", "6"),
+    ("Q", "P"),
+    ("W", "O"),
+    ("E", "I"),
+    ("R", "U"),
+    ("T", "Y"),
+    ("A", ";"),
+    ("S", "L"),
+    ("D", "K"),
+    ("F", "J"),
+    ("G", "H"),
+    ("Z", "/"),
+    ("X", "."),
+    ("C", ","),
+    ("V", "M"),
+    ("B", "N"),
+    ("Tab", "Enter"),
+];
+
+// Bindings that go left or right, which swap places when mirroring
+const LEFT_RIGHT: &[(&str, &str)] = &[
+    ("move_left", "move_right"),
+    ("warp_left", "warp_right"),
+    ("scroll_left", "scroll_right"),
+];
+
+impl Layout {
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Layout::Qwerty | Layout::LeftHand | Layout::RightHand => &[],
+            Layout::Colemak => COLEMAK,
+            Layout::Dvorak => DVORAK,
+            Layout::Azerty => AZERTY,
+        }
+    }
+
+    /// The default `key_bindings` for this layout, to go between the built-in defaults and the
+    /// config file
+    pub fn key_bindings(self) -> Value {
+        let mut bindings = config::defaults()["key_bindings"].take();
+        match self {
+            Layout::LeftHand | Layout::RightHand => {
+                let left_hand: Value =
+                    serde_json::from_str(include_str!("../resources/left_hand_bindings.json"))
+                        .expect("The left hand bindings are valid JSON");
+                config::merge(&mut bindings, left_hand);
+                if self == Layout::RightHand {
+                    let grid = GridSize::default();
+                    mirror(&mut bindings, grid.regions[0], grid.cells[0]);
+                }
+            }
+            _ => translate(&mut bindings, self.table()),
+        }
+        bindings
+    }
+}
+
+/// Flips `key_bindings` left to right: every key to its mirror image across the middle of a US
+/// keyboard, the rows of `region` and `grid` reversed so the keys still sit like the regions and
+/// cells they pick, and left and right swapped. `mirror_layout` itself stays, to flip back with.
+pub fn mirror(bindings: &mut Value, region_cols: usize, cell_cols: usize) {
+    let toggle = bindings.get("mirror_layout").cloned();
+    let table: Vec<_> = MIRROR.iter().flat_map(|&(a, b)| [(a, b), (b, a)]).collect();
+    translate(bindings, &table);
+    if let Some(toggle) = toggle {
+        bindings["mirror_layout"] = toggle;
+    }
+
+    for (list, cols) in [("region", region_cols), ("grid", cell_cols)] {
+        if let Some(keys) = bindings[list].as_array_mut() {
+            keys.chunks_mut(cols.max(1)).for_each(<[Value]>::reverse);
+        }
+    }
+    if let Some(mouse) = bindings["mouse"].as_object_mut() {
+        for (left, right) in LEFT_RIGHT {
+            let left_keys = mouse.remove(*left);
+            let right_keys = mouse.remove(*right);
+            if let Some(keys) = right_keys {
+                mouse.insert(left.to_string(), keys);
+            }
+            if let Some(keys) = left_keys {
+                mouse.insert(right.to_string(), keys);
+            }
+        }
+    }
+}
+
+// Swaps keys by `table`, keeping any modifiers in front of them
+fn translate(value: &mut Value, table: &[(&str, &str)]) {
+    match value {
+        Value::String(name) => {
+            let (_, key) = split_modifiers(name);
+            if let Some((_, to)) = table.iter().find(|(from, _)| *from == key) {
+                let prefix = &name[..name.len() - key.len()];
+                *name = format!("{prefix}{to}");
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| translate(v, table)),
+        Value::Object(values) => values.values_mut().for_each(|v| translate(v, table)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use egui::Key;
+
+    const LAYOUTS: [Layout; 6] = [
+        Layout::Qwerty,
+        Layout::Colemak,
+        Layout::Dvorak,
+        Layout::Azerty,
+        Layout::LeftHand,
+        Layout::RightHand,
+    ];
+
+    #[test]
+    fn every_preset_has_unique_parseable_keys() {
+        for layout in LAYOUTS {
+            let bindings = layout.key_bindings();
+            let keys = |value: &Value| -> Vec<Key> {
+                let names: Vec<_> = match value {
+                    Value::Array(names) => names.iter().collect(),
+                    Value::Object(names) => names.values().collect(),
+                    name => vec![name],
+                };
+                names
+                    .into_iter()
+                    .map(|name| {
+                        let name = name.as_str().unwrap();
+                        Key::from_name(name)
+                            .unwrap_or_else(|| panic!("{layout:?}: unknown key {name:?}"))
+                    })
+                    .collect()
+            };
+            for list in ["region", "grid", "mouse"] {
+                let keys = keys(&bindings[list]);
+                for (i, key) in keys.iter().enumerate() {
+                    assert!(
+                        !keys[..i].contains(key),
+                        "{layout:?}: {key:?} is in {list} twice"
+                    );
+                }
+            }
+            for name in ["skip_to_cell", "prev_screen", "next_screen"] {
+                keys(&bindings[name]);
+            }
+        }
+    }
+
+    #[test]
+    fn one_hand_presets_stay_on_their_side() {
+        let left: Vec<_> = MIRROR.iter().map(|(left, _)| *left).collect();
+        let right: Vec<_> = MIRROR.iter().map(|(_, right)| *right).collect();
+        for (layout, side) in [(Layout::LeftHand, left), (Layout::RightHand, right)] {
+            let bindings = layout.key_bindings();
+            let mut names: Vec<&Value> = ["skip_to_cell", "prev_screen", "next_screen"]
+                .iter()
+                .map(|name| &bindings[name])
+                .collect();
+            names.extend(bindings["region"].as_array().unwrap());
+            names.extend(bindings["grid"].as_array().unwrap());
+            names.extend(bindings["mouse"].as_object().unwrap().values());
+            for name in names {
+                let name = name.as_str().unwrap();
+                assert!(
+                    name == "Space" || side.contains(&name),
+                    "{layout:?}: {name} is out of reach"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mirroring_twice_changes_nothing() {
+        let mut bindings = Layout::Qwerty.key_bindings();
+        bindings["mirror_layout"] = "ctrl+M".into();
+        bindings["back_to_screen"] = "ctrl+Q".into();
+        let original = bindings.clone();
+
+        mirror(&mut bindings, 4, 5);
+        assert_eq!(bindings["mouse"]["move_left"], "S");
+        assert_eq!(bindings["mouse"]["move_up"], "E");
+        assert_eq!(bindings["region"][0], "U");
+        assert_eq!(bindings["back_to_screen"], "ctrl+P");
+        assert_eq!(bindings["mirror_layout"], "ctrl+M");
+
+        mirror(&mut bindings, 4, 5);
+        assert_eq!(bindings, original);
+    }
+
+    #[test]
+    fn every_preset_is_a_valid_config() {
+        for layout in LAYOUTS {
+            let mut config = config::defaults();
+            config["layout"] = serde_json::to_value(layout).unwrap();
+            config["key_bindings"] = layout.key_bindings();
+            if let Err(problems) = crate::check_config(config) {
+                panic!("{layout:?}: {problems:?}");
+            }
+        }
+    }
+}
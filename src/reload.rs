@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::config;
+
+/// Counts the changes to the config file and the files it includes, so the overlay and the loop
+/// that reopens it can each tell whether there's a newer config than the one they run with
+#[derive(Clone)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    generation: Arc<AtomicU64>,
+    // The config file and its includes, shared with the watcher's callback
+    files: Arc<Mutex<HashSet<PathBuf>>>,
+    // Stops watching once the last clone is gone
+    watcher: Arc<Mutex<Watched>>,
+}
+
+struct Watched {
+    watcher: RecommendedWatcher,
+    dirs: HashSet<PathBuf>,
+}
+
+impl ConfigWatcher {
+    pub fn spawn(path: &Path) -> notify::Result<Self> {
+        let path = std::path::absolute(path)?;
+        let generation = Arc::new(AtomicU64::new(0));
+        let files = Arc::new(Mutex::new(HashSet::new()));
+        let (counter, watched_files) = (generation.clone(), files.clone());
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            let files = watched_files.lock().unwrap();
+            let ours = event.paths.iter().any(|changed| files.contains(changed));
+            if ours && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        })?;
+        let config_watcher = ConfigWatcher {
+            path,
+            generation,
+            files,
+            watcher: Arc::new(Mutex::new(Watched {
+                watcher,
+                dirs: HashSet::new(),
+            })),
+        };
+        config_watcher.watch(&config_watcher.path)?;
+        config_watcher.refresh();
+        Ok(config_watcher)
+    }
+
+    /// Follows the includes of the config file again, after a reload may have changed them
+    pub fn refresh(&self) {
+        let files: HashSet<PathBuf> = config::files(&self.path)
+            .into_iter()
+            .filter_map(|file| std::path::absolute(file).ok())
+            .collect();
+        for file in &files {
+            if let Err(err) = self.watch(file) {
+                eprintln!("Not reloading on changes to {}: {err}", file.display());
+            }
+        }
+        *self.files.lock().unwrap() = files;
+    }
+
+    // Editors often save by writing a new file and renaming it over the old one, which a watch on
+    // the file itself would lose track of, so this watches the directory it's in
+    fn watch(&self, file: &Path) -> notify::Result<()> {
+        let dir = file.parent().unwrap_or(Path::new("/"));
+        let mut watched = self.watcher.lock().unwrap();
+        if !watched.dirs.contains(dir) {
+            watched.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            watched.dirs.insert(dir.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Goes up with every change to the files
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+}
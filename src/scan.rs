@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use crate::action::Action;
+use crate::i18n::Msg;
+use crate::Mode;
+
+/// What switch scanning steps through in Cell mode, once the pointer is in place
+pub const CELL_ACTIONS: [(Action, Msg); 4] = [
+    (Action::LeftClickAndExit, Msg::ScanLeftClick),
+    (Action::RightClick, Msg::ScanRightClick),
+    (Action::MiddleClick, Msg::ScanMiddleClick),
+    (Action::ShowNarrow, Msg::ScanBack),
+];
+
+/// Switch scanning: highlights regions, then cells, then click actions one after another, so
+/// everything can be reached with a single `scan_select` key.
+pub struct Scanner {
+    interval: Duration,
+    started: Instant,
+    mode: Mode,
+}
+
+impl Scanner {
+    pub fn new(interval: Duration, mode: Mode) -> Self {
+        Scanner {
+            interval,
+            started: Instant::now(),
+            mode,
+        }
+    }
+
+    /// Starts over from the first item whenever the mode changes
+    pub fn sync(&mut self, mode: Mode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.started = Instant::now();
+        }
+    }
+
+    /// Index of the highlighted item out of `count`
+    pub fn index(&self, count: usize) -> usize {
+        let steps = self.started.elapsed().as_millis() / self.interval.as_millis().max(1);
+        (steps % count.max(1) as u128) as usize
+    }
+}
@@ -0,0 +1,39 @@
+/// A named spot on the screen, in screen coordinates, that the search prompt can jump to
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Target {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Targets whose name contains the letters of `query` in order, best match first. Letters that
+/// follow each other or start a word make a better match, skipped letters a worse one.
+pub fn matches<'a>(targets: &'a [Target], query: &str) -> Vec<&'a Target> {
+    let mut scored: Vec<_> = targets
+        .iter()
+        .filter_map(|t| score(&t.name, query).map(|s| (s, t)))
+        .collect();
+    // Stable, so equally good matches stay in config order
+    scored.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    scored.into_iter().map(|(_, t)| t).collect()
+}
+
+fn score(name: &str, query: &str) -> Option<i32> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + name[next..].iter().position(|c| *c == q)?;
+        if last.is_some_and(|l| found == l + 1) {
+            score += 3;
+        }
+        if found == 0 || !name[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        score -= (found - next) as i32;
+        last = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use egui::Vec2;
+
+// Used when a display doesn't report its physical size
+const FALLBACK_PIXELS_PER_MM: f32 = 96.0 / 25.4;
+
+/// How fast held movement keys move the pointer. A plain number is pixels per frame, strings
+/// like `"40mm/s"` or `"25%/s"` (of the display width) give the same feel on every display.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "JsonSpeed", into = "JsonSpeed")]
+pub enum Speed {
+    PixelsPerFrame(f32),
+    MillimetersPerSecond(f32),
+    ScreenPercentPerSecond(f32),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum JsonSpeed {
+    Pixels(f32),
+    Unit(String),
+}
+
+impl TryFrom<JsonSpeed> for Speed {
+    type Error = String;
+
+    fn try_from(speed: JsonSpeed) -> Result<Self, Self::Error> {
+        match speed {
+            JsonSpeed::Pixels(px) => Ok(Speed::PixelsPerFrame(px)),
+            JsonSpeed::Unit(s) => s.parse(),
+        }
+    }
+}
+
+impl From<Speed> for JsonSpeed {
+    fn from(speed: Speed) -> Self {
+        match speed {
+            Speed::PixelsPerFrame(px) => JsonSpeed::Pixels(px),
+            Speed::MillimetersPerSecond(v) => JsonSpeed::Unit(format!("{v}mm/s")),
+            Speed::ScreenPercentPerSecond(v) => JsonSpeed::Unit(format!("{v}%/s")),
+        }
+    }
+}
+
+impl std::str::FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (value, unit): (&str, fn(f32) -> Speed) = if let Some(v) = s.strip_suffix("mm/s") {
+            (v, Speed::MillimetersPerSecond)
+        } else if let Some(v) = s.strip_suffix("%/s") {
+            (v, Speed::ScreenPercentPerSecond)
+        } else if let Some(v) = s.strip_suffix("px") {
+            (v, Speed::PixelsPerFrame)
+        } else {
+            return Err(format!("Unknown speed unit in {s}, use px, mm/s or %/s"));
+        };
+        value
+            .trim()
+            .parse()
+            .map(unit)
+            .map_err(|_| format!("Invalid speed {s}"))
+    }
+}
+
+impl Speed {
+    /// Pixels to move this frame on a display of `size` pixels and `size_mm` millimeters, where
+    /// the last frame took `dt` seconds
+    pub fn pixels_per_frame(self, size: Vec2, size_mm: Vec2, dt: f32) -> f32 {
+        match self {
+            Speed::PixelsPerFrame(px) => px,
+            Speed::MillimetersPerSecond(mm) => {
+                let pixels_per_mm = if size_mm.x > 0.0 {
+                    size.x / size_mm.x
+                } else {
+                    FALLBACK_PIXELS_PER_MM
+                };
+                mm * pixels_per_mm * dt
+            }
+            Speed::ScreenPercentPerSecond(pct) => pct / 100.0 * size.x * dt,
+        }
+    }
+}
+
+/// Held movement keys starting out slow for precision and picking up speed: `initial` at first,
+/// growing to `max` over `ramp_ms` of holding them
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Acceleration {
+    pub initial: Speed,
+    pub max: Speed,
+    pub ramp_ms: u64,
+}
+
+impl Acceleration {
+    /// How far along the ramp movement keys held for `held` seconds are, from 0 to 1
+    pub fn progress(&self, held: f32) -> f32 {
+        ramp(held * 1000.0, self.ramp_ms)
+    }
+}
+
+/// Held scroll keys picking up speed for long documents: `scroll_speed` steps per frame at first,
+/// growing to `max` over `ramp_ms` of holding them
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ScrollAcceleration {
+    pub max: i32,
+    pub ramp_ms: u64,
+}
+
+impl ScrollAcceleration {
+    /// Scroll steps per frame after holding scroll keys for `held` seconds
+    pub fn steps(&self, initial: i32, held: f32) -> f32 {
+        let t = ramp(held * 1000.0, self.ramp_ms);
+        initial as f32 + (self.max - initial) as f32 * t
+    }
+}
+
+/// Moving and scrolling picking up speed the longer the overlay stays in Cell mode, instead of
+/// chording the speed modifiers: the usual speed at first, `max_factor` times that after `ramp_ms`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CellSpeedup {
+    pub ramp_ms: u64,
+    pub max_factor: f32,
+}
+
+impl CellSpeedup {
+    /// What speeds get multiplied by after `in_cell` in Cell mode
+    pub fn factor(&self, in_cell: Duration) -> f32 {
+        let t = ramp(in_cell.as_millis() as f32, self.ramp_ms);
+        1.0 + (self.max_factor - 1.0) * t
+    }
+}
+
+// How far along a ramp of `ramp_ms` the elapsed time is, from 0 to 1
+fn ramp(elapsed_ms: f32, ramp_ms: u64) -> f32 {
+    if ramp_ms == 0 {
+        return 1.0;
+    }
+    (elapsed_ms / ramp_ms as f32).min(1.0)
+}
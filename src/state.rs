@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use egui::{pos2, Pos2};
+
+/// What a run of kmgrid leaves for the next one, in a file under `$XDG_RUNTIME_DIR`, which is
+/// cleared on logout: a left button kmgrid pressed and didn't release so far, so a crash or an
+/// overlay closed mid-drag can't leave it stuck for good, and the position history.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct RuntimeState {
+    // Seconds since the Unix epoch at which kmgrid pressed the left button
+    #[serde(default)]
+    left_button_held_since: Option<u64>,
+    // Oldest first, see `PositionHistory`
+    #[serde(default)]
+    positions: Vec<[f32; 2]>,
+}
+
+fn path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("kmgrid-state.json"),
+        // SAFETY: getuid has no preconditions and can't fail
+        None => {
+            std::env::temp_dir().join(format!("kmgrid-{}-state.json", unsafe { libc::getuid() }))
+        }
+    }
+}
+
+fn read() -> RuntimeState {
+    std::fs::read_to_string(path())
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write(state: &RuntimeState) {
+    let path = path();
+    let text = serde_json::to_string(state).unwrap();
+    if let Err(err) = std::fs::write(&path, text) {
+        eprintln!("Unable to write {}: {err}", path.display());
+    }
+}
+
+/// Notes that kmgrid pressed the left button and is leaving it down
+pub fn record_left_button_held() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut state = read();
+    state.left_button_held_since = Some(now);
+    write(&state);
+}
+
+/// Notes that the left button is up again
+pub fn record_left_button_released() {
+    let mut state = read();
+    if state.left_button_held_since.take().is_some() {
+        write(&state);
+    }
+}
+
+/// How long ago kmgrid pressed the left button, if it's still recorded as held
+pub fn left_button_held_for() -> Option<Duration> {
+    let since = UNIX_EPOCH + Duration::from_secs(read().left_button_held_since?);
+    Some(SystemTime::now().duration_since(since).unwrap_or_default())
+}
+
+/// The position history the last run left
+pub fn positions() -> Vec<Pos2> {
+    read().positions.iter().map(|[x, y]| pos2(*x, *y)).collect()
+}
+
+pub fn record_positions(positions: impl Iterator<Item = Pos2>) {
+    let mut state = read();
+    state.positions = positions.map(|pos| [pos.x, pos.y]).collect();
+    write(&state);
+}
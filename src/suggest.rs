@@ -0,0 +1,27 @@
+use egui::Key;
+
+/// Rebinds `keys` so the most used slots get the easy keys. `hits` counts the uses of each slot,
+/// `easy` lists the easiest keys first. Easy keys are swapped in, so the result binds the same
+/// keys and can't clash with other bindings, and slots that never got used mostly keep their key.
+/// Easy keys that aren't among `keys` are skipped.
+pub fn suggest(hits: &[u32], keys: &[Key], easy: &[Key]) -> Vec<Key> {
+    let mut used: Vec<usize> = (0..keys.len())
+        .filter(|&i| hits.get(i).is_some_and(|&n| n > 0))
+        .collect();
+    // Stable, so ties keep the slot order
+    used.sort_by_key(|&i| std::cmp::Reverse(hits[i]));
+
+    let mut easy_keys: Vec<Key> = Vec::new();
+    for key in easy {
+        if keys.contains(key) && !easy_keys.contains(key) {
+            easy_keys.push(*key);
+        }
+    }
+
+    let mut suggested = keys.to_vec();
+    for (slot, key) in used.into_iter().zip(easy_keys) {
+        let current = suggested.iter().position(|k| *k == key).unwrap();
+        suggested.swap(slot, current);
+    }
+    suggested
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use egui::{vec2, Align2, Color32, FontId, Painter, Rect, Rounding, Stroke};
+
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Short-lived messages drawn on top of the overlay. kmgrid is usually launched from a WM
+/// keybinding, so anything printed to stdout never reaches the user.
+#[derive(Default)]
+pub struct Toasts {
+    messages: Vec<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        println!("{message}");
+        self.messages
+            .push((message, Instant::now() + TOAST_DURATION));
+    }
+
+    /// Draws the pending messages stacked up from the bottom of `area`, dropping expired ones
+    pub fn draw(&mut self, painter: &Painter, area: Rect) {
+        let now = Instant::now();
+        self.messages.retain(|(_, expiry)| *expiry > now);
+
+        let font = FontId::proportional(18.0);
+        let mut bottom = area.center_bottom() - vec2(0.0, 40.0);
+        for (message, _) in self.messages.iter().rev() {
+            let galley = painter.layout_no_wrap(message.clone(), font.clone(), Color32::WHITE);
+            let rect = Align2::CENTER_BOTTOM
+                .anchor_size(bottom, galley.size())
+                .expand(8.0);
+
+            painter.rect(
+                rect,
+                Rounding::same(4.0),
+                Color32::from_rgba_unmultiplied(20, 20, 20, 220),
+                Stroke::new(1.0, Color32::from_rgb(172, 38, 26)),
+            );
+            painter.galley(rect.shrink(8.0).min, galley, Color32::WHITE);
+
+            bottom.y = rect.top() - 8.0;
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+
+use device_query::DeviceState;
+use egui::pos2;
+use enigo::{Axis, Button, Coordinate, Direction, InputError};
+use kmgrid::geometry::Desktop;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+// The thumb buttons, which X and libinput take for back and forward
+const BTN_SIDE: u16 = 0x113;
+const BTN_EXTRA: u16 = 0x114;
+const INPUT_PROP_POINTER: u16 = 0x00;
+
+// From linux/uinput.h, for the architectures where `_IOW` uses the generic layout
+const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+const UI_SET_EVBIT: libc::c_ulong = 0x4004_5564;
+const UI_SET_KEYBIT: libc::c_ulong = 0x4004_5565;
+const UI_SET_RELBIT: libc::c_ulong = 0x4004_5566;
+const UI_SET_ABSBIT: libc::c_ulong = 0x4004_5567;
+const UI_SET_PROPBIT: libc::c_ulong = 0x4004_556e;
+
+const ABS_CNT: usize = 0x40;
+const DEVICE_NAME: &[u8] = b"kmgrid absolute pointer";
+
+// `struct uinput_user_dev`, the legacy setup that every kernel with uinput understands
+#[repr(C)]
+struct UinputUserDev {
+    name: [u8; 80],
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+    ff_effects_max: u32,
+    absmax: [i32; ABS_CNT],
+    absmin: [i32; ABS_CNT],
+    absfuzz: [i32; ABS_CNT],
+    absflat: [i32; ABS_CNT],
+}
+
+/// A virtual absolute pointer, like a graphics tablet. VMs and remote viewers that ignore
+/// relative and XTest input still follow these. The device spans the whole desktop.
+pub struct UinputPointer {
+    file: File,
+    device_state: DeviceState,
+    desktop: Desktop,
+}
+
+impl UinputPointer {
+    pub fn new(desktop: Desktop) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .write(true)
+            .open("/dev/uinput")
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::PermissionDenied => format!(
+                    "No permission to open /dev/uinput ({err}). Add a udev rule giving your user \
+                     access, e.g. KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\", and join the \
+                     input group."
+                ),
+                io::ErrorKind::NotFound => {
+                    format!("/dev/uinput is missing ({err}), load the uinput kernel module")
+                }
+                _ => format!("Unable to open /dev/uinput: {err}"),
+            })?;
+
+        let setup = |request, value: u16| {
+            // SAFETY: the uinput setup requests take a plain int argument
+            match unsafe { libc::ioctl(file.as_raw_fd(), request, libc::c_int::from(value)) } {
+                -1 => Err(format!(
+                    "Unable to set up the uinput device: {}",
+                    io::Error::last_os_error()
+                )),
+                _ => Ok(()),
+            }
+        };
+        for ev in [EV_SYN, EV_KEY, EV_REL, EV_ABS] {
+            setup(UI_SET_EVBIT, ev)?;
+        }
+        for key in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE, BTN_SIDE, BTN_EXTRA] {
+            setup(UI_SET_KEYBIT, key)?;
+        }
+        for rel in [REL_WHEEL, REL_HWHEEL] {
+            setup(UI_SET_RELBIT, rel)?;
+        }
+        for abs in [ABS_X, ABS_Y] {
+            setup(UI_SET_ABSBIT, abs)?;
+        }
+        setup(UI_SET_PROPBIT, INPUT_PROP_POINTER)?;
+
+        let mut dev = UinputUserDev {
+            name: [0; 80],
+            bustype: 0x06, // BUS_VIRTUAL
+            vendor: 0,
+            product: 0,
+            version: 1,
+            ff_effects_max: 0,
+            absmax: [0; ABS_CNT],
+            absmin: [0; ABS_CNT],
+            absfuzz: [0; ABS_CNT],
+            absflat: [0; ABS_CNT],
+        };
+        dev.name[..DEVICE_NAME.len()].copy_from_slice(DEVICE_NAME);
+        let size = desktop.rect().size();
+        dev.absmax[ABS_X as usize] = size.x as i32 - 1;
+        dev.absmax[ABS_Y as usize] = size.y as i32 - 1;
+
+        // SAFETY: `UinputUserDev` is plain old data with the kernel's layout
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &dev as *const UinputUserDev as *const u8,
+                std::mem::size_of::<UinputUserDev>(),
+            )
+        };
+        let mut pointer = UinputPointer {
+            file,
+            device_state: DeviceState::new(),
+            desktop,
+        };
+        pointer
+            .file
+            .write_all(bytes)
+            .map_err(|err| format!("Unable to set up the uinput device: {err}"))?;
+        setup_device(&pointer.file, UI_DEV_CREATE)?;
+
+        // The display server takes a moment to pick up the new device
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        Ok(pointer)
+    }
+
+    pub fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), InputError> {
+        let (x, y) = match coordinate {
+            Coordinate::Abs => (x, y),
+            // Absolute devices can't move by an offset, so start from where the pointer is
+            Coordinate::Rel => {
+                let (px, py) = self.device_state.query_pointer().coords;
+                (px + x, py + y)
+            }
+        };
+        // The device counts from the top left corner of the desktop, which can be left of or
+        // above the primary display
+        let pos = self.desktop.to_device(pos2(x as f32, y as f32));
+        self.emit(&[(EV_ABS, ABS_X, pos.x as i32), (EV_ABS, ABS_Y, pos.y as i32)])
+    }
+
+    pub fn button(&mut self, button: Button, direction: Direction) -> Result<(), InputError> {
+        let code = match button {
+            Button::Left => BTN_LEFT,
+            Button::Right => BTN_RIGHT,
+            Button::Middle => BTN_MIDDLE,
+            Button::Back => BTN_SIDE,
+            Button::Forward => BTN_EXTRA,
+            _ => return Err(InputError::InvalidInput("Button not supported by uinput")),
+        };
+        match direction {
+            Direction::Press => self.emit(&[(EV_KEY, code, 1)]),
+            Direction::Release => self.emit(&[(EV_KEY, code, 0)]),
+            Direction::Click => {
+                self.emit(&[(EV_KEY, code, 1)])?;
+                self.emit(&[(EV_KEY, code, 0)])
+            }
+        }
+    }
+
+    pub fn scroll(&mut self, amount: i32, axis: Axis) -> Result<(), InputError> {
+        // Wheel events count up for scrolling up, the opposite of enigo, and right, like enigo
+        match axis {
+            Axis::Vertical => self.emit(&[(EV_REL, REL_WHEEL, -amount)]),
+            Axis::Horizontal => self.emit(&[(EV_REL, REL_HWHEEL, amount)]),
+        }
+    }
+
+    // Writes the events followed by a sync, so they take effect together
+    fn emit(&mut self, events: &[(u16, u16, i32)]) -> Result<(), InputError> {
+        let mut buf = Vec::new();
+        for &(kind, code, value) in events.iter().chain(&[(EV_SYN, SYN_REPORT, 0)]) {
+            let event = libc::input_event {
+                time: libc::timeval {
+                    tv_sec: 0,
+                    tv_usec: 0,
+                },
+                type_: kind,
+                code,
+                value,
+            };
+            // SAFETY: `input_event` is plain old data with the kernel's layout
+            buf.extend_from_slice(unsafe {
+                std::slice::from_raw_parts(
+                    &event as *const libc::input_event as *const u8,
+                    std::mem::size_of::<libc::input_event>(),
+                )
+            });
+        }
+        self.file
+            .write_all(&buf)
+            .map_err(|_| InputError::Simulate("Writing to /dev/uinput failed"))
+    }
+}
+
+impl Drop for UinputPointer {
+    fn drop(&mut self) {
+        let _ = setup_device(&self.file, UI_DEV_DESTROY);
+    }
+}
+
+fn setup_device(file: &File, request: libc::c_ulong) -> Result<(), String> {
+    // SAFETY: device create / destroy take no argument
+    match unsafe { libc::ioctl(file.as_raw_fd(), request) } {
+        -1 => Err(format!(
+            "Unable to set up the uinput device: {}",
+            io::Error::last_os_error()
+        )),
+        _ => Ok(()),
+    }
+}
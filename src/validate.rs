@@ -0,0 +1,428 @@
+use std::fmt;
+use std::str::FromStr;
+
+use egui::Key;
+use kmgrid::geometry::GridSize;
+use serde_json::Value;
+
+use crate::action::{self, Action};
+use crate::evdev::{self, DeviceAction};
+use crate::labels;
+use crate::speed::Speed;
+
+/// Something wrong with the config, and where, e.g. `key_bindings.mouse.left_click`
+#[derive(Debug)]
+pub struct Problem {
+    pub path: String,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Problem {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Checks the config, with includes and defaults merged in, for everything that would otherwise
+/// stop kmgrid one panic at a time: unknown key and action names, colors out of range, speeds of
+/// zero and grids without a key for every region or cell
+pub fn check(config: &Value) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    check_bindings(&config["key_bindings"], &mut problems);
+    check_grid(config, &mut problems);
+    check_colors("style", &config["style"], &mut problems);
+    check_displays(config, &mut problems);
+    check_input_devices(&config["input_devices"], &mut problems);
+
+    for name in config["after"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, _)| name)
+    {
+        if !name.parse().is_ok_and(Action::takes_after) {
+            problems.push(Problem::new(
+                format!("after.{name}"),
+                "Needs to be a click action like right_click or middle_click",
+            ));
+        }
+    }
+
+    if config["scroll_speed"]
+        .as_i64()
+        .is_none_or(|speed| speed <= 0)
+    {
+        problems.push(Problem::new(
+            "scroll_speed",
+            format!(
+                "Needs to be a whole number above 0, not {}",
+                config["scroll_speed"]
+            ),
+        ));
+    }
+    if config["scroll_acceleration"]["max"]
+        .as_i64()
+        .is_some_and(|max| max < config["scroll_speed"].as_i64().unwrap_or(1))
+    {
+        problems.push(Problem::new(
+            "scroll_acceleration.max",
+            "Needs to be at least scroll_speed, speeds only grow",
+        ));
+    }
+    if config["page_scroll_amount"]
+        .as_i64()
+        .is_some_and(|amount| amount <= 0)
+    {
+        problems.push(Problem::new(
+            "page_scroll_amount",
+            "Needs to be a whole number above 0",
+        ));
+    }
+    check_speed("movement_speed", &config["movement_speed"], &mut problems);
+    if config["cell_speedup"]["max_factor"]
+        .as_f64()
+        .is_some_and(|factor| factor < 1.0)
+    {
+        problems.push(Problem::new(
+            "cell_speedup.max_factor",
+            "Needs to be at least 1, speeds only grow",
+        ));
+    }
+    if !config["acceleration"].is_null() {
+        for name in ["initial", "max"] {
+            let path = format!("acceleration.{name}");
+            check_speed(&path, &config["acceleration"][name], &mut problems);
+        }
+    }
+
+    problems
+}
+
+fn check_speed(path: &str, speed: &Value, problems: &mut Vec<Problem>) {
+    let movement = match speed {
+        Value::Number(px) => Ok(px.as_f64().unwrap_or(0.0) as f32),
+        Value::String(s) => s.parse().map(|speed| match speed {
+            Speed::PixelsPerFrame(v)
+            | Speed::MillimetersPerSecond(v)
+            | Speed::ScreenPercentPerSecond(v) => v,
+        }),
+        other => Err(format!(
+            "Needs to be a number or a string like \"40mm/s\", not {other}"
+        )),
+    };
+    match movement {
+        Ok(speed) if speed > 0.0 => {}
+        Ok(_) => problems.push(Problem::new(path, "Needs to be above 0")),
+        Err(err) => problems.push(Problem::new(path, err)),
+    }
+}
+
+/// Prints every problem and exits, `source` says which config they're in
+pub fn report(source: &str, problems: &[Problem]) -> ! {
+    print(source, problems);
+    std::process::exit(1)
+}
+
+/// Prints every problem, `source` says which config they're in
+pub fn print(source: &str, problems: &[Problem]) {
+    eprintln!("The config from {source} has problems:");
+    for problem in problems {
+        eprintln!("  {problem}");
+    }
+}
+
+fn check_bindings(bindings: &Value, problems: &mut Vec<Problem>) {
+    let mut keys: Vec<(String, &Value)> = Vec::new();
+    // Keys of actions, which can come with modifiers
+    let mut chords: Vec<(String, &Value)> = Vec::new();
+    for list in ["region", "grid", "micro_grid", "easy_keys", "hint_keys"] {
+        for (i, key) in bindings[list].as_array().into_iter().flatten().enumerate() {
+            keys.push((format!("key_bindings.{list}[{i}]"), key));
+        }
+    }
+    let points = bindings["micro_grid"].as_array().map_or(0, Vec::len);
+    if points != 0 && points != 9 {
+        problems.push(Problem::new(
+            "key_bindings.micro_grid",
+            format!("Needs 9 keys for the 3x3 points, or none, not {points}"),
+        ));
+    }
+    if bindings["hint_keys"]
+        .as_array()
+        .is_some_and(|keys| keys.len() < 2)
+    {
+        problems.push(Problem::new(
+            "key_bindings.hint_keys",
+            "Needs at least two keys to make labels of",
+        ));
+    }
+    for name in ["skip_to_cell", "prev_screen", "next_screen"] {
+        push_keys(
+            format!("key_bindings.{name}"),
+            &bindings[name],
+            &mut chords,
+            problems,
+        );
+    }
+    // Watched while the overlay is closed, where modifiers aren't
+    for name in ["reopen", "reopen_anyway"] {
+        if !bindings[name].is_null() {
+            push_keys(
+                format!("key_bindings.{name}"),
+                &bindings[name],
+                &mut keys,
+                problems,
+            );
+        }
+    }
+    for name in [
+        "scan_select",
+        "gaze_select",
+        "pause",
+        "move_window",
+        "resize_window",
+        "activate_window",
+        "next_profile",
+        "back_to_narrow",
+        "back_to_screen",
+        "narrow_at_pointer",
+        "show_hints",
+        "mirror_layout",
+        "history_back",
+        "history_forward",
+    ] {
+        if !bindings[name].is_null() {
+            push_keys(
+                format!("key_bindings.{name}"),
+                &bindings[name],
+                &mut chords,
+                problems,
+            );
+        }
+    }
+    // Only the multi clicks are optional
+    let mouse = bindings["mouse"].as_object().into_iter().flatten();
+    for (name, key) in mouse.filter(|(_, key)| !key.is_null()) {
+        push_keys(
+            format!("key_bindings.mouse.{name}"),
+            key,
+            &mut chords,
+            problems,
+        );
+    }
+
+    let mut steps: Vec<(String, &Value)> = Vec::new();
+    for (mode, overrides) in bindings["modes"].as_object().into_iter().flatten() {
+        for (action, key) in overrides.as_object().into_iter().flatten() {
+            let path = format!("key_bindings.modes.{mode}.{action}");
+            if let Err(err) = Action::from_str(action) {
+                problems.push(Problem::new(path.clone(), err));
+            }
+            push_keys(path, key, &mut chords, problems);
+        }
+    }
+    for (i, m) in bindings["macros"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        chords.push((format!("key_bindings.macros[{i}].key"), &m["key"]));
+        for (j, step) in m["steps"].as_array().into_iter().flatten().enumerate() {
+            steps.push((format!("key_bindings.macros[{i}].steps[{j}]"), step));
+        }
+    }
+
+    let keys = keys.into_iter().map(|(path, key)| (path, key, false));
+    let chords = chords.into_iter().map(|(path, key)| (path, key, true));
+    for (path, key, chord) in keys.chain(chords) {
+        let name = key.as_str().map(|name| {
+            if chord {
+                action::split_modifiers(name).1
+            } else {
+                name
+            }
+        });
+        match name {
+            Some(name)
+                if Key::from_name(name)
+                    .or_else(|| labels::key_at_position(name))
+                    .is_some() => {}
+            Some(name) => problems.push(Problem::new(path, format!("Unknown key {name:?}"))),
+            None => problems.push(Problem::new(
+                path,
+                format!("Needs to be a key name, not {key}"),
+            )),
+        }
+    }
+    for (path, step) in steps {
+        match step.as_str().map(Action::from_str) {
+            Some(Ok(Action::RunMacro(_))) => {
+                problems.push(Problem::new(path, "Macros can't run other macros"))
+            }
+            Some(Ok(_)) => {}
+            Some(Err(err)) => problems.push(Problem::new(path, err)),
+            None => problems.push(Problem::new(path, "Needs to be an action name")),
+        }
+    }
+}
+
+// An action's key, or each of its keys if it has a list of them
+fn push_keys<'a>(
+    path: String,
+    value: &'a Value,
+    keys: &mut Vec<(String, &'a Value)>,
+    problems: &mut Vec<Problem>,
+) {
+    match value {
+        Value::Array(list) if list.is_empty() => {
+            problems.push(Problem::new(path, "Needs at least one key"))
+        }
+        Value::Array(list) => {
+            for (i, key) in list.iter().enumerate() {
+                keys.push((format!("{path}[{i}]"), key));
+            }
+        }
+        key => keys.push((path, key)),
+    }
+}
+
+fn check_grid(config: &Value, problems: &mut Vec<Problem>) {
+    let default = GridSize::default();
+    for ((cols, rows), [default_cols, default_rows], list, what) in [
+        (
+            ("region_cols", "region_rows"),
+            default.regions,
+            "region",
+            "region",
+        ),
+        (("cell_cols", "cell_rows"), default.cells, "grid", "cell"),
+    ] {
+        let cols = config[cols].as_u64().unwrap_or(default_cols as u64);
+        let rows = config[rows].as_u64().unwrap_or(default_rows as u64);
+        let count = cols * rows;
+        let keys = config["key_bindings"][list].as_array().map_or(0, Vec::len) as u64;
+        if count == 0 {
+            problems.push(Problem::new(
+                format!("{what}_cols, {what}_rows"),
+                format!("The grid needs at least one {what}"),
+            ));
+        } else if keys != count {
+            problems.push(Problem::new(
+                format!("key_bindings.{list}"),
+                format!("{cols}x{rows} {what}s need {count} keys, not {keys}"),
+            ));
+        }
+    }
+}
+
+// Display overrides can't have more regions or cells than there are keys, the grid uses the first
+fn check_displays(config: &Value, problems: &mut Vec<Problem>) {
+    let default = GridSize::default();
+    for (name, display) in config["displays"].as_object().into_iter().flatten() {
+        let path = format!("displays.{name}");
+        for offset in ["offset_x", "offset_y"] {
+            if !display[offset].is_null() && display[offset].as_i64().is_none() {
+                problems.push(Problem::new(
+                    format!("{path}.{offset}"),
+                    format!("Needs to be a whole number, not {}", display[offset]),
+                ));
+            }
+        }
+        for ((cols, rows), [default_cols, default_rows], list, what) in [
+            (
+                ("region_cols", "region_rows"),
+                default.regions,
+                "region",
+                "region",
+            ),
+            (("cell_cols", "cell_rows"), default.cells, "grid", "cell"),
+        ] {
+            let size = |name: &str, default: usize| {
+                display[name]
+                    .as_u64()
+                    .or_else(|| config[name].as_u64())
+                    .unwrap_or(default as u64)
+            };
+            let cols = size(cols, default_cols);
+            let rows = size(rows, default_rows);
+            let count = cols * rows;
+            let keys = config["key_bindings"][list].as_array().map_or(0, Vec::len) as u64;
+            if count == 0 {
+                problems.push(Problem::new(
+                    format!("{path}.{what}_cols, {path}.{what}_rows"),
+                    format!("The grid needs at least one {what}"),
+                ));
+            } else if count > keys {
+                problems.push(Problem::new(
+                    format!("{path}.{what}_cols, {path}.{what}_rows"),
+                    format!(
+                        "{cols}x{rows} {what}s need {count} keys, key_bindings.{list} has {keys}"
+                    ),
+                ));
+            }
+        }
+        check_colors(&format!("{path}.style"), &display["style"], problems);
+    }
+}
+
+fn check_input_devices(devices: &Value, problems: &mut Vec<Problem>) {
+    for (i, device) in devices.as_array().into_iter().flatten().enumerate() {
+        let path = format!("input_devices[{i}]");
+        if !device["path"].is_string() {
+            problems.push(Problem::new(
+                format!("{path}.path"),
+                "Needs the device, like \"/dev/input/by-id/usb-...-event-kbd\"",
+            ));
+        }
+        for (code, action) in device["bindings"].as_object().into_iter().flatten() {
+            let path = format!("{path}.bindings.{code}");
+            if evdev::parse_code(code).is_none() {
+                problems.push(Problem::new(
+                    &path,
+                    "Unknown button, use a name like BTN_0 or KEY_F13 or its number",
+                ));
+            }
+            match action.as_str().map(str::parse::<DeviceAction>) {
+                Some(Ok(_)) => {}
+                Some(Err(err)) => problems.push(Problem::new(&path, err)),
+                None => problems.push(Problem::new(
+                    &path,
+                    format!("Needs an action name or \"reopen\", not {action}"),
+                )),
+            }
+        }
+    }
+}
+
+fn check_colors(path: &str, style: &Value, problems: &mut Vec<Problem>) {
+    for (name, value) in style.as_object().into_iter().flatten() {
+        // Colors are the lists, the rest are sizes
+        let Value::Array(parts) = value else {
+            continue;
+        };
+        let valid = parts.len() == 4
+            && parts
+                .iter()
+                .all(|part| part.as_u64().is_some_and(|part| part <= 255));
+        if !valid {
+            problems.push(Problem::new(
+                format!("{path}.{name}"),
+                format!("Colors are [red, green, blue, alpha] from 0 to 255, not {value}"),
+            ));
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_ulong};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use raw_window_handle::{RawWindowHandle, XlibWindowHandle};
+use x11::xlib;
+
+/// `WM_CLASS` of a window: the instance name and the class name, e.g. `("navigator", "firefox")`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowClass {
+    pub name: String,
+    pub class: String,
+}
+
+impl WindowClass {
+    /// Case-insensitive match on either part, like window manager rules usually do
+    pub fn matches(&self, pattern: &str) -> bool {
+        self.name.eq_ignore_ascii_case(pattern) || self.class.eq_ignore_ascii_case(pattern)
+    }
+}
+
+/// Class of the window that has focus according to the window manager (`_NET_ACTIVE_WINDOW`).
+/// Has to be asked before the overlay opens, which takes focus itself.
+pub fn active_window_class() -> Option<WindowClass> {
+    // SAFETY: the display and everything Xlib allocates are freed before leaving the block
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return None;
+        }
+
+        let class = active_window(display).and_then(|window| {
+            let mut hint: xlib::XClassHint = std::mem::zeroed();
+            // The window may have closed since it was the active one
+            let found =
+                without_x_errors(display, || xlib::XGetClassHint(display, window, &mut hint));
+            if found.unwrap_or(0) == 0 {
+                return None;
+            }
+            let class = WindowClass {
+                name: take_string(hint.res_name),
+                class: take_string(hint.res_class),
+            };
+            Some(class)
+        });
+
+        xlib::XCloseDisplay(display);
+        class
+    }
+}
+
+/// The window that has focus according to the window manager, asked before the overlay opens
+pub fn active_window_id() -> Option<xlib::Window> {
+    // SAFETY: the display is only used within this block and closed before leaving it
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return None;
+        }
+        let window = active_window(display);
+        xlib::XCloseDisplay(display);
+        window
+    }
+}
+
+/// Whether the window that has focus is fullscreen (`_NET_WM_STATE_FULLSCREEN`), like a
+/// presentation or a video
+pub fn active_window_fullscreen() -> bool {
+    // SAFETY: the display and everything Xlib allocates are freed before leaving the block
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return false;
+        }
+
+        let fullscreen = active_window(display).is_some_and(|window| {
+            let name = CString::new("_NET_WM_STATE_FULLSCREEN").unwrap();
+            let fullscreen = xlib::XInternAtom(display, name.as_ptr(), xlib::True);
+            fullscreen != 0 && window_atoms(display, window, "_NET_WM_STATE").contains(&fullscreen)
+        });
+
+        xlib::XCloseDisplay(display);
+        fullscreen
+    }
+}
+
+// Set by `ignore_x_error` while `without_x_errors` runs
+static X_ERROR: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn ignore_x_error(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
+    X_ERROR.store(true, Ordering::Relaxed);
+    0
+}
+
+// Runs `f` with X errors like BadWindow, for a window that has gone away, noted rather than ending
+// the process as Xlib's default handler does. `None` if there were any.
+unsafe fn without_x_errors<T>(display: *mut xlib::Display, f: impl FnOnce() -> T) -> Option<T> {
+    // Errors of earlier requests still go to the handler that was there
+    xlib::XSync(display, xlib::False);
+    X_ERROR.store(false, Ordering::Relaxed);
+    let previous = xlib::XSetErrorHandler(Some(ignore_x_error));
+    let value = f();
+    xlib::XSync(display, xlib::False);
+    xlib::XSetErrorHandler(previous);
+    (!X_ERROR.load(Ordering::Relaxed)).then_some(value)
+}
+
+unsafe fn active_window(display: *mut xlib::Display) -> Option<xlib::Window> {
+    let root = xlib::XDefaultRootWindow(display);
+    window_property(display, root, "_NET_ACTIVE_WINDOW", xlib::XA_WINDOW).filter(|w| *w != 0)
+}
+
+// First item of a 32 bit format property of `window`, `None` when it isn't set. `kind` of
+// `AnyPropertyType` takes whatever type the property has.
+unsafe fn window_property(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    name: &str,
+    kind: xlib::Atom,
+) -> Option<c_ulong> {
+    let name = CString::new(name).unwrap();
+    let atom = xlib::XInternAtom(display, name.as_ptr(), xlib::True);
+    if atom == 0 {
+        return None;
+    }
+
+    let mut actual_type = 0;
+    let mut actual_format = 0;
+    let mut items = 0;
+    let mut bytes_after = 0;
+    let mut data: *mut u8 = ptr::null_mut();
+    let status = xlib::XGetWindowProperty(
+        display,
+        window,
+        atom,
+        0,
+        1,
+        xlib::False,
+        kind,
+        &mut actual_type,
+        &mut actual_format,
+        &mut items,
+        &mut bytes_after,
+        &mut data,
+    );
+    if status != xlib::Success as i32 || data.is_null() {
+        return None;
+    }
+
+    // 32 bit format properties come back as an array of longs
+    let value = (items >= 1 && actual_format == 32).then(|| *(data as *const c_ulong));
+    xlib::XFree(data as *mut _);
+    value
+}
+
+// Every item of an atom list property of `window`, like `_NET_WM_STATE`
+unsafe fn window_atoms(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    name: &str,
+) -> Vec<xlib::Atom> {
+    let name = CString::new(name).unwrap();
+    let atom = xlib::XInternAtom(display, name.as_ptr(), xlib::True);
+    if atom == 0 {
+        return Vec::new();
+    }
+
+    let mut actual_type = 0;
+    let mut actual_format = 0;
+    let mut items = 0;
+    let mut bytes_after = 0;
+    let mut data: *mut u8 = ptr::null_mut();
+    // Far more than any window manager sets
+    const MAX_ITEMS: i64 = 64;
+    let status = xlib::XGetWindowProperty(
+        display,
+        window,
+        atom,
+        0,
+        MAX_ITEMS,
+        xlib::False,
+        xlib::XA_ATOM,
+        &mut actual_type,
+        &mut actual_format,
+        &mut items,
+        &mut bytes_after,
+        &mut data,
+    );
+    if status != xlib::Success as i32 || data.is_null() {
+        return Vec::new();
+    }
+
+    let atoms = if actual_format == 32 {
+        std::slice::from_raw_parts(data as *const xlib::Atom, items as usize).to_vec()
+    } else {
+        Vec::new()
+    };
+    xlib::XFree(data as *mut _);
+    atoms
+}
+
+unsafe fn take_string(s: *mut c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    let owned = CStr::from_ptr(s).to_string_lossy().into_owned();
+    xlib::XFree(s as *mut _);
+    owned
+}
+
+/// Asks the window manager to show `window` on every workspace (`_NET_WM_STATE_STICKY`, and
+/// `_NET_WM_DESKTOP` for window managers that only go by that one)
+pub fn make_sticky(window: xlib::Window) {
+    // SAFETY: the display is only used within this block and closed before leaving it
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return;
+        }
+
+        let atom = |name: &str| {
+            let name = CString::new(name).unwrap();
+            xlib::XInternAtom(display, name.as_ptr(), xlib::False)
+        };
+        const NET_WM_STATE_ADD: i64 = 1;
+        const ALL_DESKTOPS: i64 = 0xFFFF_FFFF;
+        let messages = [
+            (
+                atom("_NET_WM_STATE"),
+                [
+                    NET_WM_STATE_ADD,
+                    atom("_NET_WM_STATE_STICKY") as i64,
+                    0,
+                    1,
+                    0,
+                ],
+            ),
+            (atom("_NET_WM_DESKTOP"), [ALL_DESKTOPS, 1, 0, 0, 0]),
+        ];
+
+        for (message_type, data) in messages {
+            send_to_window_manager(display, window, message_type, data);
+        }
+
+        xlib::XCloseDisplay(display);
+    }
+}
+
+/// The top-level windows of this process, for the overlay windows eframe has no handles for: the
+/// ones of displays besides the root viewport's
+pub fn own_windows() -> Vec<RawWindowHandle> {
+    // SAFETY: the display and everything Xlib allocates are freed before leaving the block
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return Vec::new();
+        }
+
+        let own_pid = std::process::id() as c_ulong;
+        let root = xlib::XDefaultRootWindow(display);
+        // Windows can go away while going through them. Window managers that draw frames put the
+        // client window one level down.
+        let children_of = |window| without_x_errors(display, || children(display, window));
+        let windows = children_of(root)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|top| {
+                [top]
+                    .into_iter()
+                    .chain(children_of(top).unwrap_or_default())
+            })
+            .filter(|&window| {
+                without_x_errors(display, || {
+                    window_property(display, window, "_NET_WM_PID", xlib::XA_CARDINAL)
+                }) == Some(Some(own_pid))
+            })
+            .map(|window| RawWindowHandle::Xlib(XlibWindowHandle::new(window)))
+            .collect();
+
+        xlib::XCloseDisplay(display);
+        windows
+    }
+}
+
+/// A connection to the X server kept for a series of requests, like moving a window every frame
+/// while a movement key is held
+pub struct XConnection(*mut xlib::Display);
+
+impl XConnection {
+    pub fn open() -> Option<Self> {
+        // SAFETY: a null name connects to `$DISPLAY`, a null result is checked
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        (!display.is_null()).then_some(XConnection(display))
+    }
+
+    /// For Xlib calls of other modules, valid for as long as `self` lives
+    pub fn display(&self) -> *mut xlib::Display {
+        self.0
+    }
+
+    /// Position and size of the contents of `window` on the screen, `(x, y, width, height)`,
+    /// leaving out the frame the window manager draws around it. `None` once it has closed.
+    pub fn window_geometry(&self, window: xlib::Window) -> Option<(i32, i32, u32, u32)> {
+        let display = self.0;
+        // SAFETY: the display is open for as long as `self` lives
+        unsafe {
+            without_x_errors(display, || {
+                let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+                if xlib::XGetWindowAttributes(display, window, &mut attributes) == 0 {
+                    return None;
+                }
+                let (mut x, mut y) = (0, 0);
+                let mut child = 0;
+                let found = xlib::XTranslateCoordinates(
+                    display,
+                    window,
+                    xlib::XDefaultRootWindow(display),
+                    0,
+                    0,
+                    &mut x,
+                    &mut y,
+                    &mut child,
+                );
+                let size = (attributes.width as u32, attributes.height as u32);
+                (found != 0).then_some((x, y, size.0, size.1))
+            })
+            .flatten()
+        }
+    }
+
+    /// Asks the window manager to move and resize the contents of `window` to `x`,`y` and
+    /// `width`x`height` (`_NET_MOVERESIZE_WINDOW`), which also works with frames and window
+    /// managers that ignore `XMoveResizeWindow` from other clients
+    pub fn move_resize_window(
+        &self,
+        window: xlib::Window,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) {
+        let display = self.0;
+        // SAFETY: the display is open for as long as `self` lives
+        unsafe {
+            let name = CString::new("_NET_MOVERESIZE_WINDOW").unwrap();
+            let message_type = xlib::XInternAtom(display, name.as_ptr(), xlib::False);
+            // Static gravity puts the contents rather than the frame at x,y; x, y, width and
+            // height are all set; the request comes from a pager-like tool, which window managers
+            // don't second-guess
+            const FLAGS: i64 = xlib::StaticGravity as i64 | 0b1111 << 8 | 2 << 12;
+            send_to_window_manager(
+                display,
+                window,
+                message_type,
+                [FLAGS, x as i64, y as i64, width as i64, height as i64],
+            );
+            xlib::XFlush(display);
+        }
+    }
+}
+
+impl Drop for XConnection {
+    fn drop(&mut self) {
+        // SAFETY: opened in `XConnection::open` and not used after this
+        unsafe {
+            xlib::XCloseDisplay(self.0);
+        }
+    }
+}
+
+/// Asks the window manager to focus and raise the topmost window under the pointer
+/// (`_NET_ACTIVE_WINDOW`), passing over the overlay's own windows. Returns whether there was one.
+pub fn activate_window_under_pointer() -> bool {
+    // SAFETY: the display and everything Xlib allocates are freed before leaving the block
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return false;
+        }
+
+        let window = window_under_pointer(display);
+        if let Some(window) = window {
+            let name = CString::new("_NET_ACTIVE_WINDOW").unwrap();
+            let message_type = xlib::XInternAtom(display, name.as_ptr(), xlib::False);
+            // The request comes from a pager-like tool, which window managers don't second-guess
+            // or turn into a demand for attention
+            const SOURCE_PAGER: i64 = 2;
+            send_to_window_manager(
+                display,
+                window,
+                message_type,
+                [SOURCE_PAGER, xlib::CurrentTime as i64, 0, 0, 0],
+            );
+        }
+
+        xlib::XCloseDisplay(display);
+        window.is_some()
+    }
+}
+
+// The client window, the one the window manager knows by `WM_STATE`, of the topmost top-level
+// window under the pointer that doesn't belong to this process
+unsafe fn window_under_pointer(display: *mut xlib::Display) -> Option<xlib::Window> {
+    let root = xlib::XDefaultRootWindow(display);
+    let (mut x, mut y) = (0, 0);
+    let (mut unused_x, mut unused_y, mut unused_mask) = (0, 0, 0);
+    let (mut unused_root, mut unused_child) = (0, 0);
+    if xlib::XQueryPointer(
+        display,
+        root,
+        &mut unused_root,
+        &mut unused_child,
+        &mut x,
+        &mut y,
+        &mut unused_x,
+        &mut unused_y,
+        &mut unused_mask,
+    ) == 0
+    {
+        return None;
+    }
+
+    let own_pid = std::process::id() as c_ulong;
+    // Top-level windows come bottom to top. Any of them can go away while going through them,
+    // those are skipped.
+    let tops = without_x_errors(display, || children(display, root))?;
+    tops.into_iter().rev().find_map(|top| {
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        let found = without_x_errors(display, || {
+            xlib::XGetWindowAttributes(display, top, &mut attributes)
+        });
+        if found.is_none_or(|status| status == 0) || attributes.map_state != xlib::IsViewable {
+            return None;
+        }
+        let border = attributes.border_width * 2;
+        let inside = (attributes.x..attributes.x + attributes.width + border).contains(&x)
+            && (attributes.y..attributes.y + attributes.height + border).contains(&y);
+        if !inside {
+            return None;
+        }
+        let client = client_window(display, top)?;
+        let pid = without_x_errors(display, || {
+            window_property(display, client, "_NET_WM_PID", xlib::XA_CARDINAL)
+        })?;
+        (pid != Some(own_pid)).then_some(client)
+    })
+}
+
+// `window` itself or the first window below it with `WM_STATE`, window managers that draw frames
+// put the client window inside one. Windows that go away meanwhile are skipped.
+unsafe fn client_window(display: *mut xlib::Display, window: xlib::Window) -> Option<xlib::Window> {
+    let mut queue = std::collections::VecDeque::from([window]);
+    while let Some(window) = queue.pop_front() {
+        let state = without_x_errors(display, || {
+            window_property(
+                display,
+                window,
+                "WM_STATE",
+                xlib::AnyPropertyType as xlib::Atom,
+            )
+        });
+        match state {
+            None => continue,
+            Some(Some(_)) => return Some(window),
+            Some(None) => {}
+        }
+        queue.extend(without_x_errors(display, || children(display, window)).unwrap_or_default());
+    }
+    None
+}
+
+unsafe fn children(display: *mut xlib::Display, window: xlib::Window) -> Vec<xlib::Window> {
+    let (mut unused_root, mut unused_parent) = (0, 0);
+    let mut children: *mut xlib::Window = ptr::null_mut();
+    let mut count = 0;
+    if xlib::XQueryTree(
+        display,
+        window,
+        &mut unused_root,
+        &mut unused_parent,
+        &mut children,
+        &mut count,
+    ) == 0
+        || children.is_null()
+    {
+        return Vec::new();
+    }
+    let list = std::slice::from_raw_parts(children, count as usize).to_vec();
+    xlib::XFree(children as *mut _);
+    list
+}
+
+// EWMH requests about `window` go to the root window as client messages
+unsafe fn send_to_window_manager(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    message_type: xlib::Atom,
+    data: [i64; 5],
+) {
+    let mut event = xlib::XClientMessageEvent {
+        type_: xlib::ClientMessage,
+        serial: 0,
+        send_event: xlib::True,
+        display,
+        window,
+        message_type,
+        format: 32,
+        data: xlib::ClientMessageData::new(),
+    };
+    for (i, value) in data.into_iter().enumerate() {
+        event.data.set_long(i, value);
+    }
+    let mut event = xlib::XEvent {
+        client_message: event,
+    };
+    xlib::XSendEvent(
+        display,
+        xlib::XDefaultRootWindow(display),
+        xlib::False,
+        xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+        &mut event,
+    );
+}
+
+#[cfg(windows)]
+#[link(name = "user32")]
+extern "system" {
+    fn SetWindowDisplayAffinity(hwnd: isize, affinity: u32) -> i32;
+}
+
+#[cfg(windows)]
+const WDA_EXCLUDEFROMCAPTURE: u32 = 0x11;
+
+/// Asks the system to leave `window` out of screenshots, recordings and screen sharing, returning
+/// whether it did. Only Windows offers that to applications (`WDA_EXCLUDEFROMCAPTURE`). X11 has no
+/// such thing, and Wayland compositors that can do it (e.g. KWin) keep it to their own window
+/// rules.
+pub fn exclude_from_capture(window: RawWindowHandle) -> bool {
+    match window {
+        #[cfg(windows)]
+        // SAFETY: the handle belongs to a window that's alive while eframe runs the app
+        RawWindowHandle::Win32(handle) => unsafe {
+            SetWindowDisplayAffinity(handle.hwnd.get(), WDA_EXCLUDEFROMCAPTURE) != 0
+        },
+        _ => false,
+    }
+}